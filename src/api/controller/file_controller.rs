@@ -1,27 +1,55 @@
 
-use axum::{Json, body::Body, extract::State, http::{Response, StatusCode, header}, response::IntoResponse
+use axum::{Json, body::Body, extract::{Query, State}, http::{Response, StatusCode, header}, response::IntoResponse
 };
 
-use crate::{api::{middleware::auth::AuthenticatedUser, model::dto::file_request::{DownloadRequest, UploadCompleted}, state::AppState}, errors::app_error::AppError};
+use crate::{api::{middleware::auth::AuthenticatedUser, model::dto::file_request::{ArchiveUploadCompleted, DownloadRequest, ExportKind, ExportRequest, ProfileRequest, UploadCompleted, UploadRequest, ValidationRequest}, service::jwt_service::get_user_uuid_from_claims, state::AppState}, errors::app_error::AppError, internal::io::track_format::TrackFormat};
 
 
 /*
-    API endpoint for saving request body to file on server
+    API endpoint for saving request body to file on server, the track format is detected from the content.
+    When the format query parameter is given the content has to match it, gzipped tracks may decompress within the limit of the user tier
 */
 pub async fn save_to_temp(
-    AuthenticatedUser(_): AuthenticatedUser,
+    AuthenticatedUser(user): AuthenticatedUser,
     State(state) : State<AppState>,
+    Query(params) : Query<UploadRequest>,
     request : Body
-) -> impl IntoResponse {
-    let mut stream =request.into_data_stream(); 
-    let result = state.get_file_service().save_to_temp(&mut stream)
-    .await
-    .map_err( |err| {return err.into_response();});
-    if let Err(response) = result { 
-        return response;
-    }else {
-        (StatusCode::CREATED, axum::Json::from(UploadCompleted{ file_name : result.unwrap()})).into_response()
-    }
+) -> Result<impl IntoResponse, AppError> {
+    let user_uuid = get_user_uuid_from_claims(user)
+    .map_err(|err| {
+        AppError::service_error(err)
+    })?;
+    let user = state.get_user_service().get_user_by_uuid(&user_uuid).await?;
+    let max_tracks = user.get_tier().max_tracks.max(0) as usize;
+
+    let mut stream = request.into_data_stream();
+    let file_name = state.get_file_service().save_to_temp(&mut stream, params.format, max_tracks).await?;
+    Ok((StatusCode::CREATED, axum::Json::from(UploadCompleted{ file_name })))
+}
+
+/*
+    API endpoint for saving every track of a gzip or zip archive (request body) on server, each track is validated and saved on its own.
+    The allowed archive size and number of tracks depend on the tier of the user.
+    Responds with a report of every file of the archive, CREATED if at least one track was saved
+*/
+pub async fn save_archive_to_temp(
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(state) : State<AppState>,
+    request : Body
+) -> Result<impl IntoResponse, AppError> {
+    let user_uuid = get_user_uuid_from_claims(user)
+    .map_err(|err| {
+        AppError::service_error(err)
+    })?;
+    let user = state.get_user_service().get_user_by_uuid(&user_uuid).await?;
+    let max_tracks = user.get_tier().max_tracks.max(0) as usize;
+
+    let mut stream = request.into_data_stream();
+    let files = state.get_file_service().save_archive_to_temp(&mut stream, max_tracks).await?;
+
+    let saved = files.iter().filter(|file| file.file_name.is_some()).count();
+    let status = if saved > 0 { StatusCode::CREATED } else { StatusCode::UNPROCESSABLE_ENTITY };
+    Ok((status, Json::from(ArchiveUploadCompleted { saved, failed : files.len() - saved, files })))
 }
 
 /*
@@ -32,15 +60,73 @@ pub async fn download_from_temp(
     State(state) : State<AppState>,
     Json(payload): Json<DownloadRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    let stream = state.get_file_service().download_from_temp(&payload.path).await?;
+    let format = payload.format.unwrap_or(TrackFormat::Gpx);
+    let stream = state.get_file_service().download_from_temp(&payload.path, format).await?;
 
     Ok(Response::builder()
-            .header(header::CONTENT_TYPE, "application/gpx; charset=utf-8")
-            .header(header::CONTENT_DISPOSITION, "attachment; filename=\"track.txt\"") // TODO : if track is saved give back actual name
+            .header(header::CONTENT_TYPE, format.mime_type())
+            .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"track.{}\"", format.extension())) // TODO : if track is saved give back actual name
             .status(StatusCode::OK)
             .body(Body::from_stream(stream)).map_err(|err| {
                 tracing::warn!("Could not build body from stream: {}", err.to_string());
                 return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     ))
+}
+
+/*
+    API endpoint for exporting an uploaded track as GPX, either as recorded or as the reference track built from it
+*/
+pub async fn export_track(
+    AuthenticatedUser(_): AuthenticatedUser,
+    State(state) : State<AppState>,
+    Json(payload): Json<ExportRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let format = payload.format.unwrap_or(TrackFormat::Gpx);
+    let kind = payload.kind.unwrap_or_default();
+    let gpx = state.get_file_service().export_gpx(&payload.path, format, matches!(kind, ExportKind::Reference)).await?;
+
+    let file_name = match kind {
+        ExportKind::Track => "track",
+        ExportKind::Reference => "reference"
+    };
+
+    Ok(Response::builder()
+            .header(header::CONTENT_TYPE, TrackFormat::Gpx.mime_type())
+            .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.{}\"", file_name, TrackFormat::Gpx.extension()))
+            .status(StatusCode::OK)
+            .body(Body::from(gpx)).map_err(|err| {
+                tracing::warn!("Could not build body from export: {}", err.to_string());
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    ))
+}
+
+/*
+    API endpoint for validating an uploaded track before analysis, responds with the validation report
+    (point count, time span, bounding box and every issue found)
+*/
+pub async fn validate_track(
+    AuthenticatedUser(_): AuthenticatedUser,
+    State(state) : State<AppState>,
+    Json(payload): Json<ValidationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let format = payload.format.unwrap_or(TrackFormat::Gpx);
+    let report = state.get_file_service().validate_track(&payload.path, format, payload.max_speed).await?;
+    Ok(Json::from(report))
+}
+
+
+/*
+    API endpoint for the climbing statistics of an uploaded track (ascent, descent, gradients) and its gradient profile along the course
+*/
+pub async fn profile_track(
+    AuthenticatedUser(_): AuthenticatedUser,
+    State(state) : State<AppState>,
+    Json(payload): Json<ProfileRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let format = payload.format.unwrap_or(TrackFormat::Gpx);
+    let reference = payload.reference.as_deref().map(|reference| (reference, payload.reference_format.unwrap_or(TrackFormat::Gpx)));
+    let profile = state.get_file_service().profile_track(&payload.path, format, reference, payload.bin_length).await?;
+    Ok(Json::from(profile))
 }
\ No newline at end of file
@@ -1,11 +1,74 @@
 use serde::{Deserialize, Serialize};
 
+use crate::internal::io::track_format::TrackFormat;
+
 #[derive(Deserialize)]
 pub struct DownloadRequest {
     pub path: String,
+    pub format: Option<TrackFormat>
+}
+
+/*
+    What an uploaded track is exported as, the recorded track as is or the reference (official course) built from it
+*/
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportKind {
+    #[default]
+    Track,
+    Reference
+}
+
+#[derive(Deserialize)]
+pub struct ExportRequest {
+    pub path: String,
+    pub format: Option<TrackFormat>,    // Format of the uploaded file
+    pub kind: Option<ExportKind>
+}
+
+#[derive(Deserialize)]
+pub struct ValidationRequest {
+    pub path: String,
+    pub format: Option<TrackFormat>,    // Format of the uploaded file
+    pub max_speed: Option<f64>          // Fastest plausible speed in m/s, depends on the sport
+}
+
+/*
+    Elevation profile of an uploaded track, when reference is given the track is a rider snapped on that uploaded reference
+    and profiled along it, otherwise the track is profiled as a reference
+*/
+#[derive(Deserialize)]
+pub struct ProfileRequest {
+    pub path: String,
+    pub format: Option<TrackFormat>,            // Format of the uploaded file
+    pub reference: Option<String>,
+    pub reference_format: Option<TrackFormat>,
+    pub bin_length: Option<f32>                 // Meters of course per bin of the gradient profile, at least 10
+}
+
+#[derive(Deserialize)]
+pub struct UploadRequest {
+    pub format: Option<TrackFormat>     // Expected format, detected from the content when missing
 }
 
 #[derive(Serialize)]
 pub struct UploadCompleted {
     pub file_name : String,
-}
\ No newline at end of file
+}
+/*
+    Outcome of a single file of an uploaded archive, file_name is set when the track was saved, error otherwise
+*/
+#[derive(Serialize)]
+pub struct ArchiveFileReport {
+    pub entry : String,                     // Path of the file inside the archive
+    pub file_name : Option<String>,
+    pub format : Option<TrackFormat>,
+    pub error : Option<String>
+}
+
+#[derive(Serialize)]
+pub struct ArchiveUploadCompleted {
+    pub saved : usize,
+    pub failed : usize,
+    pub files : Vec<ArchiveFileReport>
+}
@@ -2,9 +2,11 @@
 use axum::{Router, routing::{get, post}};
 use tower_cookies::CookieManagerLayer;
 use tower_http::limit::RequestBodyLimitLayer;
-use crate::api::{controller::{ auth_controller::{google_callback, google_login}, event_controller::{add_event_for_user, delete_event_for_user, get_events_for_user}, file_controller::{download_from_temp, save_to_temp}, generic::{health, landing}, tier_controller::{ get_tier_info}, token_controller::{logout_all, refresh_token, revoke_token}, user_controller::{delete_user, get_me, get_user, update_user}}, state::AppState};
+use crate::api::{controller::{ auth_controller::{google_callback, google_login}, event_controller::{add_event_for_user, delete_event_for_user, get_events_for_user}, file_controller::{download_from_temp, export_track, profile_track, save_archive_to_temp, save_to_temp, validate_track}, generic::{health, landing}, tier_controller::{ get_tier_info}, token_controller::{logout_all, refresh_token, revoke_token}, user_controller::{delete_user, get_me, get_user, update_user}}, state::AppState};
 
 const FILE_SIZE_LIMIT : usize = 1024;
+// Hard cap of archive uploads, the actual limit depends on the tier of the user (look at FileService::save_archive_to_temp)
+const ARCHIVE_SIZE_LIMIT : usize = 256 * FILE_SIZE_LIMIT * FILE_SIZE_LIMIT;
 
 /*
     Creates the main app router using the @state
@@ -13,10 +15,10 @@ pub fn build_router(state : AppState) -> Router {
     Router::new()
     .route("/", get(landing()))
     .route("/health", get(health))
-    .nest("/api", api_router())
     .nest("/auth", auth_router())
-    .layer(CookieManagerLayer::new())
     .layer(RequestBodyLimitLayer::new(FILE_SIZE_LIMIT * FILE_SIZE_LIMIT))// 1MB
+    .nest("/api", api_router())
+    .layer(CookieManagerLayer::new())
     .with_state(state)
 }
 
@@ -26,9 +28,15 @@ pub fn build_router(state : AppState) -> Router {
 fn api_router() -> Router<AppState> {
     Router::new()
     .route("/track/",  post(save_to_temp).get(download_from_temp))
+    .route("/track/export", get(export_track))
+    .route("/track/validate", get(validate_track))
+    .route("/track/profile", get(profile_track))
     .route("/tier/", get(get_tier_info)) 
     .route("/user/", get(get_user).put(update_user).delete(delete_user))
     .route("/event", post(add_event_for_user).delete(delete_event_for_user).get(get_events_for_user))
+    .layer(RequestBodyLimitLayer::new(FILE_SIZE_LIMIT * FILE_SIZE_LIMIT))// 1MB
+    // Added after the 1MB limit so archives only get their own limit
+    .route("/track/archive", post(save_archive_to_temp).layer(RequestBodyLimitLayer::new(ARCHIVE_SIZE_LIMIT)))
 }
 
 fn auth_router() -> Router<AppState> {
@@ -1,14 +1,20 @@
-use std::path::PathBuf;
+use std::{convert::Infallible, path::PathBuf};
 
-use axum::body::BodyDataStream;
-use tokio::fs::File;
-use tokio_util::io::ReaderStream;
+use axum::body::{BodyDataStream, Bytes};
+use futures_util::TryStreamExt;
+use tokio::{fs::File, io::AsyncReadExt};
+use tokio_util::io::{ReaderStream, StreamReader};
 use uuid::Uuid;
 
-use crate::{api::repository::file_repository::FileRepository, errors::{app_error::AppError, io_errors::IOError}};
+use crate::{api::{model::dto::file_request::ArchiveFileReport, repository::file_repository::FileRepository}, errors::{app_error::AppError, io_errors::IOError, service_errors::ServiceError}, internal::{io::{archive_reader::{self, ArchiveFormat, ArchiveLimits}, track_format::TrackFormat, track_loader}, model::{analysis::{self, ElevationProfile}, config::{gaps::{GapConfig, GapPolicy}, gradient::GradientConfig, outlier::{OutlierConfig, OutlierRepair}, snapping::SnappingConfig, validation::ValidationConfig}, spatial::grid::Grid, track::reference::ReferenceTrack, validation::ValidationReport}, service::{geo_conversions, track_export, track_processor, track_validation}}};
 
 
 
+// A validated upload with its detected format, or why it was refused
+type ExtractedTrack = Result<(TrackFormat, Vec<u8>), IOError>;
+// Every file of an archive with its extraction result, Err if the archive itself could not be read
+type ExtractedArchive = Result<Vec<(String, ExtractedTrack)>, IOError>;
+
 #[derive(Clone)]
 pub struct FileService {
     file_repo : FileRepository
@@ -21,6 +27,33 @@ impl FileService {
     const UPLOADS_TEMP_DIRECTORY : &str = "uploads/temp";
     const UPLOADS_USERS_DIRECTORY : &str = "uploads/users";
 
+    // Body size allowed per track of the user tier for archive uploads
+    const ARCHIVE_BYTES_PER_TRACK : usize = 1024 * 1024;
+    // Size of a single track once decompressed, guards against zip bombs
+    const EXTRACTED_TRACK_SIZE_LIMIT : usize = 64 * 1024 * 1024;
+    // Decompressed size allowed per track of the user tier, for a whole archive or a single gzipped track
+    const EXTRACTED_BYTES_PER_TRACK : usize = 16 * 1024 * 1024;
+
+    // Validation thresholds, the speed limit can be given per request
+    const VALIDATION_MAX_SPEED : f64 = 70.0;
+    const VALIDATION_MAX_TIME_GAP : f64 = 60.0;
+    const VALIDATION_MAX_DISTANCE_GAP : f64 = 500.0;
+    const VALIDATION_DUPLICATE_DISTANCE : f64 = 0.01;
+    const VALIDATION_REPORTED_POINTS : usize = 20;
+
+    // Elevation profile settings, the bin length can be given per request
+    const PROFILE_ASCENT_THRESHOLD : f32 = 5.0;
+    const PROFILE_BIN_LENGTH : f32 = 500.0;
+    const PROFILE_GRADIENT_DISTANCE : f32 = 100.0;
+    const PROFILE_GRID_CELL_SIZE : f32 = 50.0;
+    const PROFILE_CONTINUITY_CLAMP : u32 = 50;
+    // Outlier filter of the riders whose sport has no preset (look at OutlierConfig::for_sport), the speed limit is the validation one
+    const PROFILE_OUTLIER_MAX_ACCELERATION : f32 = 12.0;
+    const PROFILE_OUTLIER_JITTER_DISTANCE : f32 = 5.0;
+    const PROFILE_OUTLIER_MAX_RUN : usize = 5;
+    // Gaps are only reported in profiles, never filled
+    const PROFILE_GAP_FILL_INTERVAL : f64 = 0.0;
+
     pub fn new() -> Self {
         FileService { file_repo : FileRepository::new()}
     }
@@ -86,31 +119,292 @@ impl FileService {
     }
 
 
-    pub async fn save_to_temp(&self, stream : &mut BodyDataStream) -> Result<String, AppError> {
-        let temp_file_name = format!("{}.gpx", Uuid::new_v4());
+    /*
+        Saves the track read from @stream in the temp uploads, the track is parsed before being written so invalid uploads are never stored.
+        The body is buffered in memory (bounded by the body limit of the route) and parsed from the buffer, it is not parsed while streaming.
+        Gzipped tracks are decompressed first within the extracted size of the @max_tracks allowed by the tier of the user.
+        The format is detected from the content, when a @format is declared the content has to match it.
+        Returns the name of the saved file
+    */
+    pub async fn save_to_temp(&self, stream : &mut BodyDataStream, format : Option<TrackFormat>, max_tracks : usize) -> Result<String, AppError> {
+        let mut body = Self::read_body(stream, None).await?;
+
+        let (detected, body) = tokio::task::spawn_blocking(move || -> ExtractedTrack {
+            if ArchiveFormat::sniff(&body) == Some(ArchiveFormat::Gzip) {
+                let max_size = Self::extracted_size_limit(max_tracks).min(Self::EXTRACTED_TRACK_SIZE_LIMIT);
+                body = archive_reader::read_gzip(&body, "uploads", max_size)?;
+            }
+
+            let detected = track_loader::sniff_format(&body, "uploads")?;
+            if let Some(declared) = format.filter(|declared| *declared != detected) {
+                return Err(IOError::format_not_supported(
+                    "uploads",
+                    format!("Uploaded content is a {} track but {} was declared", detected.extension(), declared.extension()).as_str()
+                ));
+            }
+            track_loader::read_track_bytes_as(&body, "uploads", detected)?;
+            Ok((detected, body))
+        })
+        .await
+        .map_err(|err| {
+            tracing::error!("Track validation task failed: {}", err.to_string());
+            return AppError::io_error(IOError::stream_error("uploads", "Failed to validate track, please try again!"));
+        })?
+        .map_err(|err| AppError::io_error(err))?;
+
+        self.write_temp_track(body, detected).await.map_err(|err| AppError::io_error(err))
+    }
+
+    /*
+        Saves every track of the gzip or zip archive read from @stream in the temp uploads, each track is validated and saved on its own.
+        The archive may weigh at most ARCHIVE_BYTES_PER_TRACK and decompress into at most EXTRACTED_BYTES_PER_TRACK for each of the
+        @max_tracks tracks allowed by the tier of the user. Extraction stops once @max_tracks tracks are valid,
+        the files left are reported as failed without being decompressed.
+        Returns the outcome of every file of the archive
+    */
+    pub async fn save_archive_to_temp(&self, stream : &mut BodyDataStream, max_tracks : usize) -> Result<Vec<ArchiveFileReport>, AppError> {
+        let max_tracks = max_tracks.max(1);
+        let body = Self::read_body(stream, Some(max_tracks.saturating_mul(Self::ARCHIVE_BYTES_PER_TRACK))).await?;
+
+        let extracted = tokio::task::spawn_blocking(move || -> ExtractedArchive {
+            let limits = ArchiveLimits { max_entry_size : Self::EXTRACTED_TRACK_SIZE_LIMIT, max_total_size : Self::extracted_size_limit(max_tracks) };
+            let mut entries = archive_reader::read_archive(&body, "archive", limits)?;
+
+            let mut extracted = Vec::new();
+            let mut track_count = 0usize;
+            while track_count < max_tracks {
+                let Some(entry) = entries.next() else {
+                    break;
+                };
+                let track = entry.content.and_then(|content| {
+                    let format = track_loader::sniff_format(&content, &entry.name)?;
+                    track_loader::read_track_bytes_as(&content, &entry.name, format)?;
+                    Ok((format, content))
+                });
+                if track.is_ok() {
+                    track_count += 1;
+                }
+                extracted.push((entry.name, track));
+            }
+
+            for name in entries.skip_remaining() {
+                let limit = IOError::size_limit(&name, format!("Your tier allows {} tracks per archive", max_tracks).as_str());
+                extracted.push((name, Err(limit)));
+            }
+            Ok(extracted)
+        })
+        .await
+        .map_err(|err| {
+            tracing::error!("Archive extraction task failed: {}", err.to_string());
+            return AppError::io_error(IOError::stream_error("archive", "Failed to extract archive, please try again!"));
+        })?
+        .map_err(|err| AppError::io_error(err))?;
+
+        let mut reports = Vec::with_capacity(extracted.len());
+        for (entry, track) in extracted {
+            let saved = match track {
+                Ok((format, content)) => self.write_temp_track(content, format).await.map(|file_name| (format, file_name)),
+                Err(err) => Err(err)
+            };
+
+            reports.push(match saved {
+                Ok((format, file_name)) => ArchiveFileReport { entry, file_name : Some(file_name), format : Some(format), error : None },
+                Err(err) => ArchiveFileReport { entry, file_name : None, format : None, error : Some(err.to_string()) }
+            });
+        }
+
+        Ok(reports)
+    }
+
+    // Decompressed bytes allowed for the @max_tracks of the user tier
+    fn extracted_size_limit(max_tracks : usize) -> usize {
+        max_tracks.max(1).saturating_mul(Self::EXTRACTED_BYTES_PER_TRACK)
+    }
+
+    /*
+        Collects the request body of @stream, failing once more than @limit bytes were received
+    */
+    async fn read_body(stream : &mut BodyDataStream, limit : Option<usize>) -> Result<Vec<u8>, AppError> {
+        let limit = limit.unwrap_or(usize::MAX - 1);
+        let mut body = Vec::new();
+        StreamReader::new(stream.map_err(std::io::Error::other))
+            .take(limit as u64 + 1)
+            .read_to_end(&mut body)
+            .await
+            .map_err(|err| {
+                tracing::error!("Failed to read upload stream: {}", err.to_string());
+                return AppError::io_error(IOError::stream_error("uploads", "Uh oh! Failed to read the uploaded file, please try again!"));
+            })?;
 
-        if let Err(error) = self.file_repo.stream_to_file(
-            &temp_file_name, 
-            Self::UPLOADS_TEMP_DIRECTORY, 
-            stream
-        ).await {
-            return Err(AppError::io_error(error));
+        if body.len() > limit {
+            return Err(AppError::io_error(IOError::size_limit("uploads", format!("Uploads are limited to {} bytes", limit).as_str())));
         }
+        Ok(body)
+    }
+
+    /*
+        Writes the validated track @body of @format in the temp uploads, returns the name of the written file
+    */
+    async fn write_temp_track(&self, body : Vec<u8>, format : TrackFormat) -> Result<String, IOError> {
+        let temp_file_name = format!("{}.{}", Uuid::new_v4(), format.extension());
+        let body_stream = futures_util::stream::once(async move { Ok::<Bytes, Infallible>(Bytes::from(body)) });
 
+        self.file_repo.stream_to_file(&temp_file_name, Self::UPLOADS_TEMP_DIRECTORY, body_stream).await?;
         Ok(temp_file_name)
     }
 
-    pub async fn download_from_temp(&self, path : &str) -> Result<ReaderStream<File>, AppError> {
+    pub async fn download_from_temp(&self, path : &str, format : TrackFormat) -> Result<ReaderStream<File>, AppError> {
         if !FileRepository::path_is_valid(&path) {
             tracing::error!("Download file request contains illegal arguments in file name {}", &path);
             return Err(AppError::io_error(IOError::invalid_path("downloads", "Invalid path name!")));
         }
 
-        let origin_path = std::path::Path::new(Self::UPLOADS_TEMP_DIRECTORY).join(format!("{}.gpx", &path));
+        let origin_path = std::path::Path::new(Self::UPLOADS_TEMP_DIRECTORY).join(format!("{}.{}", &path, format.extension()));
 
         match self.file_repo.stream_from_file(origin_path.to_str().unwrap_or("none.txt")).await {
             Ok (res) => Ok(res),
             Err(err) => Err(AppError::io_error(err))
         }
     }
+
+    /*
+        Exports the uploaded track at @path (stored as @format) as GPX, when @as_reference is set the reference track built from
+        the upload (routes and checkpoints included) is exported instead of the recorded points
+    */
+    pub async fn export_gpx(&self, path : &str, format : TrackFormat, as_reference : bool) -> Result<Vec<u8>, AppError> {
+        if !FileRepository::path_is_valid(&path) {
+            tracing::error!("Export file request contains illegal arguments in file name {}", &path);
+            return Err(AppError::io_error(IOError::invalid_path("exports", "Invalid path name!")));
+        }
+
+        let origin_path = std::path::Path::new(Self::UPLOADS_TEMP_DIRECTORY).join(format!("{}.{}", &path, format.extension()));
+        let class_name = path.to_string();
+
+        let export = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, ServiceError> {
+            if as_reference {
+                let reference = Self::process_reference(&origin_path, &class_name)?;
+                track_export::reference_to_gpx(Vec::new(), &reference)
+            } else {
+                let track = track_loader::load_track(&origin_path).map_err(|err| ServiceError::io_error(err))?;
+                track_export::spatial_to_gpx(Vec::new(), &track)
+            }
+        })
+        .await
+        .map_err(|err| {
+            tracing::error!("Track export task failed: {}", err.to_string());
+            return AppError::io_error(IOError::stream_error("exports", "Failed to export track, please try again!"));
+        })?;
+
+        export.map_err(|err| AppError::service_error(err))
+    }
+
+    /*
+        Validates the uploaded track at @path (stored as @format), points moving faster than @max_speed (m/s) are reported as implausible.
+        Returns the validation report, an unreadable file is an error and not a report
+    */
+    pub async fn validate_track(&self, path : &str, format : TrackFormat, max_speed : Option<f64>) -> Result<ValidationReport, AppError> {
+        if !FileRepository::path_is_valid(&path) {
+            tracing::error!("Validation request contains illegal arguments in file name {}", &path);
+            return Err(AppError::io_error(IOError::invalid_path("validation", "Invalid path name!")));
+        }
+
+        let origin_path = std::path::Path::new(Self::UPLOADS_TEMP_DIRECTORY).join(format!("{}.{}", &path, format.extension()));
+        let config = ValidationConfig::new(
+            max_speed.unwrap_or(Self::VALIDATION_MAX_SPEED),
+            Self::VALIDATION_MAX_TIME_GAP,
+            Self::VALIDATION_MAX_DISTANCE_GAP,
+            Self::VALIDATION_DUPLICATE_DISTANCE,
+            Self::VALIDATION_REPORTED_POINTS
+        );
+
+        let report = tokio::task::spawn_blocking(move || -> Result<ValidationReport, IOError> {
+            let track = track_loader::load_track(&origin_path)?;
+            Ok(track_validation::validate_track(&track, &config))
+        })
+        .await
+        .map_err(|err| {
+            tracing::error!("Track validation task failed: {}", err.to_string());
+            return AppError::io_error(IOError::stream_error("validation", "Failed to validate track, please try again!"));
+        })?;
+
+        report.map_err(|err| AppError::io_error(err))
+    }
+
+    /*
+        Climbing statistics and gradient profile of the uploaded track at @path (stored as @format), binned every @bin_length meters
+        (at least MIN_BIN_LENGTH, long tracks get longer bins, look at MAX_BIN_COUNT).
+        Without @reference the track is profiled as a course, with it the track is snapped as a rider on the uploaded reference
+        (path and format) and profiled along it, its recording gaps (validation thresholds) are reported in the coverage of the profile
+    */
+    pub async fn profile_track(&self, path : &str, format : TrackFormat, reference : Option<(&str, TrackFormat)>, bin_length : Option<f32>) -> Result<ElevationProfile, AppError> {
+        let paths = std::iter::once(path).chain(reference.map(|(reference_path, _)| reference_path));
+        if paths.clone().any(|path| !FileRepository::path_is_valid(&path)) {
+            tracing::error!("Profile request contains illegal arguments in file names {:?}", paths.collect::<Vec<_>>());
+            return Err(AppError::io_error(IOError::invalid_path("profiles", "Invalid path name!")));
+        }
+
+        let track_path = std::path::Path::new(Self::UPLOADS_TEMP_DIRECTORY).join(format!("{}.{}", &path, format.extension()));
+        let reference = reference.map(|(reference_path, reference_format)| {
+            let origin_path = std::path::Path::new(Self::UPLOADS_TEMP_DIRECTORY).join(format!("{}.{}", reference_path, reference_format.extension()));
+            (origin_path, reference_path.to_string())
+        });
+        if bin_length.is_some_and(|bin_length| !(bin_length >= analysis::MIN_BIN_LENGTH)) {
+            return Err(AppError::service_error(ServiceError::invalid_data(
+                format!("Profile bins must be at least {} m long", analysis::MIN_BIN_LENGTH).as_str()
+            )));
+        }
+        let config = GradientConfig::new(
+            Self::PROFILE_ASCENT_THRESHOLD,
+            bin_length.unwrap_or(Self::PROFILE_BIN_LENGTH),
+            Self::PROFILE_GRADIENT_DISTANCE
+        );
+        let class_name = path.to_string();
+
+        let profile = tokio::task::spawn_blocking(move || -> Result<ElevationProfile, ServiceError> {
+            let Some((reference_path, reference_class)) = reference else {
+                let reference = Self::process_reference(&track_path, &class_name)?;
+                return Ok(analysis::reference_profile(&reference, &config));
+            };
+
+            let reference = Self::process_reference(&reference_path, &reference_class)?;
+            let outlier_config = OutlierConfig::new(
+                Self::VALIDATION_MAX_SPEED as f32,
+                Self::PROFILE_OUTLIER_MAX_ACCELERATION,
+                Self::PROFILE_OUTLIER_JITTER_DISTANCE,
+                Self::PROFILE_OUTLIER_MAX_RUN,
+                OutlierRepair::Interpolate
+            );
+            let rider = track_processor::process_rider_track(
+                &track_path, Uuid::nil(), 0, geo_conversions::WGS84, &reference.projection, &reference.origin, Some(&outlier_config)
+            )?;
+            let grid = Grid::from_track(&reference, Self::PROFILE_GRID_CELL_SIZE)
+                .map_err(|err| ServiceError::io_error(IOError::domain_error("profiles", err)))?;
+            let gap_config = GapConfig::new(
+                Self::VALIDATION_MAX_TIME_GAP,
+                Self::VALIDATION_MAX_DISTANCE_GAP as f32,
+                Self::PROFILE_GAP_FILL_INTERVAL,
+                GapPolicy::Leave
+            );
+            let matched = track_processor::snap_rider_track(&rider, &reference, &grid, &SnappingConfig::new(Self::PROFILE_CONTINUITY_CLAMP), &gap_config)?;
+            Ok(analysis::rider_profile(&matched, &reference, &config))
+        })
+        .await
+        .map_err(|err| {
+            tracing::error!("Track profile task failed: {}", err.to_string());
+            return AppError::io_error(IOError::stream_error("profiles", "Failed to profile track, please try again!"));
+        })?;
+
+        profile.map_err(|err| AppError::service_error(err))
+    }
+
+    /*
+        Processes the uploaded reference at @origin_path in the metric projection selected from its location, the warnings of the selection are logged
+    */
+    fn process_reference(origin_path : &std::path::Path, class_name : &str) -> Result<ReferenceTrack, ServiceError> {
+        let (reference, projection) = track_processor::process_reference_track_auto(origin_path, class_name, None)?;
+        for warning in &projection.warnings {
+            tracing::info!("Projection {} selected for {} : {}", projection.crs, class_name, warning.message);
+        }
+        Ok(reference)
+    }
 }
\ No newline at end of file
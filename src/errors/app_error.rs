@@ -127,9 +127,13 @@ fn map_io_to_status_code(kind : &IOErrorType) -> StatusCode {
                     crate::errors::io_errors::IOErrorType::InvalidPath(_) => StatusCode::BAD_REQUEST,
                     crate::errors::io_errors::IOErrorType::RecordOperation(_) => StatusCode::INTERNAL_SERVER_ERROR,
                     crate::errors::io_errors::IOErrorType::StreamError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-                    crate::errors::io_errors::IOErrorType::XmlParsingFail(_) => StatusCode::INTERNAL_SERVER_ERROR,
-                    crate::errors::io_errors::IOErrorType::XmlReaderFail(_) => StatusCode::INTERNAL_SERVER_ERROR,
-                    crate::errors::io_errors::IOErrorType::DomainError(_) => StatusCode::BAD_REQUEST
+                    crate::errors::io_errors::IOErrorType::XmlParsingFail(_) => StatusCode::BAD_REQUEST,
+                    crate::errors::io_errors::IOErrorType::XmlReaderFail(_) => StatusCode::BAD_REQUEST,
+                    crate::errors::io_errors::IOErrorType::BinaryParsingFail(_) => StatusCode::BAD_REQUEST,
+                    crate::errors::io_errors::IOErrorType::JsonParsingFail(_) => StatusCode::BAD_REQUEST,
+                    crate::errors::io_errors::IOErrorType::RowSkipped(_, _) => StatusCode::BAD_REQUEST,
+                    crate::errors::io_errors::IOErrorType::DomainError(_) => StatusCode::BAD_REQUEST,
+                    crate::errors::io_errors::IOErrorType::SizeLimitExceeded(_) => StatusCode::PAYLOAD_TOO_LARGE
     }
 }
 
@@ -146,6 +150,7 @@ impl IntoResponse for AppError {
             AppErrorType::ServiceError(service_error) => {
                 match &service_error.etype {
                     crate::errors::service_errors::ServiceErrorType::CoordinateConversionError(_, _, _, _) => StatusCode::INTERNAL_SERVER_ERROR,
+                    crate::errors::service_errors::ServiceErrorType::InvalidProjection(_, _, _) => StatusCode::BAD_REQUEST,
                     crate::errors::service_errors::ServiceErrorType::EmptyTrack() => StatusCode::BAD_REQUEST,
                     crate::errors::service_errors::ServiceErrorType::IOError(err) => map_io_to_status_code(&err.etype),
                     crate::errors::service_errors::ServiceErrorType::InvalidData(_) => StatusCode::BAD_REQUEST,
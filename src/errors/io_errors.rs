@@ -18,6 +18,18 @@ impl IOError {
         return IOError { source: source.to_string(), etype: IOErrorType::XmlParsingFail(reason.to_string())}
     }
 
+    pub fn binary_parser(source: &str, reason : &str) -> Self {
+        return IOError { source: source.to_string(), etype: IOErrorType::BinaryParsingFail(reason.to_string())}
+    }
+
+    pub fn json_parser(source: &str, reason : &str) -> Self {
+        return IOError { source: source.to_string(), etype: IOErrorType::JsonParsingFail(reason.to_string())}
+    }
+
+    pub fn row_skipped(source: &str, row : usize, reason : &str) -> Self {
+        return IOError { source: source.to_string(), etype: IOErrorType::RowSkipped(row, reason.to_string())}
+    }
+
     pub fn format_not_supported(source : &str, reason : &str) -> Self {
         return IOError { source: source.to_string(), etype: IOErrorType::FormatNotSupported(reason.to_string()) }
     }
@@ -38,6 +50,10 @@ impl IOError {
         return IOError { source: source.to_string(), etype: IOErrorType::DomainError(reason) }
     }
 
+    pub fn size_limit(source : &str, reason : &str) -> Self {
+        return IOError { source: source.to_string(), etype: IOErrorType::SizeLimitExceeded(reason.to_string()) }
+    }
+
     pub fn stream_error(source : &str, reason : &str) -> Self {
         return IOError { source: source.to_string(), etype: IOErrorType::StreamError(reason.to_string()) }
     }
@@ -63,6 +79,12 @@ pub enum IOErrorType {
 
     XmlParsingFail(String), // Reason
 
+    BinaryParsingFail(String), // Reason
+
+    JsonParsingFail(String), // Reason
+
+    RowSkipped(usize, String), // Row (1 based, header included), Reason
+
     FormatNotSupported(String), // Reason
     
     InvalidPath(String), // Reason
@@ -74,6 +96,8 @@ pub enum IOErrorType {
     DomainError(DomainError), // Wrapper for domain Error
 
     StreamError(String), // Reason
+
+    SizeLimitExceeded(String), // Reason
 }
 
 impl Display for IOErrorType {
@@ -83,6 +107,12 @@ impl Display for IOErrorType {
                 write!(f, "Failed to read xml file : {}", reason),
             IOErrorType::XmlParsingFail(reason) =>
                 write!(f, "Failed to parse xml file : {}", reason),
+            IOErrorType::BinaryParsingFail(reason) =>
+                write!(f, "Failed to decode binary file : {}", reason),
+            IOErrorType::JsonParsingFail(reason) =>
+                write!(f, "Failed to parse json file : {}", reason),
+            IOErrorType::RowSkipped(row, reason) =>
+                write!(f, "Skipped row {} : {}", row, reason),
             IOErrorType::FormatNotSupported(reason) =>
                 write!(f, "File format not supported : {}", reason),
             IOErrorType::InvalidPath(reason) =>
@@ -94,7 +124,9 @@ impl Display for IOErrorType {
             IOErrorType::DomainError(reason) =>
                 write!(f, "{}", reason),
             IOErrorType::StreamError(reason) =>
-                write!(f, "Failed to operate on stream : {}", reason)
+                write!(f, "Failed to operate on stream : {}", reason),
+            IOErrorType::SizeLimitExceeded(reason) =>
+                write!(f, "Size limit exceeded : {}", reason)
         }
     }
 }
@@ -13,6 +13,10 @@ impl ServiceError {
         return ServiceError { etype : ServiceErrorType::CoordinateConversionError(origin.to_string(), destination.to_string(), original_coordiante.to_string(), reason.to_string())};
     }
 
+    pub fn invalid_projection(origin : &str, destination : &str, reason : &str) -> Self {
+        return ServiceError { etype : ServiceErrorType::InvalidProjection(origin.to_string(), destination.to_string(), reason.to_string())};
+    }
+
     pub fn io_error(io_error : IOError) -> Self {
         return ServiceError { etype: ServiceErrorType::IOError(io_error) }
     }
@@ -53,6 +57,9 @@ pub enum ServiceErrorType {
     // Origin, Destination, Original Point, Reason
     CoordinateConversionError(String, String, String, String), 
 
+    // Origin, Destination, Reason
+    InvalidProjection(String, String, String),
+
     // Composed Error
     IOError(IOError),
 
@@ -73,6 +80,8 @@ impl Display for ServiceErrorType {
         match &self {
             ServiceErrorType::CoordinateConversionError(origin, destination, original_point, reason) =>
                 write!(f, "failed to convert point [{}] from {} to {}: {}", original_point, origin, destination, reason),
+            ServiceErrorType::InvalidProjection(origin, destination, reason) =>
+                write!(f, "no projection from {} to {}: {}", origin, destination, reason),
             ServiceErrorType::IOError(err) =>
                 write!(f, "{}", err.to_string()),
             ServiceErrorType::InvalidData(err) =>
@@ -1 +1,15 @@
-pub mod track_loader;
\ No newline at end of file
+pub mod track_loader;
+pub mod track_format;
+pub mod fit_loader;
+pub mod tcx_loader;
+pub mod xml_util;
+pub mod kml_loader;
+pub mod kml_writer;
+pub mod nmea_loader;
+pub mod geojson_loader;
+pub mod geojson_writer;
+pub mod csv_loader;
+pub mod gpx_writer;
+pub mod time_parser;
+pub mod archive_reader;
+pub mod dem_reader;
\ No newline at end of file
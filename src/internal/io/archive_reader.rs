@@ -0,0 +1,215 @@
+use std::io::{Cursor, Read};
+
+use flate2::read::MultiGzDecoder;
+use zip::ZipArchive;
+
+use crate::errors::io_errors::IOError;
+
+// Compressed containers accepted next to plain track files
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Gzip,       // A single compressed track (.gpx.gz, .fit.gz...)
+    Zip         // Any number of tracks, possibly gzipped themselves
+}
+
+impl ArchiveFormat {
+    /*
+        Detects the archive format from the first bytes of @bytes, KMZ files are zip archives as well
+    */
+    pub fn sniff(bytes : &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0x1F, 0x8B]) {
+            Some(ArchiveFormat::Gzip)
+        } else if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+// A file found in an archive, extraction errors are kept per file
+pub struct ArchiveEntry {
+    pub name : String,                          // Path of the file inside the archive
+    pub content : Result<Vec<u8>, IOError>
+}
+
+/*
+    Decompresses the gzip @bytes read from @source, concatenated gzip members are read as one stream
+    Throws:
+    SizeLimitExceeded if the decompressed content is larger than @max_size,
+    BinaryParsingFail if the content is not valid gzip
+*/
+pub fn read_gzip(bytes : &[u8], source : &str, max_size : usize) -> Result<Vec<u8>, IOError> {
+    read_limited(MultiGzDecoder::new(bytes), source, max_size)
+}
+
+// Decompression limits of an archive, files past the total limit are kept as failed entries without being read
+#[derive(Clone, Copy, Debug)]
+pub struct ArchiveLimits {
+    pub max_entry_size : usize,     // Decompressed bytes of a single file
+    pub max_total_size : usize      // Decompressed bytes of all the files read from the archive
+}
+
+/*
+    The files of a gzip or zip archive, every call to next() decompresses one file so the caller can stop before the end of the archive.
+    Look at read_archive
+*/
+pub struct ArchiveEntries<'a> {
+    source : String,
+    archive : ArchiveContent<'a>,
+    index : usize,                  // Next file of the archive
+    remaining_size : usize,         // Decompressed bytes left to the archive, look at ArchiveLimits
+    max_entry_size : usize
+}
+
+enum ArchiveContent<'a> {
+    Gzip(&'a [u8]),
+    Zip(ZipArchive<Cursor<&'a [u8]>>)
+}
+
+/*
+    Opens the gzip or zip archive in @bytes, @source is only used to report errors.
+    The single file of a gzip archive is named after @source without its .gz extension.
+    Directories, hidden files and macOS resource forks (__MACOSX) of zip archives are skipped, gzipped files inside them are decompressed.
+    Files are decompressed one at a time while iterating within the @limits, larger files are kept as failed entries
+    Throws:
+    FormatNotSupported if @bytes is not a gzip or zip archive,
+    BinaryParsingFail if the zip directory can not be read
+*/
+pub fn read_archive<'a>(bytes : &'a [u8], source : &str, limits : ArchiveLimits) -> Result<ArchiveEntries<'a>, IOError> {
+    let archive = match ArchiveFormat::sniff(bytes) {
+        Some(ArchiveFormat::Gzip) => ArchiveContent::Gzip(bytes),
+        Some(ArchiveFormat::Zip) => ArchiveContent::Zip(ZipArchive::new(Cursor::new(bytes)).map_err(
+            |err| IOError::binary_parser(source, err.to_string().as_str())
+        )?),
+        None => return Err(IOError::format_not_supported(source, "Only gzip and zip archives are supported"))
+    };
+    Ok(ArchiveEntries {
+        source : source.to_string(),
+        archive,
+        index : 0,
+        remaining_size : limits.max_total_size,
+        max_entry_size : limits.max_entry_size
+    })
+}
+
+impl ArchiveEntries<'_> {
+    /*
+        Names of the files left in the archive (look at read_archive for the files skipped), they are not decompressed
+    */
+    pub fn skip_remaining(self) -> Vec<String> {
+        match self.archive {
+            ArchiveContent::Gzip(_) if self.index == 0 => vec![strip_gz(&self.source).to_string()],
+            ArchiveContent::Gzip(_) => Vec::new(),
+            ArchiveContent::Zip(archive) => (self.index..archive.len())
+                .map(|index| archive.name_for_index(index).map(str::to_string).unwrap_or_else(|| format!("#{}", index)))
+                .filter(|name| !is_skipped(name))
+                .map(|name| strip_gz(&name).to_string())
+                .collect()
+        }
+    }
+}
+
+impl Iterator for ArchiveEntries<'_> {
+    type Item = ArchiveEntry;
+
+    fn next(&mut self) -> Option<ArchiveEntry> {
+        let ArchiveEntries { source, archive, index, remaining_size, max_entry_size } = self;
+        match archive {
+            ArchiveContent::Gzip(bytes) => {
+                if *index > 0 {
+                    return None;
+                }
+                *index = 1;
+                Some(ArchiveEntry {
+                    name : strip_gz(source).to_string(),
+                    content : read_budget(MultiGzDecoder::new(*bytes), source, *max_entry_size, remaining_size)
+                })
+            }
+            ArchiveContent::Zip(archive) => {
+                while *index < archive.len() {
+                    let file_index = *index;
+                    *index += 1;
+                    let file = match archive.by_index(file_index) {
+                        Ok(file) => file,
+                        Err(err) => return Some(ArchiveEntry {
+                            name : format!("#{}", file_index),
+                            content : Err(IOError::binary_parser(source, err.to_string().as_str()))
+                        })
+                    };
+
+                    let name = file.name().to_string();
+                    if file.is_dir() || is_skipped(&name) {
+                        continue;
+                    }
+
+                    // The declared size can lie, read_budget checks the real one
+                    let content = if file.size() > *max_entry_size as u64 {
+                        Err(IOError::size_limit(&name, format!("Files are limited to {} bytes once extracted", max_entry_size).as_str()))
+                    } else {
+                        read_budget(file, &name, *max_entry_size, remaining_size)
+                    };
+
+                    let content = match content {
+                        Ok(extracted) if ArchiveFormat::sniff(&extracted) == Some(ArchiveFormat::Gzip) => {
+                            read_budget(MultiGzDecoder::new(extracted.as_slice()), &name, *max_entry_size, remaining_size)
+                        }
+                        other => other
+                    };
+                    return Some(ArchiveEntry { name : strip_gz(&name).to_string(), content });
+                }
+                None
+            }
+        }
+    }
+}
+
+// Directories, hidden files and macOS resource forks of zip archives
+fn is_skipped(name : &str) -> bool {
+    name.ends_with('/') || name.split('/').any(|component| component.starts_with('.') || component == "__MACOSX")
+}
+
+/*
+    Reads @reader to its end within @max_size bytes and the @remaining_size bytes left to the archive, the bytes read are taken from @remaining_size.
+    Nothing is read once the archive has no bytes left
+*/
+fn read_budget<R : Read>(reader : R, source : &str, max_size : usize, remaining_size : &mut usize) -> Result<Vec<u8>, IOError> {
+    let archive_limit = || IOError::size_limit(source, "The archive is over the extracted size allowed by your tier");
+    if *remaining_size == 0 {
+        return Err(archive_limit());
+    }
+
+    let limit = max_size.min(*remaining_size);
+    let mut content = Vec::new();
+    let read = reader.take(limit as u64 + 1).read_to_end(&mut content);
+    *remaining_size -= content.len().min(*remaining_size);
+    read.map_err(|err| IOError::binary_parser(source, err.to_string().as_str()))?;
+
+    if content.len() > max_size {
+        return Err(IOError::size_limit(source, format!("Files are limited to {} bytes once extracted", max_size).as_str()));
+    }
+    if content.len() > limit {
+        return Err(archive_limit());
+    }
+    Ok(content)
+}
+
+/*
+    Reads @reader to its end, failing once more than @max_size bytes were read
+*/
+fn read_limited<R : Read>(reader : R, source : &str, max_size : usize) -> Result<Vec<u8>, IOError> {
+    let mut content = Vec::new();
+    reader.take(max_size as u64 + 1).read_to_end(&mut content).map_err(
+        |err| IOError::binary_parser(source, err.to_string().as_str())
+    )?;
+    if content.len() > max_size {
+        return Err(IOError::size_limit(source, format!("Files are limited to {} bytes once extracted", max_size).as_str()));
+    }
+    Ok(content)
+}
+
+fn strip_gz(name : &str) -> &str {
+    name.strip_suffix(".gz")
+        .or_else(|| name.strip_suffix(".GZ"))
+        .unwrap_or(name)
+}
@@ -0,0 +1,323 @@
+use std::{fs::File, io::{BufRead, BufReader, Cursor, Read}, path::Path};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::{errors::io_errors::IOError, internal::model::{config::csv::{AngleUnit, CsvColumn, CsvColumns, CsvConfig, CsvTimeFormat, CsvUnits, LengthUnit}, spatial::points::SpatialPoint, track::common::{SpatialTrack, TrackInfo, TrackMetadata, TrackSegment}}};
+
+const FEET_TO_METERS : f64 = 0.3048;
+const SEMICIRCLES_TO_DEGREES : f64 = 180.0 / 2_147_483_648.0;
+
+// Header names recognised when no column mapping is given
+const LATITUDE_NAMES : [&str; 2] = ["lat", "latitude"];
+const LONGITUDE_NAMES : [&str; 4] = ["lon", "lng", "long", "longitude"];
+const ELEVATION_NAMES : [&str; 4] = ["ele", "elevation", "alt", "altitude"];
+const TIME_NAMES : [&str; 6] = ["time", "timestamp", "datetime", "date_time", "elapsed", "elapsed_seconds"];
+const HDOP_NAMES : [&str; 1] = ["hdop"];
+const ACCURACY_NAMES : [&str; 4] = ["accuracy", "acc", "horizontal_accuracy", "hacc"];
+
+// Time read from a row, absolute or relative to the start of the recording
+enum RowTime {
+    Absolute(DateTime<Utc>),
+    Elapsed(f64)
+}
+
+// Column indices resolved from the configured mapping
+struct ColumnIndices {
+    latitude : usize,
+    longitude : usize,
+    elevation : Option<usize>,
+    time : Option<usize>,
+    hdop : Option<usize>,
+    accuracy : Option<usize>
+}
+
+/*
+    Loads a track from the csv file with @path using the column mapping and units of @config.
+    Returns the track with the rows that were skipped, look at read_csv
+ */
+pub fn load_csv(path : &Path, config : &CsvConfig) -> Result<(SpatialTrack, Vec<IOError>), IOError> {
+    let str_path = path.to_str().unwrap_or("unkown file path");
+
+    let file = File::open(path).map_err(
+        |err| {return IOError::invalid_path(str_path, err.to_string().as_str());}
+    )?;
+    read_csv(BufReader::new(file), str_path, config)
+}
+
+/*
+    Loads a track from the csv file with @path, the column mapping is detected from the header row (look at detect_csv_config)
+ */
+pub fn load_csv_detected(path : &Path, delimiter : u8) -> Result<(SpatialTrack, Vec<IOError>), IOError> {
+    let str_path = path.to_str().unwrap_or("unkown file path");
+
+    let file = File::open(path).map_err(
+        |err| {return IOError::invalid_path(str_path, err.to_string().as_str());}
+    )?;
+    read_csv_detected(BufReader::new(file), str_path, delimiter)
+}
+
+/*
+    Reads a csv document from @reader detecting its column mapping from the header row and the first data row
+    Throws: FormatNotSupported if no latitude or longitude column can be found
+ */
+pub fn read_csv_detected<R : BufRead>(mut reader : R, source : &str, delimiter : u8) -> Result<(SpatialTrack, Vec<IOError>), IOError> {
+    let mut prefix = Vec::new();
+    let mut header = String::new();
+    let mut first_row = String::new();
+    for line in [&mut header, &mut first_row] {
+        reader.read_line(line).map_err(|err| IOError::stream_error(source, err.to_string().as_str()))?;
+        prefix.extend_from_slice(line.as_bytes());
+    }
+
+    let config = detect_csv_config(&header, &first_row, delimiter).ok_or_else(
+        || IOError::format_not_supported(source, "csv header has no latitude and longitude columns, a column mapping is required")
+    )?;
+
+    read_csv(Cursor::new(prefix).chain(reader), source, &config)
+}
+
+/*
+    Builds a column mapping from the names in the @header row, well known names (lat, lon, ele, time...) are recognised.
+    The time format is guessed from the value in @first_row, coordinates are expected in degrees and elevation in meters.
+    Returns None if no latitude or longitude column exists
+ */
+pub fn detect_csv_config(header : &str, first_row : &str, delimiter : u8) -> Option<CsvConfig> {
+    let names : Vec<String> = split_fields(header.trim_end_matches(['\r', '\n']), delimiter)
+        .iter()
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+    let find = |aliases : &[&str]| names.iter().position(|name| aliases.contains(&name.as_str()));
+
+    let latitude = find(&LATITUDE_NAMES)?;
+    let longitude = find(&LONGITUDE_NAMES)?;
+    let elevation = find(&ELEVATION_NAMES);
+    let time = find(&TIME_NAMES);
+    let hdop = find(&HDOP_NAMES);
+    let accuracy = find(&ACCURACY_NAMES);
+
+    let time_format = time.and_then(|index| {
+        let values = split_fields(first_row.trim_end_matches(['\r', '\n']), delimiter);
+        let value = values.get(index)?.trim().to_string();
+
+        if DateTime::parse_from_rfc3339(&value).is_ok() {
+            return Some(CsvTimeFormat::Rfc3339);
+        }
+        let number = value.parse::<f64>().ok()?;
+        Some(if names[index].starts_with("elapsed") || number < 1e8 {
+            CsvTimeFormat::ElapsedSeconds
+        } else if number >= 1e11 {
+            CsvTimeFormat::UnixMillis
+        } else {
+            CsvTimeFormat::UnixSeconds
+        })
+    }).unwrap_or(CsvTimeFormat::Rfc3339);
+
+    Some(CsvConfig::new(
+        delimiter,
+        true,
+        CsvColumns {
+            latitude : CsvColumn::Index(latitude),
+            longitude : CsvColumn::Index(longitude),
+            elevation : elevation.map(CsvColumn::Index),
+            time : time.map(CsvColumn::Index),
+            hdop : hdop.map(CsvColumn::Index),
+            accuracy : accuracy.map(CsvColumn::Index)
+        },
+        CsvUnits { angle: AngleUnit::Degrees, elevation: LengthUnit::Meters, time: time_format }
+    ))
+}
+
+/*
+    Reads a csv document from @reader using the column mapping and units of @config, @source is only used to report errors.
+    Rows that can not be read (missing fields, invalid numbers or times, coordinates out of range) are skipped,
+    every skipped row is returned as a RowSkipped error next to the track. Empty rows are ignored.
+    Throws:
+    FormatNotSupported if a configured column is missing from the header,
+    StreamError if @reader fails
+ */
+pub fn read_csv<R : BufRead>(mut reader : R, source : &str, config : &CsvConfig) -> Result<(SpatialTrack, Vec<IOError>), IOError> {
+    const INITIAL_ALLOCATION_SIZE : usize = 12_000;
+
+    let delimiter = config.get_delimiter();
+    let units = config.get_units();
+
+    let mut points : Vec<SpatialPoint> = Vec::with_capacity(INITIAL_ALLOCATION_SIZE);
+    let mut skipped : Vec<IOError> = Vec::new();
+    let mut initial_stamp : Option<DateTime<Utc>> = None;
+    let mut initial_elapsed : Option<f64> = None;
+
+    let mut line = String::new();
+    let mut row = 0usize;
+
+    let mut header : Option<Vec<String>> = None;
+    if config.get_has_header() {
+        row += 1;
+        reader.read_line(&mut line).map_err(|err| IOError::stream_error(source, err.to_string().as_str()))?;
+        header = Some(split_fields(line.trim_end_matches(['\r', '\n']), delimiter));
+    }
+    let columns = resolve_columns(config.get_columns(), header.as_deref(), source)?;
+
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line).map_err(|err| IOError::stream_error(source, err.to_string().as_str()))?;
+        if read == 0 {
+            break;
+        }
+        row += 1;
+
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        if trimmed.trim().is_empty() {
+            continue;
+        }
+
+        let fields = split_fields(trimmed, delimiter);
+        match read_row(&fields, &columns, units) {
+            Ok((mut point, time)) => {
+                point.delta_seconds = time.map(|time| match time {
+                    RowTime::Absolute(time) => {
+                        let initial = *initial_stamp.get_or_insert(time);
+                        (time - initial).num_milliseconds() as f64 / 1000.0
+                    }
+                    RowTime::Elapsed(elapsed) => elapsed - *initial_elapsed.get_or_insert(elapsed)
+                });
+                points.push(point);
+            }
+            Err(reason) => skipped.push(IOError::row_skipped(source, row, reason.as_str()))
+        }
+    }
+
+    Ok((SpatialTrack {
+        segments : TrackSegment::single(points.len()),
+        laps : Vec::new(),
+        track : points,
+        sensors : Vec::new(),
+        start_time : initial_stamp.unwrap_or_default(),
+        tracks : vec![TrackInfo::default()],
+        routes : Vec::new(),
+        waypoints : Vec::new(),
+        metadata : TrackMetadata::default()
+    }, skipped))
+}
+
+fn resolve_columns(columns : &CsvColumns, header : Option<&[String]>, source : &str) -> Result<ColumnIndices, IOError> {
+    let resolve = |column : &CsvColumn| -> Result<usize, IOError> {
+        match column {
+            CsvColumn::Index(index) => Ok(*index),
+            CsvColumn::Name(name) => header
+                .ok_or_else(|| IOError::format_not_supported(source, format!("Column {:?} is addressed by name but the csv has no header", name).as_str()))?
+                .iter()
+                .position(|field| field.trim().eq_ignore_ascii_case(name.trim()))
+                .ok_or_else(|| IOError::format_not_supported(source, format!("Column {:?} not found in the csv header", name).as_str()))
+        }
+    };
+
+    Ok(ColumnIndices {
+        latitude : resolve(&columns.latitude)?,
+        longitude : resolve(&columns.longitude)?,
+        elevation : columns.elevation.as_ref().map(resolve).transpose()?,
+        time : columns.time.as_ref().map(resolve).transpose()?,
+        hdop : columns.hdop.as_ref().map(resolve).transpose()?,
+        accuracy : columns.accuracy.as_ref().map(resolve).transpose()?
+    })
+}
+
+/*
+    Reads the point of a row, the Err holds the reason the row is skipped
+*/
+fn read_row(fields : &[String], columns : &ColumnIndices, units : &CsvUnits) -> Result<(SpatialPoint, Option<RowTime>), String> {
+    let field = |index : usize, name : &str| -> Result<&str, String> {
+        fields.get(index)
+            .map(|value| value.trim())
+            .ok_or_else(|| format!("missing {} field (column {})", name, index))
+    };
+    let optional_field = |index : Option<usize>, name : &str| -> Result<Option<&str>, String> {
+        index.map(|index| field(index, name))
+            .transpose()
+            .map(|value| value.filter(|value| !value.is_empty()))
+    };
+    let number = |value : &str, name : &str| -> Result<f64, String> {
+        value.parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| format!("invalid {} {:?}", name, value))
+    };
+
+    let lat = to_degrees(number(field(columns.latitude, "latitude")?, "latitude")?, units.angle);
+    let lon = to_degrees(number(field(columns.longitude, "longitude")?, "longitude")?, units.angle);
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(format!("coordinate ({}, {}) out of range", lat, lon));
+    }
+
+    let elev = optional_field(columns.elevation, "elevation")?
+        .map(|value| number(value, "elevation"))
+        .transpose()?
+        .map(|elevation| match units.elevation {
+            LengthUnit::Meters => elevation,
+            LengthUnit::Feet => elevation * FEET_TO_METERS
+        });
+
+    let time = optional_field(columns.time, "time")?
+        .map(|value| parse_time(value, &units.time).ok_or_else(|| format!("invalid time {:?}", value)))
+        .transpose()?;
+
+    let hdop = optional_field(columns.hdop, "hdop")?
+        .map(|value| number(value, "hdop"))
+        .transpose()?;
+    let accuracy = optional_field(columns.accuracy, "accuracy")?
+        .map(|value| number(value, "accuracy"))
+        .transpose()?;
+
+    Ok((SpatialPoint { lon, lat, elev, delta_seconds: None, hdop, accuracy }, time))
+}
+
+fn to_degrees(value : f64, unit : AngleUnit) -> f64 {
+    match unit {
+        AngleUnit::Degrees => value,
+        AngleUnit::Radians => value.to_degrees(),
+        AngleUnit::Semicircles => value * SEMICIRCLES_TO_DEGREES
+    }
+}
+
+fn parse_time(value : &str, format : &CsvTimeFormat) -> Option<RowTime> {
+    let from_millis = |millis : f64| DateTime::from_timestamp_millis(millis.round() as i64).map(RowTime::Absolute);
+
+    match format {
+        CsvTimeFormat::Rfc3339 => DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|date_time| RowTime::Absolute(date_time.with_timezone(&Utc))),
+        CsvTimeFormat::UnixSeconds => from_millis(value.parse::<f64>().ok()? * 1000.0),
+        CsvTimeFormat::UnixMillis => from_millis(value.parse::<f64>().ok()?),
+        CsvTimeFormat::Pattern(pattern) => DateTime::parse_from_str(value, pattern)
+            .map(|date_time| date_time.with_timezone(&Utc))
+            .or_else(|_| NaiveDateTime::parse_from_str(value, pattern).map(|date_time| date_time.and_utc()))
+            .ok()
+            .map(RowTime::Absolute),
+        CsvTimeFormat::ElapsedSeconds => value.parse::<f64>().ok().filter(|value| value.is_finite()).map(RowTime::Elapsed),
+        CsvTimeFormat::ElapsedMillis => value.parse::<f64>().ok().filter(|value| value.is_finite()).map(|millis| RowTime::Elapsed(millis / 1000.0))
+    }
+}
+
+/*
+    Splits a csv @line on @delimiter, fields may be quoted with " and quotes inside them doubled ("")
+*/
+fn split_fields(line : &str, delimiter : u8) -> Vec<String> {
+    let delimiter = delimiter as char;
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(character) = chars.next() {
+        match character {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            _ if character == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(character)
+        }
+    }
+    fields.push(field);
+    fields
+}
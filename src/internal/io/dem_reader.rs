@@ -0,0 +1,229 @@
+use std::{collections::HashMap, fs::File, io::BufReader, path::{Path, PathBuf}, sync::{Arc, Mutex}};
+
+use tiff::{ColorType, decoder::{Decoder, DecodingResult}, tags::Tag};
+
+use crate::{errors::io_errors::IOError, internal::model::spatial::dem::{DemGrid, DemTile}};
+
+// Value of the SRTM samples without data
+const HGT_VOID : i16 = -32768;
+
+// GeoTIFF keys (GeoKeyDirectoryTag) and the values supported
+const GEO_KEY_MODEL_TYPE : u16 = 1024;
+const MODEL_TYPE_GEOGRAPHIC : u16 = 2;
+const GEO_KEY_RASTER_TYPE : u16 = 1025;
+const RASTER_PIXEL_IS_POINT : u16 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DemFormat {
+    Hgt,        // SRTM tile, big endian i16 samples, the tile is named after its south west corner (N45E006.hgt)
+    GeoTiff     // Single band GeoTIFF in geographic coordinates (WGS84)
+}
+
+/*
+    The DEM files of a directory, a raster is only read (and then kept in memory) the first time a point falls into it.
+    Safe to share between threads
+*/
+pub struct DemStore {
+    sources : Vec<(PathBuf, DemFormat, DemGrid)>,
+    tiles : Mutex<HashMap<usize, Arc<DemTile>>>
+}
+
+impl DemStore {
+    /*
+        Indexes the .hgt, .tif and .tiff files of @directory (not recursive) by reading their headers.
+        Files that can not be read are skipped with a warning.
+        Throws: InvalidPath if @directory can not be listed
+    */
+    pub fn open(directory : &Path) -> Result<Self, IOError> {
+        let str_path = directory.to_str().unwrap_or("");
+        let entries = std::fs::read_dir(directory).map_err(
+            |err| IOError::invalid_path(str_path, err.to_string().as_str())
+        )?;
+
+        let mut sources = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let format = match path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_ascii_lowercase()).as_deref() {
+                Some("hgt") => DemFormat::Hgt,
+                Some("tif") | Some("tiff") => DemFormat::GeoTiff,
+                _ => continue
+            };
+            let grid = match format {
+                DemFormat::Hgt => read_hgt_grid(&path),
+                DemFormat::GeoTiff => read_geotiff_grid(&path)
+            };
+            match grid {
+                Ok(grid) => sources.push((path, format, grid)),
+                Err(err) => tracing::warn!("DEM file skipped : {}", err)
+            }
+        }
+        // Finer rasters are sampled first where rasters overlap
+        sources.sort_by(|(_, _, left), (_, _, right)| left.lon_step.total_cmp(&right.lon_step));
+
+        Ok(DemStore { sources, tiles : Mutex::new(HashMap::new()) })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /*
+        Returns the elevation in meters at (@lon, @lat) from the first raster holding data there, None if no raster covers the point
+        Throws: the errors of read_hgt and read_geotiff when a raster is read for the first time
+    */
+    pub fn sample(&self, lon : f64, lat : f64) -> Result<Option<f32>, IOError> {
+        for (index, (path, format, grid)) in self.sources.iter().enumerate() {
+            if !grid.contains(lon, lat) {
+                continue;
+            }
+            let tile = self.tile(index, path, *format)?;
+            if let Some(elevation) = tile.sample(lon, lat) {
+                return Ok(Some(elevation));
+            }
+        }
+        Ok(None)
+    }
+
+    fn tile(&self, index : usize, path : &Path, format : DemFormat) -> Result<Arc<DemTile>, IOError> {
+        let mut tiles = self.tiles.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(tile) = tiles.get(&index) {
+            return Ok(tile.clone());
+        }
+        let tile = Arc::new(match format {
+            DemFormat::Hgt => read_hgt(path)?,
+            DemFormat::GeoTiff => read_geotiff(path)?
+        });
+        tiles.insert(index, tile.clone());
+        Ok(tile)
+    }
+}
+
+/*
+    Reads the SRTM tile at @path (1 or 3 arc second), its position is read from the file name (N45E006.hgt)
+    Throws:
+    InvalidPath if the file can not be read,
+    FormatNotSupported if the name is not a tile name or the file is not a square raster
+*/
+pub fn read_hgt(path : &Path) -> Result<DemTile, IOError> {
+    let str_path = path.to_str().unwrap_or("");
+    let grid = read_hgt_grid(path)?;
+    let bytes = std::fs::read(path).map_err(|err| IOError::invalid_path(str_path, err.to_string().as_str()))?;
+
+    let elevations = bytes.chunks_exact(2)
+        .map(|sample| i16::from_be_bytes([sample[0], sample[1]]))
+        .map(|elevation| if elevation == HGT_VOID { f32::NAN } else { elevation as f32 })
+        .collect();
+    Ok(DemTile { grid, elevations })
+}
+
+fn read_hgt_grid(path : &Path) -> Result<DemGrid, IOError> {
+    let str_path = path.to_str().unwrap_or("");
+    let name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("").to_ascii_uppercase();
+    let (lat, lon) = parse_hgt_name(&name).ok_or_else(
+        || IOError::format_not_supported(str_path, "SRTM tiles must be named after their south west corner (ex: N45E006.hgt)")
+    )?;
+
+    let size = std::fs::metadata(path).map_err(|err| IOError::invalid_path(str_path, err.to_string().as_str()))?.len();
+    let side = ((size / 2) as f64).sqrt().round() as usize;
+    if side < 2 || (side * side * 2) as u64 != size {
+        return Err(IOError::format_not_supported(str_path, "SRTM tiles must hold a square raster of 16 bit samples"));
+    }
+
+    let step = 1.0 / (side - 1) as f64;
+    Ok(DemGrid { west : lon as f64, north : lat as f64 + 1.0, lon_step : step, lat_step : step, width : side, height : side })
+}
+
+/*
+    Returns the (latitude, longitude) of the south west corner of the tile @name (N45E006, S12W077)
+*/
+fn parse_hgt_name(name : &str) -> Option<(i32, i32)> {
+    let name = name.get(..7)?;
+    let lat = name.get(1..3)?.parse::<i32>().ok()?;
+    let lon = name.get(4..7)?.parse::<i32>().ok()?;
+    let lat = match name.as_bytes()[0] { b'N' => lat, b'S' => -lat, _ => return None };
+    let lon = match name.as_bytes()[3] { b'E' => lon, b'W' => -lon, _ => return None };
+    Some((lat, lon))
+}
+
+/*
+    Reads the single band GeoTIFF at @path, the raster must be in geographic coordinates (WGS84) and georeferenced by a tie point and a pixel scale.
+    Samples equal to the GDAL no data value are kept as NaN
+    Throws:
+    InvalidPath if the file can not be opened,
+    BinaryParsingFail if the tiff can not be decoded,
+    FormatNotSupported if the raster is projected, has several bands or no georeference
+*/
+pub fn read_geotiff(path : &Path) -> Result<DemTile, IOError> {
+    let str_path = path.to_str().unwrap_or("");
+    let mut decoder = open_geotiff(path)?;
+    let grid = read_georeference(&mut decoder, str_path)?;
+    let nodata = decoder.get_tag_ascii_string(Tag::GdalNodata).ok()
+        .and_then(|value| value.trim().trim_end_matches('\0').parse::<f64>().ok());
+
+    let image = decoder.read_image().map_err(|err| IOError::binary_parser(str_path, err.to_string().as_str()))?;
+    let values : Vec<f64> = match image {
+        DecodingResult::U8(values) => values.into_iter().map(f64::from).collect(),
+        DecodingResult::U16(values) => values.into_iter().map(f64::from).collect(),
+        DecodingResult::U32(values) => values.into_iter().map(f64::from).collect(),
+        DecodingResult::U64(values) => values.into_iter().map(|value| value as f64).collect(),
+        DecodingResult::I8(values) => values.into_iter().map(f64::from).collect(),
+        DecodingResult::I16(values) => values.into_iter().map(f64::from).collect(),
+        DecodingResult::I32(values) => values.into_iter().map(f64::from).collect(),
+        DecodingResult::I64(values) => values.into_iter().map(|value| value as f64).collect(),
+        DecodingResult::F32(values) => values.into_iter().map(f64::from).collect(),
+        DecodingResult::F64(values) => values
+    };
+
+    let elevations = values.into_iter()
+        .map(|value| if nodata == Some(value) || !value.is_finite() { f32::NAN } else { value as f32 })
+        .collect();
+    Ok(DemTile { grid, elevations })
+}
+
+fn read_geotiff_grid(path : &Path) -> Result<DemGrid, IOError> {
+    let mut decoder = open_geotiff(path)?;
+    read_georeference(&mut decoder, path.to_str().unwrap_or(""))
+}
+
+fn open_geotiff(path : &Path) -> Result<Decoder<BufReader<File>>, IOError> {
+    let str_path = path.to_str().unwrap_or("");
+    let file = File::open(path).map_err(|err| IOError::invalid_path(str_path, err.to_string().as_str()))?;
+    Decoder::new(BufReader::new(file)).map_err(|err| IOError::binary_parser(str_path, err.to_string().as_str()))
+}
+
+fn read_georeference(decoder : &mut Decoder<BufReader<File>>, source : &str) -> Result<DemGrid, IOError> {
+    let tiff_error = |err : tiff::TiffError| IOError::binary_parser(source, err.to_string().as_str());
+
+    if !matches!(decoder.colortype().map_err(tiff_error)?, ColorType::Gray(_)) {
+        return Err(IOError::format_not_supported(source, "Only single band GeoTIFF elevation models are supported"));
+    }
+    let (width, height) = decoder.dimensions().map_err(tiff_error)?;
+
+    let geo_keys : Vec<u16> = decoder.find_tag_unsigned_vec(Tag::GeoKeyDirectoryTag).map_err(tiff_error)?.unwrap_or_default();
+    // Header of 4 values then (key, location, count, value) entries
+    let geo_key = |key : u16| geo_keys.get(4..).unwrap_or(&[]).chunks_exact(4)
+        .find(|entry| entry[0] == key && entry[1] == 0)
+        .map(|entry| entry[3]);
+    if geo_key(GEO_KEY_MODEL_TYPE).is_some_and(|model_type| model_type != MODEL_TYPE_GEOGRAPHIC) {
+        return Err(IOError::format_not_supported(source, "Only GeoTIFF in geographic coordinates (WGS84) are supported"));
+    }
+    let pixel_is_point = geo_key(GEO_KEY_RASTER_TYPE) == Some(RASTER_PIXEL_IS_POINT);
+
+    let missing_georeference = || IOError::format_not_supported(source, "GeoTIFF without a tie point and a pixel scale");
+    let scale = decoder.get_tag_f64_vec(Tag::ModelPixelScaleTag).map_err(|_| missing_georeference())?;
+    let tie_point = decoder.get_tag_f64_vec(Tag::ModelTiepointTag).map_err(|_| missing_georeference())?;
+    if scale.len() < 2 || tie_point.len() < 6 || scale[0] <= 0.0 || scale[1] <= 0.0 {
+        return Err(missing_georeference());
+    }
+
+    // The tie point is the corner of its pixel unless the raster is PixelIsPoint
+    let half_pixel = if pixel_is_point { 0.0 } else { 0.5 };
+    Ok(DemGrid {
+        west : tie_point[3] - (tie_point[0] - half_pixel) * scale[0],
+        north : tie_point[4] + (tie_point[1] - half_pixel) * scale[1],
+        lon_step : scale[0],
+        lat_step : scale[1],
+        width : width as usize,
+        height : height as usize
+    })
+}
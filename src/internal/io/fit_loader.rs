@@ -0,0 +1,402 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use crate::{errors::io_errors::IOError, internal::model::{spatial::points::{SensorPoint, SpatialPoint, keep_recorded}, track::common::{SpatialTrack, TrackInfo, TrackMetadata, TrackSegment, push_segment}}};
+
+const FIT_EPOCH_OFFSET : i64 = 631_065_600;                     // Seconds between the unix epoch and the FIT epoch (1989-12-31T00:00:00Z)
+const SEMICIRCLES_TO_DEGREES : f64 = 180.0 / 2_147_483_648.0;   // 2^31 semicircles make 180 degrees
+
+// Global message numbers
+const MESG_FILE_ID : u16 = 0;
+const MESG_SESSION : u16 = 18;
+const MESG_RECORD : u16 = 20;
+const MESG_EVENT : u16 = 21;
+
+// Field numbers of the used messages
+const FIELD_TIMESTAMP : u8 = 253;
+const FILE_ID_MANUFACTURER : u8 = 1;
+const FILE_ID_TIME_CREATED : u8 = 4;
+const SESSION_SPORT : u8 = 5;
+const RECORD_POSITION_LAT : u8 = 0;
+const RECORD_POSITION_LONG : u8 = 1;
+const RECORD_ALTITUDE : u8 = 2;
+const RECORD_ENHANCED_ALTITUDE : u8 = 78;
+const RECORD_HEART_RATE : u8 = 3;
+const RECORD_CADENCE : u8 = 4;
+const RECORD_SPEED : u8 = 6;
+const RECORD_POWER : u8 = 7;
+const RECORD_TEMPERATURE : u8 = 13;
+const RECORD_ENHANCED_SPEED : u8 = 73;
+const RECORD_GPS_ACCURACY : u8 = 31;
+const EVENT_EVENT : u8 = 0;
+const EVENT_EVENT_TYPE : u8 = 1;
+
+const EVENT_TIMER : u64 = 0;
+const EVENT_TYPE_STOP : u64 = 1;
+const EVENT_TYPE_STOP_ALL : u64 = 4;
+
+const CRC_TABLE : [u16; 16] = [
+    0x0000, 0xCC01, 0xD801, 0x1400, 0xF001, 0x3C00, 0x2800, 0xE401,
+    0xA001, 0x6C00, 0x7800, 0xB401, 0x5000, 0x9C01, 0x8801, 0x4400
+];
+
+struct FieldDefinition {
+    number : u8,
+    size : u8
+}
+
+struct MessageDefinition {
+    global_number : u16,
+    big_endian : bool,
+    fields : Vec<FieldDefinition>,
+    developer_size : usize      // Developer fields are skipped, only their total size is kept
+}
+
+/*
+    Bounds checked reader over the bytes of a FIT file
+*/
+struct FitCursor<'a> {
+    bytes : &'a [u8],
+    position : usize,
+    source : &'a str
+}
+
+impl<'a> FitCursor<'a> {
+    fn take(&mut self, count : usize) -> Result<&'a [u8], IOError> {
+        let end = self.position + count;
+        let slice = self.bytes.get(self.position..end).ok_or_else(|| {
+            IOError::binary_parser(self.source, format!("Unexpected end of file at byte {}", self.position).as_str())
+        })?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, IOError> {
+        Ok(self.take(1)?[0])
+    }
+}
+
+/*
+    Track under construction while the messages of a FIT file are decoded
+*/
+#[derive(Default)]
+struct FitTrackBuilder {
+    points : Vec<SpatialPoint>,
+    sensors : Vec<SensorPoint>,
+    segments : Vec<TrackSegment>,
+    tracks : Vec<TrackInfo>,
+    metadata : TrackMetadata,
+    segment_start : usize,
+    initial_timestamp : Option<u32>
+}
+
+/*
+    Loads a track from the FIT file (Garmin, Wahoo and other head units) found at @path
+ */
+pub fn load_fit(path : &Path) -> Result<SpatialTrack, IOError> {
+    let str_path = path.to_str().unwrap_or("unkown file path");
+    let bytes = std::fs::read(path).map_err(
+        |err| IOError::invalid_path(str_path, err.to_string().as_str())
+    )?;
+    read_fit(&bytes, str_path)
+}
+
+/*
+    Decodes the FIT @bytes read from @source into a track.
+    Positions are converted from semicircles to degrees, every timer stop starts a new segment and every chained FIT file a new track.
+    Records without a position fix are skipped, heart rate, cadence, power, temperature and speed of the records are kept as sensor values.
+ */
+pub fn read_fit(bytes : &[u8], source : &str) -> Result<SpatialTrack, IOError> {
+    let mut cursor = FitCursor { bytes, position: 0, source };
+    let mut builder = FitTrackBuilder::default();
+
+    while cursor.position < bytes.len() {
+        let file_start = cursor.position;
+        let header_size = cursor.byte()? as usize;
+        if header_size < 12 {
+            return Err(IOError::binary_parser(source, "Invalid FIT header size"));
+        }
+        let header = cursor.take(header_size - 1)?;
+        if &header[7..11] != b".FIT" {
+            return Err(IOError::binary_parser(source, "Missing .FIT signature"));
+        }
+        let data_size = u32::from_le_bytes([header[3], header[4], header[5], header[6]]) as usize;
+        let data_end = cursor.position + data_size;
+        if data_end + 2 > bytes.len() {
+            return Err(IOError::binary_parser(source, "Declared data size exceeds file size"));
+        }
+
+        let stored_crc = u16::from_le_bytes([bytes[data_end], bytes[data_end + 1]]);
+        if stored_crc != 0 {
+            let computed_crc = bytes[file_start..data_end].iter().fold(0u16, |crc, &byte| crc16(crc, byte));
+            if computed_crc != stored_crc {
+                return Err(IOError::binary_parser(source, "File checksum does not match"));
+            }
+        }
+
+        // Every chained FIT file is a separate activity
+        builder.close_segment();
+        builder.tracks.push(TrackInfo::default());
+
+        let mut definitions : [Option<MessageDefinition>; 16] = Default::default();
+        let mut last_timestamp : Option<u32> = None;
+
+        while cursor.position < data_end {
+            let record_header = cursor.byte()?;
+
+            if record_header & 0x80 != 0 {
+                // Compressed timestamp header, only data messages use it
+                let local_type = ((record_header >> 5) & 0x03) as usize;
+                let offset = (record_header & 0x1F) as u32;
+                let timestamp = last_timestamp.map(|last| {
+                    let mut timestamp = (last & !0x1F) + offset;
+                    if offset < (last & 0x1F) {
+                        timestamp += 0x20;
+                    }
+                    timestamp
+                });
+                let definition = definitions[local_type].as_ref().ok_or_else(
+                    || IOError::binary_parser(source, "Data message uses an undefined local message type")
+                )?;
+                let message = read_message(&mut cursor, definition)?;
+                last_timestamp = message.timestamp.or(timestamp);
+                builder.handle_message(&message, definition, last_timestamp);
+            } else if record_header & 0x40 != 0 {
+                let local_type = (record_header & 0x0F) as usize;
+                let has_developer_fields = record_header & 0x20 != 0;
+                definitions[local_type] = Some(read_definition(&mut cursor, has_developer_fields)?);
+            } else {
+                let local_type = (record_header & 0x0F) as usize;
+                let definition = definitions[local_type].as_ref().ok_or_else(
+                    || IOError::binary_parser(source, "Data message uses an undefined local message type")
+                )?;
+                let message = read_message(&mut cursor, definition)?;
+                if message.timestamp.is_some() {
+                    last_timestamp = message.timestamp;
+                }
+                builder.handle_message(&message, definition, message.timestamp);
+            }
+        }
+
+        if cursor.position != data_end {
+            return Err(IOError::binary_parser(source, "Last message overflows the declared data size"));
+        }
+        cursor.take(2)?;
+    }
+
+    builder.close_segment();
+
+    let start_time = builder.initial_timestamp
+        .and_then(fit_to_utc)
+        .unwrap_or_default();
+
+    Ok(SpatialTrack {
+        track: builder.points,
+        sensors: keep_recorded(builder.sensors),
+        start_time,
+        segments: builder.segments,
+        laps: Vec::new(),
+        tracks: builder.tracks,
+        routes: Vec::new(),
+        waypoints: Vec::new(),
+        metadata: builder.metadata
+    })
+}
+
+/*
+    Raw field values of a single data message
+*/
+struct FitMessage<'a> {
+    fields : Vec<(u8, &'a [u8])>,
+    timestamp : Option<u32>
+}
+
+impl<'a> FitMessage<'a> {
+    fn get(&self, number : u8) -> Option<&'a [u8]> {
+        self.fields.iter().find(|(field, _)| *field == number).map(|(_, raw)| *raw)
+    }
+}
+
+fn read_definition(cursor : &mut FitCursor, has_developer_fields : bool) -> Result<MessageDefinition, IOError> {
+    let header = cursor.take(5)?;
+    let big_endian = header[1] == 1;
+    let global_number = if big_endian {
+        u16::from_be_bytes([header[2], header[3]])
+    } else {
+        u16::from_le_bytes([header[2], header[3]])
+    };
+
+    let field_count = header[4] as usize;
+    let fields = cursor.take(field_count * 3)?
+        .chunks_exact(3)
+        .map(|field| FieldDefinition { number: field[0], size: field[1] })
+        .collect();
+
+    let mut developer_size = 0usize;
+    if has_developer_fields {
+        let developer_count = cursor.byte()? as usize;
+        developer_size = cursor.take(developer_count * 3)?
+            .chunks_exact(3)
+            .map(|field| field[1] as usize)
+            .sum();
+    }
+
+    Ok(MessageDefinition { global_number, big_endian, fields, developer_size })
+}
+
+fn read_message<'a>(cursor : &mut FitCursor<'a>, definition : &MessageDefinition) -> Result<FitMessage<'a>, IOError> {
+    let mut fields = Vec::with_capacity(definition.fields.len());
+    let mut timestamp = None;
+
+    for field in &definition.fields {
+        let raw = cursor.take(field.size as usize)?;
+        if field.number == FIELD_TIMESTAMP {
+            timestamp = read_uint(raw, definition.big_endian)
+                .filter(|&value| value != u32::MAX as u64)
+                .map(|value| value as u32);
+        }
+        fields.push((field.number, raw));
+    }
+    cursor.take(definition.developer_size)?;
+
+    Ok(FitMessage { fields, timestamp })
+}
+
+impl FitTrackBuilder {
+    fn close_segment(&mut self) {
+        push_segment(&mut self.segments, self.segment_start, self.points.len(), self.tracks.len().saturating_sub(1));
+        self.segment_start = self.points.len();
+    }
+
+    /*
+        Applies a decoded @message of type @definition to the track, @timestamp is the resolved time of the message
+    */
+    fn handle_message(&mut self, message : &FitMessage, definition : &MessageDefinition, timestamp : Option<u32>) {
+        let big_endian = definition.big_endian;
+        let uint = |number : u8| message.get(number).and_then(|raw| read_uint(raw, big_endian));
+
+        match definition.global_number {
+            MESG_RECORD => {
+                let lat = uint(RECORD_POSITION_LAT).filter(|&value| value != 0x7FFF_FFFF).map(|value| value as u32 as i32);
+                let lon = uint(RECORD_POSITION_LONG).filter(|&value| value != 0x7FFF_FFFF).map(|value| value as u32 as i32);
+                let (Some(lat), Some(lon)) = (lat, lon) else {
+                    return;
+                };
+
+                let elevation = uint(RECORD_ENHANCED_ALTITUDE).filter(|&value| value != u32::MAX as u64)
+                    .or_else(|| uint(RECORD_ALTITUDE).filter(|&value| value != u16::MAX as u64))
+                    .map(|value| value as f64 / 5.0 - 500.0);
+
+                let delta_seconds = timestamp.map(|timestamp| {
+                    let initial = *self.initial_timestamp.get_or_insert(timestamp);
+                    timestamp as f64 - initial as f64
+                });
+
+                self.points.push(SpatialPoint {
+                    lon: lon as f64 * SEMICIRCLES_TO_DEGREES,
+                    lat: lat as f64 * SEMICIRCLES_TO_DEGREES,
+                    elev: elevation,
+                    delta_seconds,
+                    hdop: None,
+                    accuracy: uint(RECORD_GPS_ACCURACY).filter(|&value| value != u8::MAX as u64).map(|value| value as f64)
+                });
+
+                // Speeds are stored in mm/s, temperature is the only signed field
+                self.sensors.push(SensorPoint {
+                    heart_rate: uint(RECORD_HEART_RATE).filter(|&value| value != u8::MAX as u64).map(|value| value as f32),
+                    cadence: uint(RECORD_CADENCE).filter(|&value| value != u8::MAX as u64).map(|value| value as f32),
+                    power: uint(RECORD_POWER).filter(|&value| value != u16::MAX as u64).map(|value| value as f32),
+                    temperature: uint(RECORD_TEMPERATURE).filter(|&value| value != i8::MAX as u64).map(|value| value as u8 as i8 as f32),
+                    speed: uint(RECORD_ENHANCED_SPEED).filter(|&value| value != u32::MAX as u64)
+                        .or_else(|| uint(RECORD_SPEED).filter(|&value| value != u16::MAX as u64))
+                        .map(|value| value as f32 / 1000.0)
+                });
+            }
+            MESG_EVENT => {
+                let is_timer_stop = uint(EVENT_EVENT) == Some(EVENT_TIMER)
+                    && matches!(uint(EVENT_EVENT_TYPE), Some(EVENT_TYPE_STOP) | Some(EVENT_TYPE_STOP_ALL));
+                if is_timer_stop {
+                    self.close_segment();
+                }
+            }
+            MESG_SESSION => {
+                if let (Some(sport), Some(track)) = (uint(SESSION_SPORT), self.tracks.last_mut()) {
+                    track.kind = Some(sport_name(sport));
+                }
+            }
+            MESG_FILE_ID => {
+                if let Some(manufacturer) = uint(FILE_ID_MANUFACTURER).filter(|&value| value != u16::MAX as u64) {
+                    self.metadata.creator = Some(manufacturer_name(manufacturer));
+                }
+                if let Some(time_created) = uint(FILE_ID_TIME_CREATED).filter(|&value| value != u32::MAX as u64) {
+                    self.metadata.time = fit_to_utc(time_created as u32);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/*
+    Reads an unsigned integer field of 1, 2, 4 or 8 bytes, array and string fields return None
+*/
+#[inline]
+fn read_uint(raw : &[u8], big_endian : bool) -> Option<u64> {
+    match (raw.len(), big_endian) {
+        (1, _) => Some(raw[0] as u64),
+        (2, false) => Some(u16::from_le_bytes([raw[0], raw[1]]) as u64),
+        (2, true) => Some(u16::from_be_bytes([raw[0], raw[1]]) as u64),
+        (4, false) => Some(u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as u64),
+        (4, true) => Some(u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]) as u64),
+        (8, false) => Some(u64::from_le_bytes(raw.try_into().ok()?)),
+        (8, true) => Some(u64::from_be_bytes(raw.try_into().ok()?)),
+        _ => None
+    }
+}
+
+fn fit_to_utc(timestamp : u32) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(timestamp as i64 + FIT_EPOCH_OFFSET, 0)
+}
+
+#[inline]
+fn crc16(crc : u16, byte : u8) -> u16 {
+    let mut tmp = CRC_TABLE[(crc & 0xF) as usize];
+    let mut crc = (crc >> 4) & 0x0FFF;
+    crc = crc ^ tmp ^ CRC_TABLE[(byte & 0xF) as usize];
+
+    tmp = CRC_TABLE[(crc & 0xF) as usize];
+    crc = (crc >> 4) & 0x0FFF;
+    crc ^ tmp ^ CRC_TABLE[((byte >> 4) & 0xF) as usize]
+}
+
+fn manufacturer_name(manufacturer : u64) -> String {
+    match manufacturer {
+        1 => "garmin".to_string(),
+        15 => "dynastream".to_string(),
+        23 => "suunto".to_string(),
+        32 => "wahoo_fitness".to_string(),
+        69 => "stages_cycling".to_string(),
+        70 => "sigmasport".to_string(),
+        71 => "tomtom".to_string(),
+        89 => "bryton".to_string(),
+        255 => "development".to_string(),
+        260 => "zwift".to_string(),
+        265 => "strava".to_string(),
+        294 => "coros".to_string(),
+        other => format!("manufacturer {}", other)
+    }
+}
+
+fn sport_name(sport : u64) -> String {
+    match sport {
+        0 => "generic".to_string(),
+        1 => "running".to_string(),
+        2 => "cycling".to_string(),
+        5 => "swimming".to_string(),
+        11 => "walking".to_string(),
+        17 => "hiking".to_string(),
+        21 => "e_biking".to_string(),
+        other => format!("sport {}", other)
+    }
+}
@@ -0,0 +1,349 @@
+use std::{fs::File, io::{BufReader, Read}, path::Path};
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::{errors::io_errors::IOError, internal::{io::geojson_writer::SENSOR_PROPERTIES, model::{spatial::points::{MatchPoint, SensorPoint, SpatialPoint, keep_recorded}, track::{common::{SpatialRoute, SpatialTrack, TrackInfo, TrackMetadata, TrackOrigin, Waypoint, push_segment}, riders::MatchedTrack}}}};
+
+// Position of a feature with the properties that apply to it (per-point properties of lines included)
+struct FeaturePoint {
+    point : SpatialPoint,
+    properties : Map<String, Value>
+}
+
+enum FeatureGeometry {
+    Lines(Vec<Vec<FeaturePoint>>),
+    Point(FeaturePoint)
+}
+
+struct Feature {
+    properties : Map<String, Value>,
+    geometry : FeatureGeometry
+}
+
+/*
+    Loads a track from the GeoJSON file with @path
+ */
+pub fn load_geojson(path : &Path) -> Result<SpatialTrack, IOError> {
+    let str_path = path.to_str().unwrap_or("unkown file path");
+
+    let file = File::open(path).map_err(
+        |err| {return IOError::invalid_path(str_path, err.to_string().as_str());}
+    )?;
+    read_geojson(BufReader::new(file), str_path)
+}
+
+/*
+    Reads a GeoJSON document (FeatureCollection, Feature or bare geometry) from @reader, @source is only used to report errors.
+    Every LineString/MultiLineString feature becomes a TrackInfo with a segment per line, or a route when its role property is "route".
+    Point features carrying a segment_index (GeoJsonLayout::Points) are track points, the other points are waypoints.
+    Point times are read from the time (or togeojson times) property, falling back to delta_seconds.
+    Sensor values are read from the heart_rate, cadence, power, temperature and speed properties.
+    Throws: JsonParsingFail if the document is not valid GeoJSON
+ */
+pub fn read_geojson<R : Read>(reader : R, source : &str) -> Result<SpatialTrack, IOError> {
+    let root : Value = serde_json::from_reader(reader).map_err(
+        |err| IOError::json_parser(source, err.to_string().as_str())
+    )?;
+    let collection_properties = root.get("properties").and_then(Value::as_object);
+    let features = read_features(&root, source)?;
+
+    let mut points : Vec<SpatialPoint> = Vec::new();
+    let mut sensors : Vec<SensorPoint> = Vec::new();
+    let mut segments = Vec::new();
+    let mut tracks : Vec<TrackInfo> = Vec::new();
+    let mut routes : Vec<SpatialRoute> = Vec::new();
+    let mut waypoints : Vec<Waypoint> = Vec::new();
+    let metadata = TrackMetadata {
+        name : collection_properties.and_then(|properties| string_property(properties, "name")),
+        description : collection_properties.and_then(|properties| string_property(properties, "description")),
+        author : collection_properties.and_then(|properties| string_property(properties, "author")),
+        creator : collection_properties.and_then(|properties| string_property(properties, "creator")),
+        time : collection_properties.and_then(|properties| time_property(properties, "time"))
+    };
+
+    let mut initial_stamp : Option<DateTime<Utc>> = None;
+
+    // Track and segment index of the point feature run being read
+    let mut point_run : Option<(u64, u64)> = None;
+    let mut segment_start = 0usize;
+
+    for feature in features {
+        match feature.geometry {
+            FeatureGeometry::Lines(lines) => {
+                push_segment(&mut segments, segment_start, points.len(), tracks.len().saturating_sub(1));
+                point_run = None;
+
+                let info = track_info(&feature.properties);
+                if string_property(&feature.properties, "role").as_deref() == Some("route") {
+                    routes.push(SpatialRoute {
+                        info,
+                        points : lines.into_iter().flatten().map(|feature_point| feature_point.point).collect()
+                    });
+                    segment_start = points.len();
+                    continue;
+                }
+
+                tracks.push(info);
+                for line in lines {
+                    let start = points.len();
+                    for feature_point in line {
+                        sensors.push(sensor_point(&feature_point.properties));
+                        points.push(timed_point(feature_point, &mut initial_stamp));
+                    }
+                    push_segment(&mut segments, start, points.len(), tracks.len() - 1);
+                }
+                segment_start = points.len();
+            }
+            FeatureGeometry::Point(feature_point) => {
+                let Some(segment_index) = feature_point.properties.get("segment_index").and_then(Value::as_u64) else {
+                    waypoints.push(Waypoint { info: track_info(&feature_point.properties), point: feature_point.point });
+                    continue;
+                };
+                let track_index = feature_point.properties.get("track_index").and_then(Value::as_u64).unwrap_or(0);
+
+                if point_run != Some((track_index, segment_index)) {
+                    push_segment(&mut segments, segment_start, points.len(), tracks.len().saturating_sub(1));
+                    segment_start = points.len();
+
+                    if point_run.is_none_or(|(run_track, _)| run_track != track_index) {
+                        tracks.push(track_info(&feature_point.properties));
+                    }
+                    point_run = Some((track_index, segment_index));
+                }
+                sensors.push(sensor_point(&feature_point.properties));
+                points.push(timed_point(feature_point, &mut initial_stamp));
+            }
+        }
+    }
+    push_segment(&mut segments, segment_start, points.len(), tracks.len().saturating_sub(1));
+
+    let start_time = initial_stamp
+        .or_else(|| collection_properties.and_then(|properties| time_property(properties, "start_time")))
+        .unwrap_or_default();
+
+    Ok(SpatialTrack {
+        track: points,
+        sensors: keep_recorded(sensors),
+        start_time,
+        segments,
+        laps: Vec::new(),
+        tracks,
+        routes,
+        waypoints,
+        metadata
+    })
+}
+
+/*
+    Reads a matched track written by track_export::matched_to_geojson (in any layout) from @reader, @source is only used to report errors.
+    The matched points are rebuilt from the per-point properties, the geometry only serves visualisation and is ignored.
+    Throws: JsonParsingFail if the document is not valid GeoJSON or a track or point property is missing
+ */
+pub fn read_matched_geojson<R : Read>(reader : R, source : &str) -> Result<MatchedTrack, IOError> {
+    let root : Value = serde_json::from_reader(reader).map_err(
+        |err| IOError::json_parser(source, err.to_string().as_str())
+    )?;
+    let empty = Map::new();
+    let collection_properties = root.get("properties").and_then(Value::as_object).unwrap_or(&empty);
+    let missing = |key : &str| IOError::json_parser(source, format!("Missing or invalid matched track property {:?}", key).as_str());
+
+    let bound_uuid = collection_properties.get("bound_uuid")
+        .and_then(Value::as_str)
+        .and_then(|uuid| Uuid::parse_str(uuid).ok())
+        .ok_or_else(|| missing("bound_uuid"))?;
+    let variant = collection_properties.get("variant").and_then(Value::as_u64).ok_or_else(|| missing("variant"))? as u32;
+    let projection = string_property(collection_properties, "projection").ok_or_else(|| missing("projection"))?;
+    let start_time = time_property(collection_properties, "start_time").ok_or_else(|| missing("start_time"))?;
+    let origin = collection_properties.get("origin")
+        .and_then(Value::as_array)
+        .and_then(|origin| Some(TrackOrigin { epsg_x: origin.first()?.as_f64()?, epsg_y: origin.get(1)?.as_f64()? }))
+        .ok_or_else(|| missing("origin"))?;
+
+    let mut track = Vec::new();
+    let mut sensors = Vec::new();
+    for feature in read_features(&root, source)? {
+        let feature_points = match feature.geometry {
+            FeatureGeometry::Lines(lines) => lines.into_iter().flatten().collect(),
+            FeatureGeometry::Point(feature_point) if feature_point.properties.contains_key("segment_index") => vec![feature_point],
+            FeatureGeometry::Point(_) => continue
+        };
+
+        for feature_point in feature_points {
+            let properties = &feature_point.properties;
+            let number = |key : &str| properties.get(key).and_then(Value::as_f64).ok_or_else(
+                || IOError::json_parser(source, format!("Missing or invalid property {:?} of matched point {}", key, track.len()).as_str())
+            );
+            let flag = |key : &str| properties.get(key).and_then(Value::as_bool).unwrap_or(false);
+
+            track.push(MatchPoint {
+                reference_index : number("reference_index")? as u32,
+                delta_seconds : number("delta_seconds")?,
+                direction_similarity : number("direction_similarity")? as f32,
+                lateral : number("lateral")? as f32,
+                distance_z : number("distance_z")? as f32,
+                // Matched tracks written before has_elevation was exported always had an elevation
+                has_elevation : properties.get("has_elevation").and_then(Value::as_bool).unwrap_or(true),
+                count_to_error : flag("count_to_error"),
+                segment_start : flag("segment_start"),
+                gap_end : flag("gap_end")
+            });
+            sensors.push(sensor_point(properties));
+        }
+    }
+
+    Ok(MatchedTrack {
+        bound_uuid,
+        variant,
+        projection,
+        start_time,
+        track_origin : origin,
+        track,
+        sensors : keep_recorded(sensors),
+        gaps : Vec::new()
+    })
+}
+
+/*
+    Collects the features of a FeatureCollection, a single Feature or a bare geometry in @root.
+    Only point and line geometries are read, the others (polygons, collections) are skipped.
+*/
+fn read_features(root : &Value, source : &str) -> Result<Vec<Feature>, IOError> {
+    let raw_features : Vec<(Option<&Value>, Map<String, Value>)> = match root.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => root.get("features")
+            .and_then(Value::as_array)
+            .ok_or_else(|| IOError::json_parser(source, "FeatureCollection without a features array"))?
+            .iter()
+            .map(|feature| (feature.get("geometry"), feature_properties(feature)))
+            .collect(),
+        Some("Feature") => vec![(root.get("geometry"), feature_properties(root))],
+        Some(_) => vec![(Some(root), Map::new())],
+        None => return Err(IOError::json_parser(source, "Document is not a GeoJSON object"))
+    };
+
+    let mut features = Vec::with_capacity(raw_features.len());
+    for (geometry, properties) in raw_features {
+        let Some(geometry) = geometry.filter(|geometry| !geometry.is_null()) else {
+            continue;
+        };
+        let coordinates = geometry.get("coordinates").unwrap_or(&Value::Null);
+        let coordinate_properties = properties.get("coordinateProperties").and_then(Value::as_object);
+
+        let geometry = match geometry.get("type").and_then(Value::as_str) {
+            Some("Point") => FeatureGeometry::Point(FeaturePoint {
+                point : read_position(coordinates, source)?,
+                properties : properties.clone()
+            }),
+            Some("LineString") => FeatureGeometry::Lines(vec![
+                read_line(coordinates, coordinate_properties, None, source)?
+            ]),
+            Some("MultiLineString") => FeatureGeometry::Lines(
+                coordinates.as_array()
+                    .ok_or_else(|| IOError::json_parser(source, "MultiLineString coordinates are not an array"))?
+                    .iter()
+                    .enumerate()
+                    .map(|(line_index, line)| read_line(line, coordinate_properties, Some(line_index), source))
+                    .collect::<Result<Vec<_>, IOError>>()?
+            ),
+            _ => continue
+        };
+        features.push(Feature { properties, geometry });
+    }
+
+    Ok(features)
+}
+
+/*
+    Reads the positions of a line, the per-point properties are taken from the arrays of @coordinate_properties
+    (indexed by @line_index first for MultiLineString)
+*/
+fn read_line(coordinates : &Value, coordinate_properties : Option<&Map<String, Value>>, line_index : Option<usize>, source : &str) -> Result<Vec<FeaturePoint>, IOError> {
+    let positions = coordinates.as_array()
+        .ok_or_else(|| IOError::json_parser(source, "Line coordinates are not an array"))?;
+
+    positions.iter().enumerate().map(|(index, position)| {
+        let mut properties = Map::new();
+        for (key, values) in coordinate_properties.into_iter().flatten() {
+            let values = match line_index {
+                Some(line_index) => values.get(line_index),
+                None => Some(values)
+            };
+            if let Some(value) = values.and_then(|values| values.get(index)).filter(|value| !value.is_null()) {
+                properties.insert(key.clone(), value.clone());
+            }
+        }
+
+        Ok(FeaturePoint { point: read_position(position, source)?, properties })
+    }).collect()
+}
+
+fn read_position(position : &Value, source : &str) -> Result<SpatialPoint, IOError> {
+    let invalid = || IOError::json_parser(source, format!("Invalid position {}", position).as_str());
+    let values = position.as_array().ok_or_else(invalid)?;
+
+    Ok(SpatialPoint {
+        lon : values.first().and_then(Value::as_f64).ok_or_else(invalid)?,
+        lat : values.get(1).and_then(Value::as_f64).ok_or_else(invalid)?,
+        elev : values.get(2).and_then(Value::as_f64),
+        delta_seconds : None,
+        hdop : None,
+        accuracy : None
+    })
+}
+
+/*
+    Completes the point of @feature_point with its time offset (relative to @initial_stamp, set by the first timed point), hdop and accuracy
+*/
+fn timed_point(feature_point : FeaturePoint, initial_stamp : &mut Option<DateTime<Utc>>) -> SpatialPoint {
+    let properties = &feature_point.properties;
+    let time = time_property(properties, "time").or_else(|| time_property(properties, "times"));
+
+    let delta_seconds = match time {
+        Some(time) => {
+            let initial = *initial_stamp.get_or_insert(time);
+            Some((time - initial).num_milliseconds() as f64 / 1000.0)
+        }
+        None => properties.get("delta_seconds").and_then(Value::as_f64)
+    };
+
+    SpatialPoint {
+        delta_seconds,
+        hdop : properties.get("hdop").and_then(Value::as_f64),
+        accuracy : properties.get("accuracy").and_then(Value::as_f64),
+        ..feature_point.point
+    }
+}
+
+fn sensor_point(properties : &Map<String, Value>) -> SensorPoint {
+    let mut sensor = SensorPoint::default();
+    for (key, channel) in SENSOR_PROPERTIES {
+        if let Some(value) = properties.get(key).and_then(Value::as_f64) {
+            sensor.set(channel, value as f32);
+        }
+    }
+    sensor
+}
+
+fn feature_properties(feature : &Value) -> Map<String, Value> {
+    feature.get("properties").and_then(Value::as_object).cloned().unwrap_or_default()
+}
+
+fn track_info(properties : &Map<String, Value>) -> TrackInfo {
+    TrackInfo {
+        name : string_property(properties, "name"),
+        description : string_property(properties, "description"),
+        kind : string_property(properties, "kind")
+    }
+}
+
+fn string_property(properties : &Map<String, Value>, key : &str) -> Option<String> {
+    properties.get(key).and_then(Value::as_str).map(|value| value.to_string())
+}
+
+fn time_property(properties : &Map<String, Value>, key : &str) -> Option<DateTime<Utc>> {
+    properties.get(key)
+        .and_then(Value::as_str)
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|date_time| date_time.with_timezone(&Utc))
+}
@@ -0,0 +1,160 @@
+use std::io::Write;
+
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+use crate::{errors::io_errors::IOError, internal::model::spatial::points::{SensorChannel, SensorPoint, SpatialPoint}};
+
+// Per-point property holding each sensor channel
+pub const SENSOR_PROPERTIES : [(&str, SensorChannel); 5] = [
+    ("heart_rate", SensorChannel::HeartRate),
+    ("cadence", SensorChannel::Cadence),
+    ("power", SensorChannel::Power),
+    ("temperature", SensorChannel::Temperature),
+    ("speed", SensorChannel::Speed)
+];
+
+/*
+    How the points of a line are written:
+    Lines - one LineString/MultiLineString feature per line, per-point properties as arrays in properties.coordinateProperties
+    Points - one Point feature per point, carrying the line properties, its own properties, track_index and segment_index
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeoJsonLayout {
+    #[default]
+    Lines,
+    Points
+}
+
+pub struct GeoJsonPoint {
+    pub point : SpatialPoint,
+    pub properties : Map<String, Value>
+}
+
+/*
+    A line of the document made of one or more segments
+*/
+pub struct GeoJsonLine {
+    pub properties : Map<String, Value>,
+    pub segments : Vec<Vec<GeoJsonPoint>>
+}
+
+/*
+    Writes a FeatureCollection made of @lines (in the given @layout) and the standalone @points to @writer.
+    @collection_properties are written as a foreign "properties" member of the collection.
+    @source is only used to report errors.
+    Throws: StreamError if the @writer fails
+ */
+pub fn write_geojson<W : Write>(
+    writer : &mut W,
+    source : &str,
+    layout : GeoJsonLayout,
+    collection_properties : Map<String, Value>,
+    lines : Vec<GeoJsonLine>,
+    points : Vec<GeoJsonPoint>
+) -> Result<(), IOError> {
+    let mut features = Vec::new();
+
+    for (track_index, line) in lines.into_iter().enumerate() {
+        match layout {
+            GeoJsonLayout::Lines => features.push(line_feature(line)),
+            GeoJsonLayout::Points => {
+                for (segment_index, segment) in line.segments.into_iter().enumerate() {
+                    for point in segment {
+                        let mut properties = line.properties.clone();
+                        properties.extend(point.properties);
+                        properties.insert("track_index".to_string(), json!(track_index));
+                        properties.insert("segment_index".to_string(), json!(segment_index));
+                        features.push(point_feature(&point.point, properties));
+                    }
+                }
+            }
+        }
+    }
+
+    features.extend(points.into_iter().map(|point| point_feature(&point.point, point.properties)));
+
+    let collection = json!({
+        "type": "FeatureCollection",
+        "properties": collection_properties,
+        "features": features
+    });
+
+    serde_json::to_writer(&mut *writer, &collection)
+        .map_err(|err| IOError::stream_error(source, err.to_string().as_str()))?;
+    writer.flush()
+        .map_err(|err| IOError::stream_error(source, err.to_string().as_str()))
+}
+
+/*
+    Builds a LineString (single segment) or MultiLineString feature, per-point properties are transposed into
+    properties.coordinateProperties, one array per key (an array per segment for MultiLineString), missing values are null
+*/
+fn line_feature(line : GeoJsonLine) -> Value {
+    let is_multi = line.segments.len() != 1;
+
+    let mut keys : Vec<String> = Vec::new();
+    for point in line.segments.iter().flatten() {
+        for key in point.properties.keys() {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+    }
+
+    let mut coordinate_properties = Map::new();
+    for key in keys {
+        let mut per_segment = line.segments.iter().map(|segment| {
+            Value::Array(segment.iter().map(|point| point.properties.get(&key).cloned().unwrap_or(Value::Null)).collect())
+        });
+        let values = if is_multi { Value::Array(per_segment.collect()) } else { per_segment.next().unwrap_or(Value::Null) };
+        coordinate_properties.insert(key, values);
+    }
+
+    let mut properties = line.properties;
+    if !coordinate_properties.is_empty() {
+        properties.insert("coordinateProperties".to_string(), Value::Object(coordinate_properties));
+    }
+
+    let mut lines = line.segments.iter().map(|segment| {
+        Value::Array(segment.iter().map(|point| position(&point.point)).collect())
+    });
+    let geometry = if is_multi {
+        json!({ "type": "MultiLineString", "coordinates": lines.collect::<Vec<Value>>() })
+    } else {
+        json!({ "type": "LineString", "coordinates": lines.next().unwrap_or(Value::Array(Vec::new())) })
+    };
+
+    json!({
+        "type": "Feature",
+        "geometry": geometry,
+        "properties": properties
+    })
+}
+
+/*
+    Adds the recorded channels of @sensor to the per-point @properties (look at SENSOR_PROPERTIES)
+*/
+pub fn insert_sensor_properties(properties : &mut Map<String, Value>, sensor : &SensorPoint) {
+    for (key, channel) in SENSOR_PROPERTIES {
+        if let Some(value) = sensor.get(channel) {
+            properties.insert(key.to_string(), json!(value));
+        }
+    }
+}
+
+fn point_feature(point : &SpatialPoint, properties : Map<String, Value>) -> Value {
+    json!({
+        "type": "Feature",
+        "geometry": { "type": "Point", "coordinates": position(point) },
+        "properties": properties
+    })
+}
+
+fn position(point : &SpatialPoint) -> Value {
+    match point.elev {
+        Some(elev) => json!([point.lon, point.lat, elev]),
+        None => json!([point.lon, point.lat])
+    }
+}
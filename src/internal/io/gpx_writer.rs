@@ -0,0 +1,229 @@
+use std::{fmt::Write as _, io::Write};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::{errors::io_errors::IOError, internal::{io::xml_util::escape_text, model::{spatial::points::{SensorPoint, SpatialPoint}, track::common::{SpatialRoute, TrackInfo, TrackMetadata, Waypoint}}}};
+
+// Namespace of the values written in <extensions> (distance along the reference...)
+pub const EXTENSIONS_NAMESPACE : &str = "urn:gps-analyzer:gpx:1";
+const EXTENSIONS_PREFIX : &str = "gpsa";
+const TRACK_POINT_EXTENSION_NAMESPACE : &str = "http://www.garmin.com/xmlschemas/TrackPointExtension/v2";
+const TRACK_POINT_EXTENSION_PREFIX : &str = "gpxtpx";
+
+/*
+    Streaming GPX 1.1 writer, every element is written to the underlying writer as soon as it is given.
+    Elements must be given in the order required by the GPX schema: waypoints, then routes, then tracks.
+    Extension values are written as <gpsa:name>value</gpsa:name> in the EXTENSIONS_NAMESPACE.
+    Sensor values are written in a Garmin TrackPointExtension (v2), power has no place there and is written as <gpsa:power>.
+*/
+pub struct GpxWriter<W : Write> {
+    writer : W,
+    source : String,        // Only used to report errors
+    in_track : bool,
+    in_segment : bool
+}
+
+impl<W : Write> GpxWriter<W> {
+    /*
+        Starts the document on @writer with the file @metadata, @source is only used to report errors
+        Throws: StreamError if the @writer fails
+    */
+    pub fn start(writer : W, source : &str, metadata : &TrackMetadata) -> Result<Self, IOError> {
+        let mut gpx_writer = GpxWriter { writer, source : source.to_string(), in_track : false, in_segment : false };
+
+        let creator = metadata.creator.as_deref().unwrap_or("GPS Analyzer");
+        let mut text = String::new();
+        let _ = writeln!(text, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(
+            text,
+            r#"<gpx version="1.1" creator="{}" xmlns="http://www.topografix.com/GPX/1/1" xmlns:{}="{}" xmlns:{}="{}">"#,
+            escape_text(creator), EXTENSIONS_PREFIX, EXTENSIONS_NAMESPACE, TRACK_POINT_EXTENSION_PREFIX, TRACK_POINT_EXTENSION_NAMESPACE
+        );
+
+        let has_metadata = metadata.name.is_some() || metadata.description.is_some() || metadata.author.is_some() || metadata.time.is_some();
+        if has_metadata {
+            text.push_str("<metadata>");
+            push_text_element(&mut text, "name", metadata.name.as_deref());
+            push_text_element(&mut text, "desc", metadata.description.as_deref());
+            if let Some(author) = &metadata.author {
+                let _ = write!(text, "<author><name>{}</name></author>", escape_text(author));
+            }
+            if let Some(time) = metadata.time {
+                let _ = write!(text, "<time>{}</time>", format_time(time));
+            }
+            text.push_str("</metadata>\n");
+        }
+
+        gpx_writer.emit(&text)?;
+        Ok(gpx_writer)
+    }
+
+    /*
+        Writes a <wpt> for @waypoint with the given @extensions
+    */
+    pub fn write_waypoint(&mut self, waypoint : &Waypoint, extensions : &[(&str, String)]) -> Result<(), IOError> {
+        let mut text = String::new();
+        push_point(&mut text, "wpt", &waypoint.point, None, Some(&waypoint.info), None, extensions);
+        self.emit(&text)
+    }
+
+    /*
+        Writes a <rte> holding every point of @route
+    */
+    pub fn write_route(&mut self, route : &SpatialRoute) -> Result<(), IOError> {
+        let mut text = String::from("<rte>");
+        push_info(&mut text, &route.info);
+        text.push('\n');
+        for point in &route.points {
+            push_point(&mut text, "rtept", point, None, None, None, &[]);
+        }
+        text.push_str("</rte>\n");
+        self.emit(&text)
+    }
+
+    /*
+        Opens a <trk> described by @info, the open track (and segment) is closed first
+    */
+    pub fn start_track(&mut self, info : &TrackInfo) -> Result<(), IOError> {
+        self.end_track()?;
+
+        let mut text = String::from("<trk>");
+        push_info(&mut text, info);
+        text.push('\n');
+        self.in_track = true;
+        self.emit(&text)
+    }
+
+    /*
+        Opens a <trkseg> in the open track, the open segment is closed first
+    */
+    pub fn start_segment(&mut self) -> Result<(), IOError> {
+        if !self.in_track {
+            self.start_track(&TrackInfo::default())?;
+        }
+        self.end_segment()?;
+        self.in_segment = true;
+        self.emit("<trkseg>\n")
+    }
+
+    /*
+        Writes a <trkpt> for @point recorded at @time with its @sensor values and the given @extensions in the open segment
+    */
+    pub fn write_track_point(&mut self, point : &SpatialPoint, time : Option<DateTime<Utc>>, sensor : Option<&SensorPoint>, extensions : &[(&str, String)]) -> Result<(), IOError> {
+        if !self.in_segment {
+            self.start_segment()?;
+        }
+        let mut text = String::new();
+        push_point(&mut text, "trkpt", point, time, None, sensor, extensions);
+        self.emit(&text)
+    }
+
+    pub fn end_segment(&mut self) -> Result<(), IOError> {
+        if !self.in_segment {
+            return Ok(());
+        }
+        self.in_segment = false;
+        self.emit("</trkseg>\n")
+    }
+
+    pub fn end_track(&mut self) -> Result<(), IOError> {
+        self.end_segment()?;
+        if !self.in_track {
+            return Ok(());
+        }
+        self.in_track = false;
+        self.emit("</trk>\n")
+    }
+
+    /*
+        Closes every open element and the document, returns the underlying writer
+        Throws: StreamError if the writer fails
+    */
+    pub fn finish(mut self) -> Result<W, IOError> {
+        self.end_track()?;
+        self.emit("</gpx>\n")?;
+        self.writer.flush().map_err(|err| IOError::stream_error(&self.source, err.to_string().as_str()))?;
+        Ok(self.writer)
+    }
+
+    fn emit(&mut self, text : &str) -> Result<(), IOError> {
+        self.writer.write_all(text.as_bytes())
+            .map_err(|err| IOError::stream_error(&self.source, err.to_string().as_str()))
+    }
+}
+
+/*
+    Returns @time in the format used by GPX (UTC, Z suffix, fractional seconds only when present)
+*/
+pub fn format_time(time : DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn push_point(
+    text : &mut String,
+    tag : &str,
+    point : &SpatialPoint,
+    time : Option<DateTime<Utc>>,
+    info : Option<&TrackInfo>,
+    sensor : Option<&SensorPoint>,
+    extensions : &[(&str, String)]
+) {
+    let _ = write!(text, r#"<{} lat="{:.7}" lon="{:.7}">"#, tag, point.lat, point.lon);
+    if let Some(elev) = point.elev {
+        let _ = write!(text, "<ele>{:.2}</ele>", elev);
+    }
+    if let Some(time) = time {
+        let _ = write!(text, "<time>{}</time>", format_time(time));
+    }
+    if let Some(info) = info {
+        push_info(text, info);
+    }
+    if let Some(hdop) = point.hdop {
+        let _ = write!(text, "<hdop>{:.1}</hdop>", hdop);
+    }
+    let sensor = sensor.filter(|sensor| !sensor.is_empty());
+    if !extensions.is_empty() || sensor.is_some() {
+        text.push_str("<extensions>");
+        if let Some(sensor) = sensor {
+            push_sensor(text, sensor);
+        }
+        for (name, value) in extensions {
+            let _ = write!(text, "<{prefix}:{name}>{value}</{prefix}:{name}>", prefix = EXTENSIONS_PREFIX, name = name, value = escape_text(value));
+        }
+        text.push_str("</extensions>");
+    }
+    let _ = writeln!(text, "</{}>", tag);
+}
+
+/*
+    Writes the TrackPointExtension of @sensor, its children follow the order of the schema (atemp, hr, cad, speed)
+*/
+fn push_sensor(text : &mut String, sensor : &SensorPoint) {
+    let channels = [("atemp", sensor.temperature), ("hr", sensor.heart_rate), ("cad", sensor.cadence), ("speed", sensor.speed)];
+    if channels.iter().any(|(_, value)| value.is_some()) {
+        let _ = write!(text, "<{}:TrackPointExtension>", TRACK_POINT_EXTENSION_PREFIX);
+        for (name, value) in channels {
+            if let Some(value) = value {
+                // hr and cad are unsigned integers in the schema
+                let value = if matches!(name, "hr" | "cad") { format!("{}", value.round() as u32) } else { format!("{}", value) };
+                let _ = write!(text, "<{prefix}:{name}>{value}</{prefix}:{name}>", prefix = TRACK_POINT_EXTENSION_PREFIX, name = name, value = value);
+            }
+        }
+        let _ = write!(text, "</{}:TrackPointExtension>", TRACK_POINT_EXTENSION_PREFIX);
+    }
+    if let Some(power) = sensor.power {
+        let _ = write!(text, "<{prefix}:power>{value}</{prefix}:power>", prefix = EXTENSIONS_PREFIX, value = power);
+    }
+}
+
+fn push_info(text : &mut String, info : &TrackInfo) {
+    push_text_element(text, "name", info.name.as_deref());
+    push_text_element(text, "desc", info.description.as_deref());
+    push_text_element(text, "type", info.kind.as_deref());
+}
+
+fn push_text_element(text : &mut String, tag : &str, value : Option<&str>) {
+    if let Some(value) = value {
+        let _ = write!(text, "<{}>{}</{}>", tag, escape_text(value), tag);
+    }
+}
@@ -0,0 +1,272 @@
+use std::{fs::File, io::{BufRead, BufReader, Read}, path::Path};
+
+use quick_xml::{Reader, events::Event};
+
+use crate::{errors::io_errors::IOError, internal::{io::{time_parser, xml_util::unescape_text}, model::{spatial::points::SpatialPoint, track::common::{SpatialTrack, TrackInfo, TrackMetadata, Waypoint, push_segment}}}};
+
+// Element whose text content is currently being read
+#[derive(Clone, Copy, PartialEq)]
+enum TextField {
+    None,
+    Name,
+    Description,
+    Coordinates,
+    When,
+    Coord
+}
+
+// Geometry element currently being read
+#[derive(Clone, Copy, PartialEq)]
+enum Geometry {
+    None,
+    Point,
+    LineString,
+    Track
+}
+
+/*
+    Loads a track from the KML (Google Earth) file with @path
+ */
+pub fn load_kml(path : &Path) -> Result<SpatialTrack, IOError> {
+    let str_path = path.to_str().unwrap_or("unkown file path");
+
+    const FILE_BUFFER_SIZE : usize = 64 * 1024;
+
+    let file = File::open(path).map_err(
+        |err| {return IOError::xml_reader(str_path, err.to_string().as_str());}
+    )?;
+    read_kml(BufReader::with_capacity(FILE_BUFFER_SIZE, file), str_path)
+}
+
+/*
+    Loads a track from the KMZ (zipped KML) file with @path, the archive root document (doc.kml) is preferred over any other kml entry
+ */
+pub fn load_kmz(path : &Path) -> Result<SpatialTrack, IOError> {
+    let str_path = path.to_str().unwrap_or("unkown file path");
+
+    let file = File::open(path).map_err(
+        |err| {return IOError::invalid_path(str_path, err.to_string().as_str());}
+    )?;
+    read_kmz(BufReader::new(file), str_path)
+}
+
+/*
+    Reads a KMZ archive from @reader, @source is only used to report errors
+ */
+pub fn read_kmz<R : Read + std::io::Seek>(reader : R, source : &str) -> Result<SpatialTrack, IOError> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(
+        |err| IOError::binary_parser(source, err.to_string().as_str())
+    )?;
+
+    let document_name = archive.file_names()
+        .filter(|name| name.to_ascii_lowercase().ends_with(".kml"))
+        .min_by_key(|name| (!name.eq_ignore_ascii_case("doc.kml"), name.matches('/').count()))
+        .map(|name| name.to_string())
+        .ok_or_else(|| IOError::format_not_supported(source, "KMZ archive does not contain a kml document"))?;
+
+    let document = archive.by_name(&document_name).map_err(
+        |err| IOError::binary_parser(source, err.to_string().as_str())
+    )?;
+    read_kml(BufReader::new(document), source)
+}
+
+/*
+    Reads a KML document from @reader, @source is only used to report errors.
+    Every Placemark with line geometry becomes a TrackInfo and each of its LineString or gx:Track elements a segment.
+    Named Point placemarks are loaded as waypoints.
+ */
+pub fn read_kml<R : BufRead>(reader : R, source : &str) -> Result<SpatialTrack, IOError> {
+    const INITIAL_ALLOCATION_SIZE : usize = 12_000;
+
+    let mut reader = Reader::from_reader(reader);
+    reader.trim_text(true);
+
+    let mut xml_buffer = Vec::with_capacity(INITIAL_ALLOCATION_SIZE);
+    let mut points : Vec<SpatialPoint> = Vec::with_capacity(INITIAL_ALLOCATION_SIZE);
+    let mut segments = Vec::new();
+    let mut tracks : Vec<TrackInfo> = Vec::new();
+    let mut waypoints : Vec<Waypoint> = Vec::new();
+    let mut metadata = TrackMetadata::default();
+
+    let mut initial_stamp : Option<f64> = None;
+    let mut text_field = TextField::None;
+    let mut geometry = Geometry::None;
+
+    let mut placemark : Option<TrackInfo> = None;
+    let mut placemark_track : Option<usize> = None;     // Index in tracks once the placemark holds line geometry
+    let mut placemark_point : Option<SpatialPoint> = None;
+
+    // gx:Track stores times and coordinates in two parallel lists
+    let mut track_times : Vec<f64> = Vec::new();
+    let mut track_coords : Vec<SpatialPoint> = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut xml_buffer).map_err(
+            |err| { IOError::xml_reader(source, err.to_string().as_str())}
+        )? {
+
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"name" => text_field = TextField::Name,
+                b"description" => text_field = TextField::Description,
+                b"coordinates" => text_field = TextField::Coordinates,
+                b"when" => text_field = TextField::When,
+                b"coord" => text_field = TextField::Coord,
+                b"Placemark" => {
+                    placemark = Some(TrackInfo::default());
+                    placemark_track = None;
+                    placemark_point = None;
+                }
+                b"Point" => geometry = Geometry::Point,
+                b"LineString" => geometry = Geometry::LineString,
+                b"Track" => {
+                    geometry = Geometry::Track;
+                    track_times.clear();
+                    track_coords.clear();
+                }
+                _ => {}
+            }
+
+            Event::Text(element) => {
+                match text_field {
+                    TextField::Name | TextField::Description => {
+                        let text = unescape_text(element.as_ref());
+                        let info = match placemark.as_mut() {
+                            Some(info) => info,
+                            None => {
+                                if text_field == TextField::Name && metadata.name.is_none() {
+                                    metadata.name = Some(text);
+                                } else if text_field == TextField::Description && metadata.description.is_none() {
+                                    metadata.description = Some(text);
+                                }
+                                text_field = TextField::None;
+                                xml_buffer.clear();
+                                continue;
+                            }
+                        };
+                        if text_field == TextField::Name {
+                            info.name = Some(text);
+                        } else {
+                            info.description = Some(text);
+                        }
+                    }
+                    TextField::Coordinates => {
+                        let str_elem = std::str::from_utf8(element.as_ref()).map_err(
+                            |err| IOError::xml_parser(source, err.to_string().as_str())
+                        )?;
+                        let parsed = parse_coordinates(str_elem, source)?;
+
+                        match geometry {
+                            Geometry::Point => placemark_point = parsed.first().copied(),
+                            Geometry::LineString => {
+                                let track_index = *placemark_track.get_or_insert_with(|| {
+                                    tracks.push(placemark.clone().unwrap_or_default());
+                                    tracks.len() - 1
+                                });
+                                let start = points.len();
+                                points.extend(parsed);
+                                push_segment(&mut segments, start, points.len(), track_index);
+                            }
+                            _ => {}
+                        }
+                    }
+                    TextField::When if geometry == Geometry::Track => {
+                        let str_elem = std::str::from_utf8(element.as_ref()).map_err(
+                            |err| IOError::xml_parser(source, err.to_string().as_str())
+                        )?;
+                        track_times.push(time_parser::parse_xml_timestamp(str_elem, source)?);
+                    }
+                    TextField::Coord if geometry == Geometry::Track => {
+                        let str_elem = std::str::from_utf8(element.as_ref()).map_err(
+                            |err| IOError::xml_parser(source, err.to_string().as_str())
+                        )?;
+                        track_coords.push(parse_tuple(str_elem.split_whitespace(), str_elem, source)?);
+                    }
+                    _ => {}
+                }
+
+                text_field = TextField::None;
+            }
+
+            Event::End(element) => match element.local_name().as_ref() {
+                b"Track" if geometry == Geometry::Track => {
+                    let track_index = *placemark_track.get_or_insert_with(|| {
+                        tracks.push(placemark.clone().unwrap_or_default());
+                        tracks.len() - 1
+                    });
+
+                    let start = points.len();
+                    for (index, coord) in track_coords.drain(..).enumerate() {
+                        let delta_seconds = track_times.get(index).map(|&time| time - *initial_stamp.get_or_insert(time));
+                        points.push(SpatialPoint { delta_seconds, ..coord });
+                    }
+                    push_segment(&mut segments, start, points.len(), track_index);
+                    geometry = Geometry::None;
+                }
+                b"Point" | b"LineString" => geometry = Geometry::None,
+                b"Placemark" => {
+                    let info = placemark.take().unwrap_or_default();
+                    match placemark_track {
+                        // Names may follow the geometry inside a placemark
+                        Some(track_index) => tracks[track_index] = info,
+                        None => {
+                            if let Some(point) = placemark_point.take() {
+                                if info.name.is_some() {
+                                    waypoints.push(Waypoint { info, point });
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => text_field = TextField::None
+            }
+
+            Event::Eof => break,
+            _ => {}
+        }
+
+        xml_buffer.clear();
+    }
+
+    Ok(SpatialTrack {
+        track: points,
+        sensors: Vec::new(),
+        start_time: initial_stamp.map(|stamp| time_parser::xml_timestamp_to_utc(stamp, source)).transpose()?.unwrap_or_default(),
+        segments,
+        laps: Vec::new(),
+        tracks,
+        routes: Vec::new(),
+        waypoints,
+        metadata
+    })
+}
+
+/*
+    Parses a KML coordinate list ("lon,lat[,alt] lon,lat[,alt] ...")
+*/
+fn parse_coordinates(text : &str, source : &str) -> Result<Vec<SpatialPoint>, IOError> {
+    text.split_whitespace()
+        .map(|tuple| parse_tuple(tuple.split(','), tuple, source))
+        .collect()
+}
+
+/*
+    Parses a single coordinate tuple given as its @components (lon, lat and an optional altitude)
+*/
+fn parse_tuple<'a>(mut components : impl Iterator<Item = &'a str>, tuple : &str, source : &str) -> Result<SpatialPoint, IOError> {
+    let mut next_value = |field : &str| -> Result<Option<f64>, IOError> {
+        components.next()
+            .map(|value| value.parse::<f64>().map_err(
+                |_| IOError::xml_parser(source, format!("Invalid data for f64 conversion in {} of coordinate {:?}", field, tuple).as_str())
+            ))
+            .transpose()
+    };
+
+    let lon = next_value("longitude")?;
+    let lat = next_value("latitude")?;
+    let elev = next_value("altitude")?;
+
+    match (lon, lat) {
+        (Some(lon), Some(lat)) => Ok(SpatialPoint { lon, lat, elev, delta_seconds: None, hdop: None, accuracy: None }),
+        _ => Err(IOError::xml_parser(source, format!("Incomplete coordinate {:?}", tuple).as_str()))
+    }
+}
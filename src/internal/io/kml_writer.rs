@@ -0,0 +1,104 @@
+use std::io::Write;
+
+use crate::{errors::io_errors::IOError, internal::{io::xml_util::escape_text, model::{analysis::Severity, spatial::points::SpatialPoint, track::common::{TrackInfo, TrackMetadata, Waypoint}}}};
+
+/*
+    A line drawn in the KML document, lines without a severity use the neutral track style
+*/
+pub struct KmlLine {
+    pub info : TrackInfo,
+    pub severity : Option<Severity>,
+    pub points : Vec<SpatialPoint>
+}
+
+// Style id and line color (aabbggrr as expected by KML), severity styles are indexed by the Severity value
+const TRACK_STYLE : (&str, &str) = ("track", "ffff901e");
+const SEVERITY_STYLES : [(&str, &str); 5] = [
+    ("severity-ok", "ff00c800"),
+    ("severity-minor", "ff00ffff"),
+    ("severity-moderate", "ff008cff"),
+    ("severity-severe", "ff0000ff"),
+    ("severity-max", "ffd30094"),
+];
+
+const LINE_WIDTH : f32 = 4.0;
+
+/*
+    Writes a KML document made of @lines and @waypoints to @writer, every line is a Placemark styled by its severity.
+    @source is only used to report errors.
+    Throws: StreamError if the @writer fails
+ */
+pub fn write_kml<W : Write>(writer : &mut W, source : &str, metadata : &TrackMetadata, lines : &[KmlLine], waypoints : &[Waypoint]) -> Result<(), IOError> {
+    write_document(writer, metadata, lines, waypoints).map_err(
+        |err| IOError::stream_error(source, err.to_string().as_str())
+    )
+}
+
+fn write_document<W : Write>(writer : &mut W, metadata : &TrackMetadata, lines : &[KmlLine], waypoints : &[Waypoint]) -> std::io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(writer, "<Document>")?;
+    write_text_element(writer, "name", metadata.name.as_deref())?;
+    write_text_element(writer, "description", metadata.description.as_deref())?;
+
+    write_style(writer, TRACK_STYLE.0, TRACK_STYLE.1)?;
+    for (id, color) in SEVERITY_STYLES {
+        write_style(writer, id, color)?;
+    }
+
+    for line in lines.iter().filter(|line| !line.points.is_empty()) {
+        writeln!(writer, "<Placemark>")?;
+        write_text_element(writer, "name", line.info.name.as_deref())?;
+        write_text_element(writer, "description", line.info.description.as_deref())?;
+        writeln!(writer, "<styleUrl>#{}</styleUrl>", style_id(line.severity))?;
+        writeln!(writer, "<LineString>")?;
+        writeln!(writer, "<tessellate>1</tessellate>")?;
+        write!(writer, "<coordinates>")?;
+        for point in &line.points {
+            write_coordinate(writer, point)?;
+            write!(writer, " ")?;
+        }
+        writeln!(writer, "</coordinates>")?;
+        writeln!(writer, "</LineString>")?;
+        writeln!(writer, "</Placemark>")?;
+    }
+
+    for waypoint in waypoints {
+        writeln!(writer, "<Placemark>")?;
+        write_text_element(writer, "name", waypoint.info.name.as_deref())?;
+        write_text_element(writer, "description", waypoint.info.description.as_deref())?;
+        write!(writer, "<Point><coordinates>")?;
+        write_coordinate(writer, &waypoint.point)?;
+        writeln!(writer, "</coordinates></Point>")?;
+        writeln!(writer, "</Placemark>")?;
+    }
+
+    writeln!(writer, "</Document>")?;
+    writeln!(writer, "</kml>")?;
+    writer.flush()
+}
+
+fn style_id(severity : Option<Severity>) -> &'static str {
+    match severity {
+        None => TRACK_STYLE.0,
+        Some(severity) => SEVERITY_STYLES[severity as usize].0
+    }
+}
+
+fn write_style<W : Write>(writer : &mut W, id : &str, color : &str) -> std::io::Result<()> {
+    writeln!(writer, r#"<Style id="{}"><LineStyle><color>{}</color><width>{}</width></LineStyle></Style>"#, id, color, LINE_WIDTH)
+}
+
+fn write_text_element<W : Write>(writer : &mut W, tag : &str, text : Option<&str>) -> std::io::Result<()> {
+    match text {
+        Some(text) => writeln!(writer, "<{}>{}</{}>", tag, escape_text(text), tag),
+        None => Ok(())
+    }
+}
+
+fn write_coordinate<W : Write>(writer : &mut W, point : &SpatialPoint) -> std::io::Result<()> {
+    match point.elev {
+        Some(elev) => write!(writer, "{:.7},{:.7},{:.1}", point.lon, point.lat, elev),
+        None => write!(writer, "{:.7},{:.7}", point.lon, point.lat)
+    }
+}
@@ -0,0 +1,254 @@
+use std::{fs::File, io::{BufRead, BufReader}, path::Path};
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::{errors::io_errors::IOError, internal::model::{spatial::points::{SensorPoint, SpatialPoint, keep_recorded}, track::common::{SpatialTrack, TrackInfo, TrackMetadata, TrackSegment}}};
+
+const SECONDS_PER_DAY : f64 = 86_400.0;
+const KNOTS_TO_METERS_PER_SECOND : f64 = 1852.0 / 3600.0;
+
+// Position fix of a single receiver epoch, merged from every sentence that shares its time of day
+#[derive(Default)]
+struct NmeaFix {
+    time_of_day : f64,
+    day_index : u32,            // Number of midnights passed since the first epoch of the log
+    date : Option<NaiveDate>,   // Only known from RMC
+    lat : Option<f64>,
+    lon : Option<f64>,
+    altitude : Option<f64>,
+    hdop : Option<f64>,
+    speed : Option<f64>         // Speed over ground from RMC, in m/s
+}
+
+/*
+    Loads a track from the NMEA 0183 log with @path
+ */
+pub fn load_nmea(path : &Path) -> Result<SpatialTrack, IOError> {
+    let str_path = path.to_str().unwrap_or("unkown file path");
+
+    const FILE_BUFFER_SIZE : usize = 64 * 1024;
+
+    let file = File::open(path).map_err(
+        |err| {return IOError::invalid_path(str_path, err.to_string().as_str());}
+    )?;
+    read_nmea(BufReader::with_capacity(FILE_BUFFER_SIZE, file), str_path)
+}
+
+/*
+    Reads NMEA 0183 sentences from @reader, @source is only used to report errors.
+    RMC and GGA sentences (any talker, GP, GN, GL...) sharing a time of day are merged into one point:
+    RMC provides the date and the speed over ground (kept as sensor speed), GGA the fix quality, HDOP and altitude.
+    Sentences with a bad checksum, void RMC fixes and GGA sentences without a fix are skipped, as is every other sentence type.
+    The date rolls over whenever the time of day goes back past midnight, so logs crossing midnight stay continuous even without RMC.
+    Throws: StreamError if @reader fails
+ */
+pub fn read_nmea<R : BufRead>(mut reader : R, source : &str) -> Result<SpatialTrack, IOError> {
+    const INITIAL_ALLOCATION_SIZE : usize = 12_000;
+
+    let mut fixes : Vec<NmeaFix> = Vec::with_capacity(INITIAL_ALLOCATION_SIZE);
+    let mut current : Option<NmeaFix> = None;
+    let mut day_index = 0u32;
+    let mut line = Vec::with_capacity(128);
+
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line).map_err(
+            |err| IOError::stream_error(source, err.to_string().as_str())
+        )?;
+        if read == 0 {
+            break;
+        }
+
+        let Some(fields) = sentence_fields(&line) else {
+            continue;
+        };
+        let sentence_type = fields[0].get(2..).unwrap_or("");
+        if sentence_type != "RMC" && sentence_type != "GGA" {
+            continue;
+        }
+
+        let Some(time_of_day) = fields.get(1).and_then(|time| parse_time_of_day(time)) else {
+            continue;
+        };
+
+        let fix = match current.take() {
+            Some(fix) if fix.time_of_day == time_of_day => fix,
+            previous => {
+                if let Some(previous) = previous {
+                    // Jumping back by more than half a day is a midnight crossing, not a late sentence
+                    if previous.time_of_day - time_of_day > SECONDS_PER_DAY / 2.0 {
+                        day_index += 1;
+                    }
+                    fixes.push(previous);
+                }
+                NmeaFix { time_of_day, day_index, ..NmeaFix::default() }
+            }
+        };
+        let fix = current.insert(fix);
+
+        match sentence_type {
+            "RMC" => read_rmc(&fields, fix),
+            _ => read_gga(&fields, fix)
+        }
+    }
+    fixes.extend(current);
+
+    Ok(fixes_to_track(fixes))
+}
+
+/*
+    Returns the comma separated fields of the sentence in @line (the first field is the address, ex: GPRMC),
+    None if the line is not a sentence or its checksum does not match
+*/
+fn sentence_fields(line : &[u8]) -> Option<Vec<&str>> {
+    let line = std::str::from_utf8(line).ok()?.trim();
+    let body = line.strip_prefix('$')?;
+
+    let body = match body.split_once('*') {
+        Some((body, checksum)) => {
+            let expected = u8::from_str_radix(checksum.get(..2)?, 16).ok()?;
+            let computed = body.bytes().fold(0u8, |checksum, byte| checksum ^ byte);
+            if expected != computed {
+                return None;
+            }
+            body
+        }
+        None => body
+    };
+
+    let fields : Vec<&str> = body.split(',').collect();
+    if fields[0].len() < 5 {
+        return None;
+    }
+    Some(fields)
+}
+
+/*
+    $--RMC,hhmmss.ss,status,llll.ll,a,yyyyy.yy,a,speed,course,ddmmyy,...
+*/
+fn read_rmc(fields : &[&str], fix : &mut NmeaFix) {
+    let field = |index : usize| fields.get(index).copied().unwrap_or("");
+
+    if let Some(date) = parse_date(field(9)) {
+        fix.date = Some(date);
+    }
+    if field(2) != "A" {
+        return;
+    }
+    if let (Some(lat), Some(lon)) = (parse_coordinate(field(3), field(4)), parse_coordinate(field(5), field(6))) {
+        if fix.lat.is_none() {
+            fix.lat = Some(lat);
+            fix.lon = Some(lon);
+        }
+        fix.speed = field(7).parse::<f64>().ok().map(|knots| knots * KNOTS_TO_METERS_PER_SECOND);
+    }
+}
+
+/*
+    $--GGA,hhmmss.ss,llll.ll,a,yyyyy.yy,a,quality,satellites,hdop,altitude,M,...
+*/
+fn read_gga(fields : &[&str], fix : &mut NmeaFix) {
+    let field = |index : usize| fields.get(index).copied().unwrap_or("");
+
+    // 0 is an invalid fix, 6 is dead reckoning and 7 a manually entered position, none of them were measured
+    let quality = field(6).parse::<u8>().unwrap_or(0);
+    if quality == 0 || quality >= 6 {
+        return;
+    }
+    if let (Some(lat), Some(lon)) = (parse_coordinate(field(2), field(3)), parse_coordinate(field(4), field(5))) {
+        // GGA positions take precedence, they belong to the same fix as the altitude
+        fix.lat = Some(lat);
+        fix.lon = Some(lon);
+        fix.hdop = field(8).parse::<f64>().ok();
+        fix.altitude = field(9).parse::<f64>().ok();
+    }
+}
+
+/*
+    Builds the track from the collected @fixes, fixes without a position are dropped.
+    The date of the first fix is derived from the first RMC date and the number of midnights between them.
+*/
+fn fixes_to_track(fixes : Vec<NmeaFix>) -> SpatialTrack {
+    let first_date = fixes.iter()
+        .find_map(|fix| fix.date.map(|date| date - chrono::Days::new(fix.day_index as u64)))
+        .unwrap_or_default();
+
+    let seconds = |fix : &NmeaFix| fix.day_index as f64 * SECONDS_PER_DAY + fix.time_of_day;
+
+    let positioned : Vec<&NmeaFix> = fixes.iter().filter(|fix| fix.lat.is_some() && fix.lon.is_some()).collect();
+    let initial_seconds = positioned.first().map(|fix| seconds(fix)).unwrap_or(0.0);
+
+    let points : Vec<SpatialPoint> = positioned.iter().map(|fix| {
+        SpatialPoint {
+            lon : fix.lon.unwrap_or_default(),
+            lat : fix.lat.unwrap_or_default(),
+            elev : fix.altitude,
+            delta_seconds : Some(seconds(fix) - initial_seconds),
+            hdop : fix.hdop,
+            accuracy : None
+        }
+    }).collect();
+    let sensors : Vec<SensorPoint> = positioned.iter()
+        .map(|fix| SensorPoint { speed : fix.speed.map(|speed| speed as f32), ..SensorPoint::default() })
+        .collect();
+
+    let start_time : DateTime<Utc> = first_date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+        + chrono::Duration::milliseconds((initial_seconds * 1000.0).round() as i64);
+
+    SpatialTrack {
+        segments : TrackSegment::single(points.len()),
+        laps : Vec::new(),
+        track : points,
+        sensors : keep_recorded(sensors),
+        start_time,
+        tracks : vec![TrackInfo::default()],
+        routes : Vec::new(),
+        waypoints : Vec::new(),
+        metadata : TrackMetadata::default()
+    }
+}
+
+/*
+    Parses hhmmss(.sss) into seconds of the day
+*/
+fn parse_time_of_day(value : &str) -> Option<f64> {
+    if value.len() < 6 || !value.is_char_boundary(6) {
+        return None;
+    }
+    let hours = value[0..2].parse::<u32>().ok()?;
+    let minutes = value[2..4].parse::<u32>().ok()?;
+    let seconds = value[4..].parse::<f64>().ok()?;
+    if hours > 23 || minutes > 59 || !(0.0..61.0).contains(&seconds) {
+        return None;
+    }
+    Some((hours * 3600 + minutes * 60) as f64 + seconds)
+}
+
+/*
+    Parses ddmmyy, two digit years from 80 on are in the 1900s (GPS time starts in 1980), the rest in the 2000s
+*/
+fn parse_date(value : &str) -> Option<NaiveDate> {
+    if value.len() != 6 || !value.is_ascii() {
+        return None;
+    }
+    let day = value[0..2].parse::<u32>().ok()?;
+    let month = value[2..4].parse::<u32>().ok()?;
+    let year = value[4..6].parse::<i32>().ok()?;
+    let century = if year >= 80 { 1900 } else { 2000 };
+    NaiveDate::from_ymd_opt(century + year, month, day)
+}
+
+/*
+    Parses a (d)ddmm.mmmm @value with its N/S/E/W @hemisphere into signed decimal degrees
+*/
+fn parse_coordinate(value : &str, hemisphere : &str) -> Option<f64> {
+    let raw = value.parse::<f64>().ok()?;
+    let degrees = (raw / 100.0).trunc();
+    let decimal = degrees + (raw - degrees * 100.0) / 60.0;
+
+    match hemisphere {
+        "N" | "E" => Some(decimal),
+        "S" | "W" => Some(-decimal),
+        _ => None
+    }
+}
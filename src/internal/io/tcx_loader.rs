@@ -0,0 +1,244 @@
+use std::{fs::File, io::{BufRead, BufReader}, path::Path};
+
+use quick_xml::{Reader, events::Event};
+
+use crate::{errors::io_errors::IOError, internal::{io::{time_parser, xml_util::{read_attribute, sensor_channel, unescape_text}}, model::{spatial::points::{SensorChannel, SensorPoint, SpatialPoint, keep_recorded}, track::common::{SpatialRoute, SpatialTrack, TrackInfo, TrackMetadata, Waypoint, push_segment}}}};
+
+// Element whose text content is currently being read
+#[derive(Clone, Copy, PartialEq)]
+enum TextField {
+    None,
+    Time,
+    Latitude,
+    Longitude,
+    Altitude,
+    Name,
+    Id,
+    Notes,
+    Sensor(SensorChannel)
+}
+
+// Point being read, all values are optional in TCX
+#[derive(Default)]
+struct TcxPoint {
+    time : Option<f64>,     // Seconds since the unix epoch
+    lat : Option<f64>,
+    lon : Option<f64>,
+    altitude : Option<f64>,
+    sensor : SensorPoint
+}
+
+/*
+    Loads a track from the TCX (Training Center XML) file with @path
+ */
+pub fn load_tcx(path : &Path) -> Result<SpatialTrack, IOError> {
+    let str_path = path.to_str().unwrap_or("unkown file path");
+
+    const FILE_BUFFER_SIZE : usize = 64 * 1024;
+
+    let file = File::open(path).map_err(
+        |err| {return IOError::xml_reader(str_path, err.to_string().as_str());}
+    )?;
+    read_tcx(BufReader::with_capacity(FILE_BUFFER_SIZE, file), str_path)
+}
+
+/*
+    Reads a TCX document from @reader, @source is only used to report errors.
+    Every <Activity> becomes a TrackInfo, every <Track> after the first of a lap (timer pause) starts a new segment.
+    Laps do not break the recording, every <Lap> after the first is only marked in SpatialTrack.laps.
+    Courses are loaded as routes and their course points as waypoints, trackpoints without a position are skipped.
+    Heart rate, cadence and the ActivityExtension values (Speed, Watts, RunCadence) of trackpoints are kept as sensor values, malformed ones are ignored.
+ */
+pub fn read_tcx<R : BufRead>(reader : R, source : &str) -> Result<SpatialTrack, IOError> {
+    const INITIAL_ALLOCATION_SIZE : usize = 12_000;
+
+    let mut reader = Reader::from_reader(reader);
+    reader.trim_text(true);
+
+    let mut xml_buffer = Vec::with_capacity(INITIAL_ALLOCATION_SIZE);
+    let mut points = Vec::with_capacity(INITIAL_ALLOCATION_SIZE);
+    let mut sensors = Vec::with_capacity(INITIAL_ALLOCATION_SIZE);
+    let mut segments = Vec::new();
+    let mut laps : Vec<usize> = Vec::new();
+    let mut tracks : Vec<TrackInfo> = Vec::new();
+    let mut routes : Vec<SpatialRoute> = Vec::new();
+    let mut waypoints : Vec<Waypoint> = Vec::new();
+    let mut metadata = TrackMetadata::default();
+
+    let mut initial_stamp : Option<f64> = None;
+    let mut text_field = TextField::None;
+
+    let mut in_activity = false;
+    let mut in_course = false;
+    let mut in_course_point = false;
+    let mut in_author = false;
+    let mut in_creator = false;
+    let mut in_point = false;
+    let mut in_heart_rate = false;
+    let mut segment_start = 0usize;
+    let mut activity_start = 0usize;
+    let mut lap_tracks = 0usize;
+
+    let mut point = TcxPoint::default();
+    let mut point_info = TrackInfo::default();
+
+    loop {
+        match reader.read_event_into(&mut xml_buffer).map_err(
+            |err| { IOError::xml_reader(source, err.to_string().as_str())}
+        )? {
+
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"Time" => text_field = TextField::Time,
+                b"LatitudeDegrees" => text_field = TextField::Latitude,
+                b"LongitudeDegrees" => text_field = TextField::Longitude,
+                b"AltitudeMeters" => text_field = TextField::Altitude,
+                b"Name" => text_field = TextField::Name,
+                b"Id" => text_field = TextField::Id,
+                b"Notes" => text_field = TextField::Notes,
+                b"Activity" => {
+                    in_activity = true;
+                    tracks.push(TrackInfo { kind: read_attribute(&element, b"Sport"), ..TrackInfo::default() });
+                    segment_start = points.len();
+                    activity_start = points.len();
+                }
+                b"Course" => {
+                    in_course = true;
+                    routes.push(SpatialRoute::default());
+                }
+                b"CoursePoint" => in_course_point = true,
+                b"Author" => in_author = true,
+                b"Creator" => in_creator = true,
+                // Laps (auto laps, lap button) split a continuous recording, they are only marked
+                b"Lap" if in_activity => {
+                    if points.len() > activity_start && laps.last() != Some(&points.len()) {
+                        laps.push(points.len());
+                    }
+                    lap_tracks = 0;
+                }
+                // Every lap holds a <Track>, a second one in the same lap follows a pause of the timer
+                b"Track" if in_activity => {
+                    if lap_tracks > 0 {
+                        push_segment(&mut segments, segment_start, points.len(), tracks.len().saturating_sub(1));
+                        segment_start = points.len();
+                    }
+                    lap_tracks += 1;
+                }
+                b"Trackpoint" => in_point = true,
+                b"HeartRateBpm" if in_point => in_heart_rate = true,
+                b"Value" if in_heart_rate => text_field = TextField::Sensor(SensorChannel::HeartRate),
+                name if in_point => {
+                    if let Some(channel) = sensor_channel(name) {
+                        text_field = TextField::Sensor(channel);
+                    }
+                }
+                _ => {}
+            }
+
+            Event::Text(element) => {
+                let str_elem = std::str::from_utf8(element.as_ref()).map_err(
+                    |err| IOError::xml_parser(source, err.to_string().as_str())
+                )?;
+
+                match text_field {
+                    TextField::Time if in_point && in_activity => {
+                        point.time = Some(time_parser::parse_xml_timestamp(str_elem, source)?);
+                    }
+                    TextField::Latitude => point.lat = Some(parse_f64(str_elem, "LatitudeDegrees", source)?),
+                    TextField::Longitude => point.lon = Some(parse_f64(str_elem, "LongitudeDegrees", source)?),
+                    TextField::Altitude => point.altitude = Some(parse_f64(str_elem, "AltitudeMeters", source)?),
+                    TextField::Sensor(channel) => {
+                        if let Ok(value) = str_elem.trim().parse::<f32>() {
+                            point.sensor.set(channel, value);
+                        }
+                    }
+                    TextField::Name if in_course_point => point_info.name = Some(unescape_text(element.as_ref())),
+                    TextField::Notes if in_course_point => point_info.description = Some(unescape_text(element.as_ref())),
+                    TextField::Name if in_author => metadata.author = Some(unescape_text(element.as_ref())),
+                    TextField::Name if in_creator => metadata.creator = Some(unescape_text(element.as_ref())),
+                    TextField::Name if in_course => {
+                        if let Some(route) = routes.last_mut() {
+                            route.info.name = Some(unescape_text(element.as_ref()));
+                        }
+                    }
+                    TextField::Id if in_activity => {
+                        if let Some(track) = tracks.last_mut() {
+                            track.name = Some(unescape_text(element.as_ref()));
+                        }
+                    }
+                    TextField::Notes if in_activity => {
+                        if let Some(track) = tracks.last_mut() {
+                            track.description = Some(unescape_text(element.as_ref()));
+                        }
+                    }
+                    _ => {}
+                }
+
+                text_field = TextField::None;
+            }
+
+            Event::End(element) => match element.local_name().as_ref() {
+                b"Trackpoint" => {
+                    let finished = std::mem::take(&mut point);
+                    if let (Some(lat), Some(lon)) = (finished.lat, finished.lon) {
+                        let delta_seconds = finished.time.map(|time| {
+                            let initial = *initial_stamp.get_or_insert(time);
+                            time - initial
+                        });
+                        let spatial_point = SpatialPoint { lon, lat, elev: finished.altitude, delta_seconds, hdop: None, accuracy: None };
+
+                        if in_activity {
+                            points.push(spatial_point);
+                            sensors.push(finished.sensor);
+                        } else if let Some(route) = routes.last_mut().filter(|_| in_course) {
+                            route.points.push(SpatialPoint { delta_seconds: None, ..spatial_point });
+                        }
+                    }
+                    in_point = false;
+                }
+                b"HeartRateBpm" => in_heart_rate = false,
+                b"CoursePoint" => {
+                    let finished = std::mem::take(&mut point);
+                    if let (Some(lat), Some(lon)) = (finished.lat, finished.lon) {
+                        waypoints.push(Waypoint {
+                            info : std::mem::take(&mut point_info),
+                            point : SpatialPoint { lon, lat, elev: finished.altitude, delta_seconds: None, hdop: None, accuracy: None }
+                        });
+                    }
+                    in_course_point = false;
+                }
+                b"Activity" => {
+                    push_segment(&mut segments, segment_start, points.len(), tracks.len().saturating_sub(1));
+                    segment_start = points.len();
+                    in_activity = false;
+                }
+                b"Course" => in_course = false,
+                b"Author" => in_author = false,
+                b"Creator" => in_creator = false,
+                _ => text_field = TextField::None
+            }
+
+            Event::Eof => break,
+            _ => {}
+        }
+
+        xml_buffer.clear();
+    }
+
+    Ok(SpatialTrack {
+        track: points,
+        sensors: keep_recorded(sensors),
+        start_time: initial_stamp.map(|stamp| time_parser::xml_timestamp_to_utc(stamp, source)).transpose()?.unwrap_or_default(),
+        segments,
+        laps,
+        tracks,
+        routes,
+        waypoints,
+        metadata
+    })
+}
+
+fn parse_f64(value : &str, field : &str, source : &str) -> Result<f64, IOError> {
+    value.parse::<f64>().map_err(
+        |_| IOError::xml_parser(source, format!("Invalid data for f64 conversion in {} field {:?}", field, value).as_str())
+    )
+}
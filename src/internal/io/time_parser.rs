@@ -0,0 +1,171 @@
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+
+use crate::errors::io_errors::IOError;
+
+const SECONDS_PER_DAY : i64 = 86_400;
+
+/*
+    Parses an RFC 3339 / ISO 8601 timestamp into seconds since the unix epoch (UTC).
+    The usual layout YYYY-MM-DDTHH:MM:SS[.fff][Z|±HH:MM|±HHMM|±HH] is parsed by hand (fast path), timestamps without a zone are taken as UTC.
+    Anything else (basic format, week dates...) goes through chrono.
+    Returns None if the timestamp is malformed
+*/
+pub fn parse_timestamp(text : &str) -> Option<f64> {
+    let text = text.trim();
+    fast_parse_timestamp(text.as_bytes()).or_else(|| slow_parse_timestamp(text))
+}
+
+/*
+    Parses the timestamp @text of a xml document, @source is only used to report errors
+    Throws: XmlParsingFail if the timestamp is malformed
+*/
+pub fn parse_xml_timestamp(text : &str, source : &str) -> Result<f64, IOError> {
+    parse_timestamp(text).ok_or_else(
+        || IOError::xml_parser(source, format!("Invalid timestamp {:?}, expected RFC 3339 (ex: 2024-05-01T10:00:00Z)", text).as_str())
+    )
+}
+
+/*
+    Returns the time @delta_seconds after @start_time (millisecond precision), None if it is not a number or out of the range of DateTime
+*/
+pub fn offset_time(start_time : DateTime<Utc>, delta_seconds : f64) -> Option<DateTime<Utc>> {
+    let milliseconds = (delta_seconds * 1000.0).round();
+    if !milliseconds.is_finite() {
+        return None;
+    }
+    TimeDelta::try_milliseconds(milliseconds as i64).and_then(|delta| start_time.checked_add_signed(delta))
+}
+
+/*
+    Converts @seconds since the unix epoch into a UTC date time (millisecond precision), None if it is not a number or out of the range of DateTime
+*/
+pub fn timestamp_to_utc(seconds : f64) -> Option<DateTime<Utc>> {
+    let milliseconds = (seconds * 1000.0).round();
+    if !milliseconds.is_finite() {
+        return None;
+    }
+    DateTime::from_timestamp_millis(milliseconds as i64)
+}
+
+/*
+    Converts the @seconds of a timestamp read from a xml document into a UTC date time, @source is only used to report errors
+    Throws: XmlParsingFail if the timestamp is out of the range of DateTime
+*/
+pub fn xml_timestamp_to_utc(seconds : f64, source : &str) -> Result<DateTime<Utc>, IOError> {
+    timestamp_to_utc(seconds).ok_or_else(
+        || IOError::xml_parser(source, format!("Timestamp {} s is out of the range of dates", seconds).as_str())
+    )
+}
+
+fn fast_parse_timestamp(bytes : &[u8]) -> Option<f64> {
+    // YYYY-MM-DDTHH:MM:SS is the shortest accepted form
+    if bytes.len() < 19 || bytes[4] != b'-' || bytes[7] != b'-' || bytes[13] != b':' || bytes[16] != b':' {
+        return None;
+    }
+    if !matches!(bytes[10], b'T' | b't' | b' ') {
+        return None;
+    }
+
+    let year = digits(bytes, 0, 4)? as i64;
+    let month = digits(bytes, 5, 2)?;
+    let day = digits(bytes, 8, 2)?;
+    let hour = digits(bytes, 11, 2)?;
+    let minute = digits(bytes, 14, 2)?;
+    let second = digits(bytes, 17, 2)?;
+
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut index = 19;
+    let mut fraction = 0.0;
+    if bytes.get(index) == Some(&b'.') || bytes.get(index) == Some(&b',') {
+        index += 1;
+        let start = index;
+        let mut scale = 1.0;
+        while let Some(byte) = bytes.get(index).filter(|byte| byte.is_ascii_digit()) {
+            scale /= 10.0;
+            fraction += (byte - b'0') as f64 * scale;
+            index += 1;
+        }
+        if index == start {
+            return None;
+        }
+    }
+
+    let offset_seconds = match bytes.get(index) {
+        None => 0,
+        Some(b'Z') | Some(b'z') if index + 1 == bytes.len() => 0,
+        Some(sign @ (b'+' | b'-')) => {
+            let offset_hours = digits(bytes, index + 1, 2)? as i64;
+            let offset_minutes = match bytes.len() - index {
+                3 => 0,
+                5 => digits(bytes, index + 3, 2)? as i64,
+                6 if bytes[index + 3] == b':' => digits(bytes, index + 4, 2)? as i64,
+                _ => return None
+            };
+            if offset_hours > 23 || offset_minutes > 59 {
+                return None;
+            }
+            let offset = offset_hours * 3600 + offset_minutes * 60;
+            if *sign == b'-' { -offset } else { offset }
+        }
+        _ => return None
+    };
+
+    let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY
+        + (hour * 3600 + minute * 60 + second) as i64
+        - offset_seconds;
+    Some(seconds as f64 + fraction)
+}
+
+fn slow_parse_timestamp(text : &str) -> Option<f64> {
+    let to_seconds = |date_time : DateTime<Utc>| date_time.timestamp_millis() as f64 / 1000.0;
+
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(text) {
+        return Some(to_seconds(date_time.with_timezone(&Utc)));
+    }
+    for pattern in ["%Y%m%dT%H%M%S%.f%#z", "%Y-%m-%dT%H:%M%#z"] {
+        if let Ok(date_time) = DateTime::parse_from_str(text, pattern) {
+            return Some(to_seconds(date_time.with_timezone(&Utc)));
+        }
+    }
+    for pattern in ["%Y%m%dT%H%M%S%.fZ", "%Y%m%dT%H%M%S%.f", "%Y-%m-%dT%H:%MZ", "%Y-%m-%dT%H:%M"] {
+        if let Ok(date_time) = NaiveDateTime::parse_from_str(text, pattern) {
+            return Some(to_seconds(date_time.and_utc()));
+        }
+    }
+    None
+}
+
+/*
+    Reads @count ascii digits of @bytes starting at @start
+*/
+#[inline(always)]
+fn digits(bytes : &[u8], start : usize, count : usize) -> Option<u32> {
+    bytes.get(start..start + count)?
+        .iter()
+        .try_fold(0u32, |value, byte| byte.is_ascii_digit().then(|| value * 10 + (byte - b'0') as u32))
+}
+
+fn days_in_month(year : i64, month : u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+/*
+    Number of days between 1970-01-01 and the given civil date (proleptic gregorian calendar)
+*/
+fn days_from_civil(year : i64, month : u32, day : u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+// Number of leading bytes TrackFormat::sniff needs to recognize a track
+pub const SNIFF_LENGTH : usize = 4096;
+
+/*
+    Track file formats understood by the loaders
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackFormat {
+    Gpx,
+    Fit,
+    Tcx,
+    Kml,
+    Kmz,
+    Nmea,
+    GeoJson,
+    Csv,
+    Tsv
+}
+
+impl TrackFormat {
+    pub fn from_extension(extension : &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "gpx" => Some(TrackFormat::Gpx),
+            "fit" => Some(TrackFormat::Fit),
+            "tcx" => Some(TrackFormat::Tcx),
+            "kml" => Some(TrackFormat::Kml),
+            "kmz" => Some(TrackFormat::Kmz),
+            "nmea" | "nma" => Some(TrackFormat::Nmea),
+            "geojson" => Some(TrackFormat::GeoJson),
+            "csv" => Some(TrackFormat::Csv),
+            "tsv" => Some(TrackFormat::Tsv),
+            _ => None
+        }
+    }
+
+    /*
+        Detects the format of a track from its first bytes (SNIFF_LENGTH are enough), regardless of any file name:
+        FIT header signature, zip archive (KMZ), root element of xml documents, json object, NMEA sentence,
+        otherwise the delimiter of the first line for csv and tsv.
+        Returns None if the content does not look like any supported format
+    */
+    pub fn sniff(bytes : &[u8]) -> Option<Self> {
+        if bytes.len() >= 12 && matches!(bytes[0], 12 | 14) && &bytes[8..12] == b".FIT" {
+            return Some(TrackFormat::Fit);
+        }
+        if bytes.starts_with(b"PK\x03\x04") {
+            return Some(TrackFormat::Kmz);
+        }
+
+        let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+        let start = bytes.iter().position(|byte| !byte.is_ascii_whitespace())?;
+        let bytes = &bytes[start..];
+
+        match bytes[0] {
+            b'<' => match xml_root_name(bytes)? {
+                b"gpx" => Some(TrackFormat::Gpx),
+                b"TrainingCenterDatabase" => Some(TrackFormat::Tcx),
+                b"kml" => Some(TrackFormat::Kml),
+                _ => None
+            },
+            b'{' => Some(TrackFormat::GeoJson),
+            b'$' | b'!' => Some(TrackFormat::Nmea),
+            _ => {
+                let first_line = bytes.split(|&byte| byte == b'\n').next().unwrap_or(bytes);
+                let tabs = first_line.iter().filter(|&&byte| byte == b'\t').count();
+                let commas = first_line.iter().filter(|&&byte| byte == b',').count();
+                if tabs > 0 && tabs >= commas {
+                    Some(TrackFormat::Tsv)
+                } else if commas > 0 {
+                    Some(TrackFormat::Csv)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    pub fn from_path(path : &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Self::from_extension)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TrackFormat::Gpx => "gpx",
+            TrackFormat::Fit => "fit",
+            TrackFormat::Tcx => "tcx",
+            TrackFormat::Kml => "kml",
+            TrackFormat::Kmz => "kmz",
+            TrackFormat::Nmea => "nmea",
+            TrackFormat::GeoJson => "geojson",
+            TrackFormat::Csv => "csv",
+            TrackFormat::Tsv => "tsv"
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            TrackFormat::Gpx => "application/gpx+xml; charset=utf-8",
+            TrackFormat::Fit => "application/vnd.ant.fit",
+            TrackFormat::Tcx => "application/vnd.garmin.tcx+xml; charset=utf-8",
+            TrackFormat::Kml => "application/vnd.google-earth.kml+xml; charset=utf-8",
+            TrackFormat::Kmz => "application/vnd.google-earth.kmz",
+            TrackFormat::Nmea => "text/plain; charset=us-ascii",
+            TrackFormat::GeoJson => "application/geo+json",
+            TrackFormat::Csv => "text/csv; charset=utf-8",
+            TrackFormat::Tsv => "text/tab-separated-values; charset=utf-8"
+        }
+    }
+}
+
+/*
+    Returns the local name (namespace prefix removed) of the first element of the xml document in @bytes,
+    the declaration, processing instructions, comments and doctype are skipped
+*/
+fn xml_root_name(bytes : &[u8]) -> Option<&[u8]> {
+    let mut index = 0;
+    while let Some(offset) = bytes[index..].iter().position(|&byte| byte == b'<') {
+        index += offset + 1;
+        match bytes.get(index)? {
+            b'?' | b'!' => {
+                // Comments may hold a '>' before their end
+                let end = if bytes[index..].starts_with(b"!--") { b"-->".as_slice() } else { b">".as_slice() };
+                index += bytes[index..].windows(end.len()).position(|window| window == end)? + end.len();
+            }
+            _ => {
+                let length = bytes[index..].iter().position(|byte| byte.is_ascii_whitespace() || matches!(byte, b'>' | b'/'))?;
+                let name = &bytes[index..index + length];
+                return Some(name.rsplit(|&byte| byte == b':').next().unwrap_or(name));
+            }
+        }
+    }
+    None
+}
@@ -1,115 +1,374 @@
-use std::{fs::File, io::BufReader, path::Path};
-
-use chrono::{DateTime, Utc};
-use quick_xml::{Reader, events::Event};
+use std::{fs::File, io::{BufRead, BufReader, Cursor, Read}, path::Path};
+
+use quick_xml::{Reader, events::{BytesStart, Event}};
+
+use crate::{errors::io_errors::IOError, internal::{io::{csv_loader, fit_loader, geojson_loader, kml_loader, nmea_loader, tcx_loader, time_parser, track_format::{SNIFF_LENGTH, TrackFormat}, xml_util::{read_attribute, sensor_channel, unescape_text}}, model::{spatial::points::{SensorChannel, SensorPoint, SpatialPoint, keep_recorded}, track::common::{SpatialRoute, SpatialTrack, TrackInfo, TrackMetadata, Waypoint, push_segment}}}};
+
+// Element whose text content is currently being read
+#[derive(Clone, Copy, PartialEq)]
+enum TextField {
+    None,
+    Time,
+    Elevation,
+    Hdop,
+    Name,
+    Description,
+    Kind,
+    Sensor(SensorChannel)
+}
 
-use crate::{errors::io_errors::IOError, internal::model::{spatial::points::SpatialPoint, track::common::SpatialTrack}};
+// Kind of point element currently being read
+#[derive(Clone, Copy, PartialEq)]
+enum PointKind {
+    Track,
+    Route,
+    Waypoint
+}
 
 /*
-    Loads a track from a track a file with @path
+    Loads a track from a track a file with @path, the loader is picked from the file extension (look at TrackFormat).
+    Files without a known extension are loaded from their content (look at read_track_bytes)
  */
 pub fn load_track(path : &Path) -> Result<SpatialTrack, IOError> {
-    if path.extension().ok_or(IOError::invalid_path(path.to_str().unwrap_or("unkown path"), "Could not collect path extension"))? != "gpx" {
-        return Err(IOError::format_not_supported(path.to_str().unwrap(), "Only supports gpx track format"));
+    let str_path = path.to_str().unwrap_or("unkown path");
+
+    match TrackFormat::from_path(path) {
+        Some(TrackFormat::Gpx) => load_gpx(path),
+        Some(TrackFormat::Fit) => fit_loader::load_fit(path),
+        Some(TrackFormat::Tcx) => tcx_loader::load_tcx(path),
+        Some(TrackFormat::Kml) => kml_loader::load_kml(path),
+        Some(TrackFormat::Kmz) => kml_loader::load_kmz(path),
+        Some(TrackFormat::Nmea) => nmea_loader::load_nmea(path),
+        Some(TrackFormat::GeoJson) => geojson_loader::load_geojson(path),
+        Some(TrackFormat::Csv) => csv_loader::load_csv_detected(path, b',').map(log_skipped_rows),
+        Some(TrackFormat::Tsv) => csv_loader::load_csv_detected(path, b'\t').map(log_skipped_rows),
+        None => {
+            let bytes = std::fs::read(path).map_err(|err| IOError::invalid_path(str_path, err.to_string().as_str()))?;
+            read_track_bytes(&bytes, str_path)
+        }
+    }
+}
+
+/*
+    Reads a track from @reader, the format is detected from the first bytes (look at TrackFormat::sniff), @source is only used to report errors
+    Throws:
+    FormatNotSupported if the content does not look like any supported format,
+    StreamError if @reader fails
+ */
+pub fn read_track<R : Read>(mut reader : R, source : &str) -> Result<SpatialTrack, IOError> {
+    let mut prefix = Vec::with_capacity(SNIFF_LENGTH);
+    (&mut reader).take(SNIFF_LENGTH as u64).read_to_end(&mut prefix).map_err(
+        |err| IOError::stream_error(source, err.to_string().as_str())
+    )?;
+    let format = sniff_format(&prefix, source)?;
+    read_track_as(Read::chain(Cursor::new(prefix), reader), source, format)
+}
+
+/*
+    Reads a track of the given @format from @reader, @source is only used to report errors.
+    Text formats are parsed while they are read, FIT and KMZ need the whole content and are buffered first
+    Throws: StreamError if @reader fails
+ */
+pub fn read_track_as<R : Read>(reader : R, source : &str, format : TrackFormat) -> Result<SpatialTrack, IOError> {
+    const READ_BUFFER_SIZE : usize = 64 * 1024;
+
+    let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, reader);
+    match format {
+        TrackFormat::Gpx => read_gpx(reader, source),
+        TrackFormat::Tcx => tcx_loader::read_tcx(reader, source),
+        TrackFormat::Kml => kml_loader::read_kml(reader, source),
+        TrackFormat::Nmea => nmea_loader::read_nmea(reader, source),
+        TrackFormat::GeoJson => geojson_loader::read_geojson(reader, source),
+        TrackFormat::Csv => csv_loader::read_csv_detected(reader, source, b',').map(log_skipped_rows),
+        TrackFormat::Tsv => csv_loader::read_csv_detected(reader, source, b'\t').map(log_skipped_rows),
+        TrackFormat::Fit | TrackFormat::Kmz => {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).map_err(
+                |err| IOError::stream_error(source, err.to_string().as_str())
+            )?;
+            read_track_bytes_as(&bytes, source, format)
+        }
     }
+}
 
+/*
+    Reads a track from the in memory @bytes, the format is detected from the content (look at TrackFormat::sniff)
+    Throws: FormatNotSupported if the content does not look like any supported format
+ */
+pub fn read_track_bytes(bytes : &[u8], source : &str) -> Result<SpatialTrack, IOError> {
+    let format = sniff_format(bytes, source)?;
+    read_track_bytes_as(bytes, source, format)
+}
+
+/*
+    Reads a track of the given @format from the in memory @bytes
+ */
+pub fn read_track_bytes_as(bytes : &[u8], source : &str, format : TrackFormat) -> Result<SpatialTrack, IOError> {
+    match format {
+        TrackFormat::Fit => fit_loader::read_fit(bytes, source),
+        TrackFormat::Kmz => kml_loader::read_kmz(Cursor::new(bytes), source),
+        _ => read_track_as(bytes, source, format)
+    }
+}
+
+/*
+    Returns the format of the track in @bytes
+    Throws: FormatNotSupported if the content does not look like any supported format
+ */
+pub fn sniff_format(bytes : &[u8], source : &str) -> Result<TrackFormat, IOError> {
+    TrackFormat::sniff(bytes).ok_or_else(
+        || IOError::format_not_supported(source, "Content is not a gpx, fit, tcx, kml, kmz, nmea, geojson, csv or tsv track")
+    )
+}
+
+/*
+    Csv and tsv tracks are loaded with a column mapping detected from their header, skipped rows are logged.
+    Use csv_loader::load_csv to load files with an explicit column mapping and get the skipped rows back
+ */
+fn log_skipped_rows((track, skipped) : (SpatialTrack, Vec<IOError>)) -> SpatialTrack {
+    for row in &skipped {
+        tracing::warn!("{}", row);
+    }
+    track
+}
+
+/*
+    Loads a track from the gpx file with @path
+ */
+pub fn load_gpx(path : &Path) -> Result<SpatialTrack, IOError> {
     let str_path = path.to_str().unwrap_or("unkown file path");
 
-    const INITIAL_ALLOCATION_SIZE : usize = 12_000;
     const FILE_BUFFER_SIZE : usize = 64 * 1024;
-    
+
     let file = File::open(path).map_err(
         |err| {return IOError::xml_reader(str_path, err.to_string().as_str());}
     )?;
-    let reader = BufReader::with_capacity(FILE_BUFFER_SIZE, file);
+    read_gpx(BufReader::with_capacity(FILE_BUFFER_SIZE, file), str_path)
+}
+
+/*
+    Reads a gpx document from @reader, @source is only used to report errors.
+    Every <trk> of the file is kept as a TrackInfo and every <trkseg> as a TrackSegment of the loaded track,
+    routes (<rte>) and waypoints (<wpt>) are loaded next to the recorded track.
+    Sensor values found in the <extensions> of track points are kept (look at xml_util::sensor_channel).
+    Throws: XmlParsingFail if a timestamp, an elevation, an hdop or a sensor value is malformed
+ */
+pub fn read_gpx<R : BufRead>(reader : R, source : &str) -> Result<SpatialTrack, IOError> {
+    const INITIAL_ALLOCATION_SIZE : usize = 12_000;
+
     let mut reader = Reader::from_reader(reader);
 
     reader.trim_text(true);
-    
+
 
     let mut xml_buffer = Vec::with_capacity(INITIAL_ALLOCATION_SIZE);
     let mut points = Vec::with_capacity(INITIAL_ALLOCATION_SIZE);
+    let mut sensors = Vec::with_capacity(INITIAL_ALLOCATION_SIZE);
+    let mut segments = Vec::new();
+    let mut tracks : Vec<TrackInfo> = Vec::new();
+    let mut routes : Vec<SpatialRoute> = Vec::new();
+    let mut waypoints : Vec<Waypoint> = Vec::new();
+    let mut metadata = TrackMetadata::default();
 
     let mut initial_time : Option<f64> = None;
-    let mut initial_stamp : Option<DateTime<Utc>> = None;
-    let mut current_time: f64 = 0.0;
-    let mut in_time = false;
-    
-    let mut in_ele = false;
+    let mut current_time : Option<f64> = None;
+    let mut text_field = TextField::None;
+
+    let mut in_metadata = false;
+    let mut in_author = false;
+    let mut in_track = false;
+    let mut in_route = false;
+    let mut in_point : Option<PointKind> = None;
+    let mut segment_start = 0usize;
 
     let mut lat = 0.0;
     let mut lon = 0.0;
-    let mut elevation = 0.0;
+    let mut elevation : Option<f64> = None;
+    let mut hdop : Option<f64> = None;
+    let mut sensor = SensorPoint::default();
+    let mut point_info = TrackInfo::default();
 
     loop {
         match reader.read_event_into(&mut xml_buffer).map_err(
-            |err| { IOError::xml_reader(str_path, err.to_string().as_str())}
+            |err| { IOError::xml_reader(source, err.to_string().as_str())}
         )? {
-            
-            Event::Start(element) if element.name().as_ref() == b"time" => {
-                in_time = true;
-            }
-
-            Event::Start(element) if element.name().as_ref() == b"ele" => {
-                in_ele = true;
-            }
 
-            Event::Start(element) if element.name().as_ref() == b"trkpt" => {
-                for attribute in element.attributes() {
-                    let attribute = attribute.unwrap();
-                    let str_attribute = unsafe { std::str::from_utf8_unchecked(&attribute.value.as_ref()) };
-                    match attribute.key.as_ref() {
-                        b"lat" => lat = str_attribute.parse::<f64>().map_err(
-                            |_| IOError::xml_parser(path.to_str().unwrap_or("unkown file path"),format!("Invalid data for f64 conversion in lat field {:?}", str_attribute).as_str())
-                        )?,
-                        b"lon" => lon = str_attribute.parse::<f64>().map_err(
-                            |_| IOError::xml_parser(path.to_str().unwrap_or("unkown file path"),format!("Invalid data for f64 conversion in long field {:?}", str_attribute).as_str())
-                        )?,
-                        _ => {}
+            Event::Start(element) => match element.name().as_ref() {
+                b"time" => text_field = TextField::Time,
+                b"ele" => text_field = TextField::Elevation,
+                b"hdop" => text_field = TextField::Hdop,
+                b"name" => text_field = TextField::Name,
+                b"desc" => text_field = TextField::Description,
+                b"type" => text_field = TextField::Kind,
+                b"gpx" => {
+                    if let Some(creator) = read_attribute(&element, b"creator") {
+                        metadata.creator = Some(creator);
                     }
                 }
+                b"metadata" => in_metadata = true,
+                b"author" => in_author = true,
+                b"trk" => {
+                    in_track = true;
+                    tracks.push(TrackInfo::default());
+                }
+                b"rte" => {
+                    in_route = true;
+                    routes.push(SpatialRoute::default());
+                }
+                b"trkseg" => {
+                    // Points found outside of a segment form their own segment
+                    push_segment(&mut segments, segment_start, points.len(), tracks.len().saturating_sub(1));
+                    segment_start = points.len();
+                }
+                b"trkpt" => {
+                    (lat, lon) = read_coordinates(&element, source)?;
+                    in_point = Some(PointKind::Track);
+                }
+                b"rtept" => {
+                    (lat, lon) = read_coordinates(&element, source)?;
+                    in_point = Some(PointKind::Route);
+                }
+                b"wpt" => {
+                    (lat, lon) = read_coordinates(&element, source)?;
+                    in_point = Some(PointKind::Waypoint);
+                }
+                _ if in_point == Some(PointKind::Track) => {
+                    if let Some(channel) = sensor_channel(element.local_name().as_ref()) {
+                        text_field = TextField::Sensor(channel);
+                    }
+                }
+                _ => {}
             }
 
-            Event::Text(element) if in_time && !in_ele => {
-                let str_elem = unsafe { std::str::from_utf8_unchecked(element.as_ref()) };
-
-
-                if let Some(initial_moment) = initial_time {
-                    current_time += fast_parse_gpx_seconds(str_elem) - initial_moment;
-                } else {
-                    let date_time = DateTime::parse_from_rfc3339(str_elem)
-                        .map_err(|err| IOError::xml_parser(str_path, err.to_string().as_str()))?
-                        .with_timezone(&Utc);
-
-                    initial_stamp = Some(date_time);
-                    initial_time = Some(0.0);
-                    current_time = 0.0;
+            Event::Empty(element) => {
+                let kind = match element.name().as_ref() {
+                    b"trkpt" => Some(PointKind::Track),
+                    b"rtept" => Some(PointKind::Route),
+                    b"wpt" => Some(PointKind::Waypoint),
+                    _ => None
+                };
+                if let Some(kind) = kind {
+                    (lat, lon) = read_coordinates(&element, source)?;
+                    let point = SpatialPoint { lon, lat, elev: None, delta_seconds: None, hdop: None, accuracy: None };
+                    if kind == PointKind::Track {
+                        sensors.push(SensorPoint::default());
+                    }
+                    push_point(kind, point, TrackInfo::default(), &mut points, &mut routes, &mut waypoints);
                 }
-
-                in_time = false;
             }
 
-            Event::Text(e) if in_ele && !in_time => {
-                let str_elem = unsafe { std::str::from_utf8_unchecked(e.as_ref()) };
-                elevation = str_elem.parse::<f64>().map_err(
-                      |err| { IOError::xml_reader(str_path, err.to_string().as_str())}
-                )?;
-            }
+            Event::Text(element) => {
+                match text_field {
+                    TextField::Time if in_point == Some(PointKind::Track) => {
+                        let str_elem = std::str::from_utf8(element.as_ref()).map_err(
+                            |err| IOError::xml_parser(source, err.to_string().as_str())
+                        )?;
 
-            Event::End(e) if e.name().as_ref() == b"trkpt" => {
-                points.push(SpatialPoint { lon, lat, elev: Some(elevation), delta_seconds: Some(current_time) });
+                        let moment = time_parser::parse_xml_timestamp(str_elem, source)?;
+                        let initial_moment = *initial_time.get_or_insert(moment);
+                        current_time = Some(moment - initial_moment);
+                    }
+                    TextField::Time if in_metadata && in_point.is_none() => {
+                        let str_elem = std::str::from_utf8(element.as_ref()).map_err(
+                            |err| IOError::xml_parser(source, err.to_string().as_str())
+                        )?;
+                        let moment = time_parser::parse_xml_timestamp(str_elem, source)?;
+                        metadata.time = Some(time_parser::xml_timestamp_to_utc(moment, source)?);
+                    }
+                    TextField::Elevation if in_point.is_some() => {
+                        let str_elem = std::str::from_utf8(element.as_ref()).map_err(
+                            |err| IOError::xml_parser(source, err.to_string().as_str())
+                        )?;
+                        elevation = Some(str_elem.parse::<f64>().map_err(
+                            |err| { IOError::xml_parser(source, err.to_string().as_str())}
+                        )?);
+                    }
+                    TextField::Hdop if in_point.is_some() => {
+                        let str_elem = std::str::from_utf8(element.as_ref()).map_err(
+                            |err| IOError::xml_parser(source, err.to_string().as_str())
+                        )?;
+                        hdop = Some(str_elem.parse::<f64>().map_err(
+                            |err| { IOError::xml_parser(source, err.to_string().as_str())}
+                        )?);
+                    }
+                    TextField::Sensor(channel) => {
+                        let str_elem = std::str::from_utf8(element.as_ref()).map_err(
+                            |err| IOError::xml_parser(source, err.to_string().as_str())
+                        )?;
+                        let value = str_elem.trim().parse::<f32>().map_err(
+                            |err| IOError::xml_parser(source, format!("Invalid sensor value {:?} : {}", str_elem, err).as_str())
+                        )?;
+                        sensor.set(channel, value);
+                    }
+                    TextField::Name | TextField::Description | TextField::Kind => {
+                        let text = unescape_text(element.as_ref());
+                        let target = if in_point.is_some() {
+                            Some(&mut point_info)
+                        } else if in_track {
+                            tracks.last_mut()
+                        } else if in_route {
+                            routes.last_mut().map(|route| &mut route.info)
+                        } else {
+                            None
+                        };
+
+                        let field = match target {
+                            Some(info) => match text_field {
+                                TextField::Name => Some(&mut info.name),
+                                TextField::Description => Some(&mut info.description),
+                                _ => Some(&mut info.kind)
+                            },
+                            None if in_author => match text_field {
+                                TextField::Name => Some(&mut metadata.author),
+                                _ => None
+                            },
+                            None if in_metadata => match text_field {
+                                TextField::Name => Some(&mut metadata.name),
+                                TextField::Description => Some(&mut metadata.description),
+                                _ => None
+                            },
+                            None => None
+                        };
+
+                        if let Some(field) = field {
+                            *field = Some(text);
+                        }
+                    }
+                    _ => {}
+                }
 
-                lat =0.0;
-                lon =0.0;
-                elevation =0.0;
+                text_field = TextField::None;
             }
 
-            Event::End(e) if e.name().as_ref() == b"time" => {
-                in_time = false;
-            }
+            Event::End(element) => match element.name().as_ref() {
+                b"trkpt" | b"rtept" | b"wpt" => {
+                    if let Some(kind) = in_point {
+                        let point = SpatialPoint { lon, lat, elev: elevation, delta_seconds: current_time, hdop, accuracy: None };
+                        if kind == PointKind::Track {
+                            sensors.push(std::mem::take(&mut sensor));
+                        }
+                        push_point(kind, point, std::mem::take(&mut point_info), &mut points, &mut routes, &mut waypoints);
+                    }
 
-            Event::End(e) if e.name().as_ref() == b"ele" => {
-                in_ele = false;
+                    lat = 0.0;
+                    lon = 0.0;
+                    elevation = None;
+                    hdop = None;
+                    sensor = SensorPoint::default();
+                    current_time = None;
+                    in_point = None;
+                }
+                b"trkseg" | b"trk" => {
+                    push_segment(&mut segments, segment_start, points.len(), tracks.len().saturating_sub(1));
+                    segment_start = points.len();
+                    if element.name().as_ref() == b"trk" {
+                        in_track = false;
+                    }
+                }
+                b"rte" => in_route = false,
+                b"metadata" => in_metadata = false,
+                b"author" => in_author = false,
+                _ => text_field = TextField::None
             }
 
             Event::Eof => break,
@@ -119,38 +378,57 @@ pub fn load_track(path : &Path) -> Result<SpatialTrack, IOError> {
         xml_buffer.clear();
     }
 
-    Ok(SpatialTrack { 
-        track: points, 
-        start_time: initial_stamp.unwrap_or_default()
+    push_segment(&mut segments, segment_start, points.len(), tracks.len().saturating_sub(1));
+
+    Ok(SpatialTrack {
+        track: points,
+        sensors: keep_recorded(sensors),
+        start_time: initial_time.map(|moment| time_parser::xml_timestamp_to_utc(moment, source)).transpose()?.unwrap_or_default(),
+        segments,
+        laps: Vec::new(),
+        tracks,
+        routes,
+        waypoints,
+        metadata
     })
 
 }
 
-#[inline]
-fn fast_parse_gpx_seconds(s: &str) -> f64 {
-    let byte_buffer = s.as_bytes();
-
-    // HH:MM:SS
-    let hour =
-        (byte_buffer[11] - b'0') as u32 * 10 + (byte_buffer[12] - b'0') as u32;
-    let min =
-        (byte_buffer[14] - b'0') as u32 * 10 + (byte_buffer[15] - b'0') as u32;
-    let sec =
-        (byte_buffer[17] - b'0') as u32 * 10 + (byte_buffer[18] - b'0') as u32;
-
-    let mut frac = 0u32;
-    let mut scale = 1_000_000_000u32;
-
-    if byte_buffer[19] == b'.' {
-        let mut i = 20;
-        while byte_buffer[i] != b'Z' {
-            frac = frac * 10 + (byte_buffer[i] - b'0') as u32;
-            scale /= 10;
-            i += 1;
+/*
+    Stores a fully read @point with its @info into the collection matching the point @kind
+*/
+fn push_point(kind : PointKind, point : SpatialPoint, info : TrackInfo, points : &mut Vec<SpatialPoint>, routes : &mut Vec<SpatialRoute>, waypoints : &mut Vec<Waypoint>) {
+    match kind {
+        PointKind::Track => points.push(point),
+        PointKind::Route => {
+            if let Some(route) = routes.last_mut() {
+                route.points.push(point);
+            }
         }
+        PointKind::Waypoint => waypoints.push(Waypoint { info, point })
     }
-
-    (hour * 3600 + min * 60 + sec) as f64
-        + (frac as f64 / scale as f64)
 }
 
+/*
+    Reads the lat and lon attributes of a point @element
+*/
+fn read_coordinates(element : &BytesStart, str_path : &str) -> Result<(f64, f64), IOError> {
+    let mut lat = 0.0;
+    let mut lon = 0.0;
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(|err| IOError::xml_parser(str_path, err.to_string().as_str()))?;
+        let str_attribute = std::str::from_utf8(attribute.value.as_ref()).map_err(
+            |err| IOError::xml_parser(str_path, err.to_string().as_str())
+        )?;
+        match attribute.key.as_ref() {
+            b"lat" => lat = str_attribute.parse::<f64>().map_err(
+                |_| IOError::xml_parser(str_path, format!("Invalid data for f64 conversion in lat field {:?}", str_attribute).as_str())
+            )?,
+            b"lon" => lon = str_attribute.parse::<f64>().map_err(
+                |_| IOError::xml_parser(str_path, format!("Invalid data for f64 conversion in long field {:?}", str_attribute).as_str())
+            )?,
+            _ => {}
+        }
+    }
+    Ok((lat, lon))
+}
@@ -0,0 +1,61 @@
+use quick_xml::events::BytesStart;
+
+use crate::internal::model::spatial::points::SensorChannel;
+
+/*
+    Returns the value of the attribute @key of @element if present
+*/
+pub fn read_attribute(element : &BytesStart, key : &[u8]) -> Option<String> {
+    element.attributes()
+        .filter_map(|attribute| attribute.ok())
+        .find(|attribute| attribute.key.as_ref() == key)
+        .map(|attribute| unescape_text(attribute.value.as_ref()))
+}
+
+/*
+    Decodes the raw @text of an element, replacing the predefined xml entities
+*/
+pub fn unescape_text(text : &[u8]) -> String {
+    let text = String::from_utf8_lossy(text);
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/*
+    Encodes @text for use as element content or attribute value, replacing the characters reserved by xml
+*/
+pub fn escape_text(text : &str) -> String {
+    if !text.contains(['&', '<', '>', '"', '\'']) {
+        return text.to_string();
+    }
+
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/*
+    Returns the sensor channel stored in the extension element with the @local_name (namespace prefix removed).
+    Covers the Garmin TrackPointExtension (hr, cad, atemp, speed), Cluetrust gpxdata (hr, cadence, temp),
+    the power extensions (power, PowerInWatts) and the Garmin ActivityExtension of tcx files (Speed, Watts, RunCadence)
+*/
+pub fn sensor_channel(local_name : &[u8]) -> Option<SensorChannel> {
+    let channel = match local_name.to_ascii_lowercase().as_slice() {
+        b"hr" | b"heartrate" => SensorChannel::HeartRate,
+        b"cad" | b"cadence" | b"runcadence" => SensorChannel::Cadence,
+        b"power" | b"powerinwatts" | b"watts" => SensorChannel::Power,
+        b"atemp" | b"temp" | b"temperature" => SensorChannel::Temperature,
+        b"speed" => SensorChannel::Speed,
+        _ => return None
+    };
+    Some(channel)
+}
@@ -1,4 +1,6 @@
 pub mod analysis;
 pub mod spatial;
 pub mod track;
-pub mod config;
\ No newline at end of file
+pub mod config;
+pub mod validation;
+pub mod movement;
\ No newline at end of file
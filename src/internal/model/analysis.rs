@@ -1,8 +1,15 @@
 
-use crate::internal::model::{config::analysis::AnalysisConfig, spatial::points::MatchPoint};
+use serde::Serialize;
+
+use crate::internal::model::{config::{analysis::AnalysisConfig, gradient::GradientConfig}, spatial::points::{MatchPoint, Point}, track::{common::TrackSegment, reference::ReferenceTrack, riders::MatchedTrack}};
+
+// Meters, shortest bin of a profile along the reference
+pub const MIN_BIN_LENGTH : f32 = 10.0;
+// Most bins of a profile along the reference, longer tracks get longer bins
+pub const MAX_BIN_COUNT : usize = 10_000;
 
 #[repr(u16)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Severity {
     Ok = 0,
     Minor = 1,
@@ -25,6 +32,7 @@ impl Severity {
 
 /*
     Fileters the used point errors to prevent the buildup of one-off errors. Look at AnaltsysConfig.minimum_continuous_error
+    A run of errors never continues over a recording break (MatchPoint.segment_start), a recording gap (MatchPoint.gap_end) or past the end of the track.
 */
 fn set_error_flags(
     matches: &mut [MatchPoint],
@@ -33,17 +41,14 @@ fn set_error_flags(
 ) {
     let mut error_count = 0;
     for point_index in 0..matches.len() {
+        if matches[point_index].segment_start || matches[point_index].gap_end {
+            close_error_run(matches, severity, point_index, error_count, config);
+            error_count = 0;
+        }
+
         match severity[point_index] {
             Severity::Ok => {
-                if error_count >= config.get_minimum_cont_error() {
-                    for previous_index in 1..=error_count {
-                        matches[point_index - previous_index].count_to_error = true;
-                    }
-                }else {
-                    for previous_index in 0..error_count {
-                        severity[point_index - previous_index] = Severity::Ok;
-                    }
-                }
+                close_error_run(matches, severity, point_index, error_count, config);
                 error_count = 0;
             }
             _ => {
@@ -51,6 +56,28 @@ fn set_error_flags(
             }
         }
     }
+    close_error_run(matches, severity, matches.len(), error_count, config);
+}
+
+/*
+    Marks the @error_count points before @run_end as errors if the run is long enough, otherwise clears their severity.
+*/
+fn close_error_run(
+    matches: &mut [MatchPoint],
+    severity : &mut [Severity],
+    run_end : usize,
+    error_count : usize,
+    config: &AnalysisConfig
+) {
+    if error_count >= config.get_minimum_cont_error() {
+        for previous_index in 1..=error_count {
+            matches[run_end - previous_index].count_to_error = true;
+        }
+    } else {
+        for previous_index in 1..=error_count {
+            severity[run_end - previous_index] = Severity::Ok;
+        }
+    }
 }
 
 /*
@@ -117,4 +144,340 @@ pub fn classify_directional(
         .collect();
     set_error_flags(matches, &mut computed_severity, config);
     computed_severity
-}
\ No newline at end of file
+}
+
+// A recording gap as seen on the reference, distances are along the reference track
+#[derive(Clone, Debug, Serialize)]
+pub struct GapReport {
+    pub start_seconds : f64,
+    pub end_seconds : f64,
+    pub duration : f64,
+    pub from_distance : f32,        // Reference distance matched before the gap
+    pub to_distance : f32,          // Reference distance matched after the gap
+    pub filled_points : usize
+}
+
+/*
+    How much of the reference is backed by recorded data, so missing data can be told apart from deviations.
+    Distances are in meters along the reference track, times in seconds
+*/
+#[derive(Clone, Debug, Serialize)]
+pub struct GapCoverage {
+    pub gap_count : usize,
+    pub missing_time : f64,                 // Sum of the gap durations
+    pub missing_distance : f32,             // Reference distance skipped by the gaps (only gaps moving forward on the reference)
+    pub matched_distance : f32,             // Reference distance between the first and the last matched point
+    pub coverage : f32,                     // Share (0...1) of the matched distance that is backed by recorded points
+    pub gaps : Vec<GapReport>
+}
+
+/*
+    Returns the gap coverage of @matched, its gaps must have been detected first (gap_detection::detect_gaps) on @reference.
+    A track without gaps (or without matched distance) has a coverage of 1
+*/
+pub fn gap_coverage(matched : &MatchedTrack, reference : &ReferenceTrack) -> GapCoverage {
+    let distance_at = |reference_index : u32| {
+        reference.track.get(reference_index as usize).map_or(0.0, |point| point.total_distance)
+    };
+
+    let gaps : Vec<GapReport> = matched.gaps.iter()
+        .map(|gap| GapReport {
+            start_seconds : gap.start_seconds,
+            end_seconds : gap.end_seconds,
+            duration : (gap.end_seconds - gap.start_seconds).max(0.0),
+            from_distance : distance_at(gap.reference_start),
+            to_distance : distance_at(gap.reference_end),
+            filled_points : gap.filled.len()
+        })
+        .collect();
+
+    let (first_distance, last_distance) = matched.track.iter()
+        .map(|matched_point| distance_at(matched_point.reference_index))
+        .fold((f32::MAX, f32::MIN), |(first, last), distance| (first.min(distance), last.max(distance)));
+    let matched_distance = if matched.track.is_empty() { 0.0 } else { last_distance - first_distance };
+    let missing_distance = gaps.iter().map(|gap| (gap.to_distance - gap.from_distance).max(0.0)).sum::<f32>();
+
+    GapCoverage {
+        gap_count : gaps.len(),
+        missing_time : gaps.iter().map(|gap| gap.duration).sum(),
+        missing_distance,
+        matched_distance,
+        coverage : if matched_distance > 0.0 { (1.0 - missing_distance / matched_distance).clamp(0.0, 1.0) } else { 1.0 },
+        gaps
+    }
+}
+
+/*
+    Result of sampling the DEM under a track, elevations in meters
+*/
+#[derive(Clone, Debug, Serialize)]
+pub struct ElevationSummary {
+    pub ascent : f32,
+    pub descent : f32,
+    pub sampled_points : usize,     // Points covered by the DEM
+    pub missing_points : usize,     // Points outside of the DEM (or on no data samples), their elevation was only shifted by offset
+    pub offset : f32                // Median of GPS elevation - DEM elevation over the sampled points
+}
+
+/*
+    Counts ascent and descent with a hysteresis : the elevation has to move by more than threshold from the last counted elevation
+    to be counted, so noise does not add up
+*/
+struct Hysteresis {
+    threshold : f32,
+    counted : Option<f32>
+}
+
+impl Hysteresis {
+    fn new(threshold : f32) -> Self {
+        Hysteresis { threshold, counted : None }
+    }
+
+    /*
+        Returns the counted elevation change at @z (positive for ascent), 0 below the threshold.
+        @restart starts counting again from @z (first point after a recording break)
+    */
+    fn push(&mut self, z : f32, restart : bool) -> f32 {
+        let counted = match self.counted {
+            Some(counted) if !restart => counted,
+            _ => {
+                self.counted = Some(z);
+                return 0.0;
+            }
+        };
+        let change = z - counted;
+        if change.abs() < self.threshold || !change.is_finite() {
+            return 0.0;
+        }
+        self.counted = Some(z);
+        change
+    }
+}
+
+/*
+    Total ascent and descent of @points, every segment on its own (the elevation change over a recording break is not climbed).
+    The elevation has to move by more than @threshold from the last counted elevation to be counted, so noise does not add up.
+    Points without elevation are skipped.
+    Returns (ascent, descent)
+*/
+pub fn ascent_descent<T : Point>(points : &[T], segments : &[TrackSegment], threshold : f32) -> (f32, f32) {
+    let segments = TrackSegment::or_single(segments, points.len());
+    let (mut ascent, mut descent) = (0.0, 0.0);
+    let mut hysteresis = Hysteresis::new(threshold);
+    for segment in segments.iter().filter(|segment| segment.end() <= points.len()) {
+        let mut restart = true;
+        for point in points[segment.start..segment.end()].iter().filter(|point| point.has_elevation()) {
+            let change = hysteresis.push(point.z(), restart);
+            if change > 0.0 { ascent += change } else { descent -= change }
+            restart = false;
+        }
+    }
+    (ascent, descent)
+}
+
+// Climbing statistics of one bin of the gradient profile, gradients are in percent
+#[derive(Clone, Debug, Serialize)]
+pub struct GradientBin {
+    pub from_distance : f32,            // Reference distance where the bin starts
+    pub to_distance : f32,
+    pub elevation : Option<f32>,        // Mean elevation of the points in the bin, None if no point fell into it
+    pub ascent : f32,
+    pub descent : f32,
+    pub gradient : Option<f32>,         // Mean gradient, elevation change over the distance moved forward in the bin
+    pub max_gradient : Option<f32>,     // Steepest climb measured over gradient_distance
+    pub min_gradient : Option<f32>      // Steepest descent measured over gradient_distance (negative)
+}
+
+/*
+    Climbing statistics of a track along the reference and its gradient profile binned along RefPoint.total_distance.
+    Elevations and distances are in meters, gradients in percent
+*/
+#[derive(Clone, Debug, Serialize)]
+pub struct ElevationProfile {
+    pub ascent : f32,
+    pub descent : f32,
+    pub distance : f32,                         // Reference distance moved forward over, the base of the gradients
+    pub max_gradient : Option<f32>,
+    pub min_gradient : Option<f32>,
+    pub average_climb_gradient : Option<f32>,   // Ascent over the distance spent climbing
+    pub average_descent_gradient : Option<f32>, // Descent over the distance spent descending (negative)
+    pub coverage : Option<GapCoverage>,         // Recording gaps of a rider profile, None for a reference profile
+    pub bins : Vec<GradientBin>
+}
+
+// A point of a track seen on the reference, first point after a recording break or gap if segment_start
+#[derive(Clone, Copy)]
+struct ProfilePoint {
+    distance : f32,
+    z : f32,
+    has_elevation : bool,   // Points without elevation only carry their segment_start to the next point with one
+    segment_start : bool
+}
+
+/*
+    Returns the climbing statistics and gradient profile of @reference, look at GradientConfig.
+    Points without elevation are left out
+*/
+pub fn reference_profile(reference : &ReferenceTrack, config : &GradientConfig) -> ElevationProfile {
+    let segments = TrackSegment::or_single(&reference.segments, reference.track.len());
+    let mut points = Vec::with_capacity(reference.track.len());
+    for segment in segments.iter().filter(|segment| segment.end() <= reference.track.len()) {
+        points.extend(reference.track[segment.start..segment.end()].iter().enumerate().map(|(offset, point)| ProfilePoint {
+            distance : point.total_distance,
+            z : point.z,
+            has_elevation : point.has_elevation,
+            segment_start : offset == 0
+        }));
+    }
+    build_profile(&points, reference_length(reference), config)
+}
+
+/*
+    Returns the climbing statistics and gradient profile of the rider matched in @matched, binned along @reference the rider was snapped on.
+    The rider elevation is rebuilt from the matched reference point and MatchPoint.distance_z, filled gap points and points without elevation are left out
+    and no climb or gradient spans a recording gap. The gaps of @matched must have been detected first (gap_detection::detect_gaps).
+    Points going back on the reference (detours, laps) count for ascent and descent but not for the gradients
+*/
+pub fn rider_profile(matched : &MatchedTrack, reference : &ReferenceTrack, config : &GradientConfig) -> ElevationProfile {
+    let points : Vec<ProfilePoint> = matched.track.iter()
+        .filter_map(|matched_point| reference.track.get(matched_point.reference_index as usize).map(|reference_point| ProfilePoint {
+            distance : reference_point.total_distance,
+            z : reference_point.z + matched_point.distance_z,
+            has_elevation : matched_point.has_elevation && reference_point.has_elevation,
+            segment_start : matched_point.segment_start || matched_point.gap_end
+        }))
+        .collect();
+    ElevationProfile {
+        coverage : Some(gap_coverage(matched, reference)),
+        ..build_profile(&points, reference_length(reference), config)
+    }
+}
+
+fn reference_length(reference : &ReferenceTrack) -> f32 {
+    reference.track.iter().map(|point| point.total_distance).fold(0.0, f32::max)
+}
+
+// Running totals of a GradientBin
+#[derive(Clone, Copy, Default)]
+struct BinTotals {
+    ascent : f32,
+    descent : f32,
+    rise : f32,
+    run : f32,
+    elevation_sum : f32,
+    elevation_count : usize,
+    max_gradient : Option<f32>,
+    min_gradient : Option<f32>
+}
+
+fn build_profile(points : &[ProfilePoint], length : f32, config : &GradientConfig) -> ElevationProfile {
+    let bin_length = if config.get_bin_length() > 0.0 { config.get_bin_length().max(MIN_BIN_LENGTH) } else { length.max(1.0) };
+    let bin_length = bin_length.max(length / MAX_BIN_COUNT as f32);
+    let bin_count = ((length / bin_length).ceil() as usize).clamp(1, MAX_BIN_COUNT);
+    let bin_of = |distance : f32| ((distance.max(0.0) / bin_length) as usize).min(bin_count - 1);
+    let mut totals = vec![BinTotals::default(); bin_count];
+
+    let mut hysteresis = Hysteresis::new(config.get_ascent_threshold());
+    let mut restart = true;
+    for point in points {
+        restart |= point.segment_start;
+        if !point.has_elevation || !point.z.is_finite() {
+            continue;
+        }
+        let bin = &mut totals[bin_of(point.distance)];
+        let change = hysteresis.push(point.z, restart);
+        if change > 0.0 { bin.ascent += change } else { bin.descent -= change }
+        bin.elevation_sum += point.z;
+        bin.elevation_count += 1;
+        restart = false;
+    }
+
+    // Gradients only over the points moving forward on the reference, every segment on its own
+    let (mut climb_rise, mut climb_run, mut descent_rise, mut descent_run) = (0.0, 0.0, 0.0, 0.0);
+    let (mut max_gradient, mut min_gradient) : (Option<f32>, Option<f32>) = (None, None);
+    let mut distance = 0.0;
+    for run in forward_runs(points) {
+        for pair in run.windows(2) {
+            let (rise, moved) = (pair[1].z - pair[0].z, pair[1].distance - pair[0].distance);
+            let bin = &mut totals[bin_of(pair[0].distance)];
+            bin.rise += rise;
+            bin.run += moved;
+            distance += moved;
+            if rise > 0.0 {
+                climb_rise += rise;
+                climb_run += moved;
+            } else {
+                descent_rise += rise;
+                descent_run += moved;
+            }
+        }
+
+        let mut end = 1;
+        for start in 0..run.len() {
+            end = end.max(start + 1);
+            while end < run.len() && run[end].distance - run[start].distance < config.get_gradient_distance() {
+                end += 1;
+            }
+            if end >= run.len() {
+                break;
+            }
+            let gradient = (run[end].z - run[start].z) / (run[end].distance - run[start].distance) * 100.0;
+            let bin = &mut totals[bin_of((run[start].distance + run[end].distance) / 2.0)];
+            bin.max_gradient = Some(bin.max_gradient.map_or(gradient, |max| max.max(gradient)));
+            bin.min_gradient = Some(bin.min_gradient.map_or(gradient, |min| min.min(gradient)));
+            max_gradient = Some(max_gradient.map_or(gradient, |max| max.max(gradient)));
+            min_gradient = Some(min_gradient.map_or(gradient, |min| min.min(gradient)));
+        }
+    }
+
+    let bins : Vec<GradientBin> = totals.iter()
+        .enumerate()
+        .map(|(index, bin)| GradientBin {
+            from_distance : index as f32 * bin_length,
+            to_distance : ((index + 1) as f32 * bin_length).min(length.max(bin_length)),
+            elevation : (bin.elevation_count > 0).then(|| bin.elevation_sum / bin.elevation_count as f32),
+            ascent : bin.ascent,
+            descent : bin.descent,
+            gradient : (bin.run > 0.0).then(|| bin.rise / bin.run * 100.0),
+            max_gradient : bin.max_gradient,
+            min_gradient : bin.min_gradient
+        })
+        .collect();
+
+    ElevationProfile {
+        ascent : bins.iter().map(|bin| bin.ascent).sum(),
+        descent : bins.iter().map(|bin| bin.descent).sum(),
+        distance,
+        max_gradient,
+        min_gradient,
+        average_climb_gradient : (climb_run > 0.0).then(|| climb_rise / climb_run * 100.0),
+        average_descent_gradient : (descent_run > 0.0).then(|| descent_rise / descent_run * 100.0),
+        coverage : None,
+        bins
+    }
+}
+
+/*
+    Splits @points at every recording break and keeps in every part only the points with an elevation
+    further along the reference than all the points before them
+*/
+fn forward_runs(points : &[ProfilePoint]) -> Vec<Vec<ProfilePoint>> {
+    let mut runs : Vec<Vec<ProfilePoint>> = Vec::new();
+    let mut restart = true;
+    for point in points {
+        restart |= point.segment_start;
+        if !point.has_elevation || !point.z.is_finite() {
+            continue;
+        }
+        match runs.last_mut() {
+            Some(run) if !restart => {
+                if run.last().is_none_or(|last| point.distance > last.distance) {
+                    run.push(*point);
+                }
+            }
+            _ => runs.push(vec![*point])
+        }
+        restart = false;
+    }
+    runs
+}
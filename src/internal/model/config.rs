@@ -1,3 +1,13 @@
 pub mod snapping;
 pub mod coordinates;
-pub mod analysis;
\ No newline at end of file
+pub mod analysis;
+pub mod csv;
+pub mod validation;
+pub mod outlier;
+pub mod smoothing;
+pub mod simplification;
+pub mod stops;
+pub mod gaps;
+pub mod elevation;
+pub mod gradient;
+pub mod speed;
\ No newline at end of file
@@ -0,0 +1,87 @@
+use serde::Deserialize;
+
+// Column of a csv file, either by its header name (case insensitive) or by its position (0 based)
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvColumn {
+    Name(String),
+    Index(usize)
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvTimeFormat {
+    Rfc3339,                // 2024-05-01T10:00:00Z, offsets are respected
+    UnixSeconds,            // Seconds since 1970-01-01 UTC
+    UnixMillis,             // Milliseconds since 1970-01-01 UTC
+    Pattern(String),        // chrono strftime pattern, times without an offset are taken as UTC
+    ElapsedSeconds,         // Seconds since the start of the recording
+    ElapsedMillis           // Milliseconds since the start of the recording
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AngleUnit {
+    Degrees,
+    Radians,
+    Semicircles             // 2^31 semicircles = 180 degrees (Garmin)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthUnit {
+    Meters,
+    Feet
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CsvColumns {
+    pub latitude : CsvColumn,
+    pub longitude : CsvColumn,
+    pub elevation : Option<CsvColumn>,
+    pub time : Option<CsvColumn>,
+    pub hdop : Option<CsvColumn>,
+    pub accuracy : Option<CsvColumn>    // Horizontal error in meters
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CsvUnits {
+    pub angle : AngleUnit,          // Unit of the latitude and longitude columns
+    pub elevation : LengthUnit,
+    pub time : CsvTimeFormat
+}
+
+#[derive(Clone, Debug)]
+pub struct CsvConfig {
+    delimiter : u8,         // Field separator, ',' for csv and '\t' for tsv
+    has_header : bool,      // The first row holds column names, required to address columns by name
+    columns : CsvColumns,   // Where every value of a point is read from
+    units : CsvUnits        // How the read values are converted
+}
+
+impl CsvConfig {
+    pub fn new(delimiter : u8, has_header : bool, columns : CsvColumns, units : CsvUnits) -> Self {
+        CsvConfig {
+            delimiter : delimiter,
+            has_header : has_header,
+            columns : columns,
+            units : units
+        }
+    }
+
+    pub fn get_delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    pub fn get_has_header(&self) -> bool {
+        self.has_header
+    }
+
+    pub fn get_columns(&self) -> &CsvColumns {
+        &self.columns
+    }
+
+    pub fn get_units(&self) -> &CsvUnits {
+        &self.units
+    }
+}
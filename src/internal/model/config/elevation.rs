@@ -0,0 +1,40 @@
+use serde::Deserialize;
+
+/*
+    How the elevations sampled from the DEM are applied to a track
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElevationMode {
+    Replace,    // z becomes the DEM elevation, points outside of the DEM keep their GPS elevation shifted by the median offset
+    Correct     // z is shifted by the median offset between the GPS and the DEM, the recorded relief is kept (barometric altimeters)
+}
+
+#[derive(Clone, Debug)]
+pub struct ElevationConfig {
+    dem_directory : String,     // Directory holding the SRTM .hgt and GeoTIFF tiles
+    mode : ElevationMode,
+    ascent_threshold : f32      // Meters, elevation changes smaller than this are noise and not counted in ascent or descent
+}
+
+impl ElevationConfig {
+    pub fn new(dem_directory : String, mode : ElevationMode, ascent_threshold : f32) -> Self {
+        ElevationConfig {
+            dem_directory : dem_directory,
+            mode : mode,
+            ascent_threshold : ascent_threshold
+        }
+    }
+
+    pub fn get_dem_directory(&self) -> &str {
+        &self.dem_directory
+    }
+
+    pub fn get_mode(&self) -> ElevationMode {
+        self.mode
+    }
+
+    pub fn get_ascent_threshold(&self) -> f32 {
+        self.ascent_threshold
+    }
+}
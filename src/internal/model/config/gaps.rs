@@ -0,0 +1,45 @@
+use serde::Deserialize;
+
+// How the missing part of a recording gap is filled
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapPolicy {
+    Leave,              // The gap is only reported
+    Linear,             // Straight line between the points around the gap, snapped to the closest reference point
+    AlongReference      // The rider is assumed to have followed the reference between the points around the gap
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GapConfig {
+    max_time_gap : f64,         // Seconds, longer intervals between two points of a segment are gaps
+    max_distance_gap : f32,     // Meters, longer jumps between two points of a segment are gaps
+    fill_interval : f64,        // Seconds between two filled points
+    policy : GapPolicy
+}
+
+impl GapConfig {
+    pub fn new(max_time_gap : f64, max_distance_gap : f32, fill_interval : f64, policy : GapPolicy) -> Self {
+        GapConfig {
+            max_time_gap : max_time_gap,
+            max_distance_gap : max_distance_gap,
+            fill_interval : fill_interval,
+            policy : policy
+        }
+    }
+
+    pub fn get_max_time_gap(&self) -> f64 {
+        self.max_time_gap
+    }
+
+    pub fn get_max_distance_gap(&self) -> f32 {
+        self.max_distance_gap
+    }
+
+    pub fn get_fill_interval(&self) -> f64 {
+        self.fill_interval
+    }
+
+    pub fn get_policy(&self) -> GapPolicy {
+        self.policy
+    }
+}
@@ -0,0 +1,28 @@
+#[derive(Clone, Copy, Debug)]
+pub struct GradientConfig {
+    ascent_threshold : f32,     // Meters, elevation changes smaller than this are noise and not counted in ascent or descent
+    bin_length : f32,           // Meters of reference distance per bin of the gradient profile, look at analysis::MIN_BIN_LENGTH
+    gradient_distance : f32     // Meters, gradients are measured over at least this distance so single elevation spikes do not make walls
+}
+
+impl GradientConfig {
+    pub fn new(ascent_threshold : f32, bin_length : f32, gradient_distance : f32) -> Self {
+        GradientConfig {
+            ascent_threshold : ascent_threshold,
+            bin_length : bin_length,
+            gradient_distance : gradient_distance
+        }
+    }
+
+    pub fn get_ascent_threshold(&self) -> f32 {
+        self.ascent_threshold
+    }
+
+    pub fn get_bin_length(&self) -> f32 {
+        self.bin_length
+    }
+
+    pub fn get_gradient_distance(&self) -> f32 {
+        self.gradient_distance
+    }
+}
@@ -0,0 +1,73 @@
+// What happens to a point detected as an outlier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutlierRepair {
+    Remove,         // The point (and its sensor values) is dropped from the track
+    Interpolate     // The point is moved between its valid neighbours, its time and sensor values are kept
+}
+
+/*
+    Thresholds of the outlier filter (look at track_filter), speeds are in m/s and accelerations in m/s².
+    Use OutlierConfig::for_sport for the presets of the usual activity types.
+*/
+#[derive(Clone, Copy, Debug)]
+pub struct OutlierConfig {
+    max_speed : f32,            // Reaching a point faster than this is impossible for the sport
+    max_acceleration : f32,     // Speeding up faster than this between two points is impossible for the sport
+    jitter_distance : f32,      // Moves shorter than this are GPS noise and never make a point an outlier
+    max_outlier_run : usize,    // Longer runs of consecutive outliers are a teleport (signal recovered elsewhere), the track is split there instead
+    repair : OutlierRepair
+}
+
+// (activity type fragments, max speed, max acceleration), the first matching entry wins
+const SPORT_PRESETS : [(&[&str], f32, f32); 6] = [
+    (&["motor", "driv", "rally", "kart"], 90.0, 12.0),
+    (&["ski", "snowboard"], 40.0, 8.0),
+    (&["cycl", "bik", "ride", "mtb", "gravel"], 30.0, 6.0),
+    (&["run", "jog", "trail"], 12.0, 6.0),
+    (&["walk", "hik", "trek"], 6.0, 4.0),
+    (&["swim", "row", "paddl", "kayak", "canoe"], 8.0, 3.0)
+];
+
+impl OutlierConfig {
+    pub fn new(max_speed : f32, max_acceleration : f32, jitter_distance : f32, max_outlier_run : usize, repair : OutlierRepair) -> Self {
+        OutlierConfig {
+            max_speed : max_speed,
+            max_acceleration : max_acceleration,
+            jitter_distance : jitter_distance,
+            max_outlier_run : max_outlier_run,
+            repair : repair
+        }
+    }
+
+    /*
+        Returns the preset for the activity type @sport as declared by the recording device (ex: "cycling", "Run", "hiking"),
+        outliers are interpolated, moves under 5 m are noise and runs of more than 5 outliers are teleports.
+        Returns None if the activity type is unknown
+    */
+    pub fn for_sport(sport : &str) -> Option<Self> {
+        let sport = sport.to_ascii_lowercase();
+        SPORT_PRESETS.iter()
+            .find(|(fragments, _, _)| fragments.iter().any(|fragment| sport.contains(fragment)))
+            .map(|(_, max_speed, max_acceleration)| OutlierConfig::new(*max_speed, *max_acceleration, 5.0, 5, OutlierRepair::Interpolate))
+    }
+
+    pub fn get_max_speed(&self) -> f32 {
+        self.max_speed
+    }
+
+    pub fn get_max_acceleration(&self) -> f32 {
+        self.max_acceleration
+    }
+
+    pub fn get_jitter_distance(&self) -> f32 {
+        self.jitter_distance
+    }
+
+    pub fn get_max_outlier_run(&self) -> usize {
+        self.max_outlier_run
+    }
+
+    pub fn get_repair(&self) -> OutlierRepair {
+        self.repair
+    }
+}
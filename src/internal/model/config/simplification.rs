@@ -0,0 +1,31 @@
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimplificationAlgorithm {
+    DouglasPeucker,     // Keeps every point further than the tolerance from the simplified line, best for storage
+    Visvalingam         // Drops the points adding the least area first, smoother shapes for display
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SimplificationConfig {
+    algorithm : SimplificationAlgorithm,
+    tolerance : f64         // Meters, the distance to the simplified line (Douglas-Peucker) or the side of the square of the minimum area (Visvalingam)
+}
+
+impl SimplificationConfig {
+    pub fn new(algorithm : SimplificationAlgorithm, tolerance : f64) -> Self {
+        SimplificationConfig {
+            algorithm : algorithm,
+            tolerance : tolerance
+        }
+    }
+
+    pub fn get_algorithm(&self) -> SimplificationAlgorithm {
+        self.algorithm
+    }
+
+    pub fn get_tolerance(&self) -> f64 {
+        self.tolerance
+    }
+}
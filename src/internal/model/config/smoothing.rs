@@ -0,0 +1,38 @@
+/*
+    Settings of the optional track smoothing stage (look at track_smoothing), distances are in meters of the projected space.
+    The measurement error of a point is its reported accuracy, else its hdop times hdop_error, else default_error.
+*/
+#[derive(Clone, Copy, Debug)]
+pub struct SmoothingConfig {
+    acceleration_noise : f32,   // Standard deviation of the unmodelled acceleration (m/s²), lower values give smoother tracks but cut corners
+    hdop_error : f32,           // Meters of error per unit of hdop (user equivalent range error of the receiver)
+    default_error : f32,        // Measurement error of points with neither accuracy nor hdop
+    min_error : f32             // Lower bound of any measurement error, devices tend to be optimistic
+}
+
+impl SmoothingConfig {
+    pub fn new(acceleration_noise : f32, hdop_error : f32, default_error : f32, min_error : f32) -> Self {
+        SmoothingConfig {
+            acceleration_noise : acceleration_noise,
+            hdop_error : hdop_error,
+            default_error : default_error,
+            min_error : min_error
+        }
+    }
+
+    pub fn get_acceleration_noise(&self) -> f32 {
+        self.acceleration_noise
+    }
+
+    pub fn get_hdop_error(&self) -> f32 {
+        self.hdop_error
+    }
+
+    pub fn get_default_error(&self) -> f32 {
+        self.default_error
+    }
+
+    pub fn get_min_error(&self) -> f32 {
+        self.min_error
+    }
+}
@@ -0,0 +1,40 @@
+use crate::internal::model::config::stops::StopConfig;
+
+#[derive(Clone, Debug)]
+pub struct SpeedConfig {
+    smoothing_window : f64,     // Seconds, speeds are averaged over a window of this length centered on every point
+    stops : StopConfig,         // Splits moving and stopped time for the averages, speeds below its min_moving_speed have no pace
+    bin_length : f32,           // Meters of reference distance per bin of the speed profile, look at analysis::MIN_BIN_LENGTH
+    percentiles : Vec<f32>      // Percentiles (0...100) of the moving speed reported in the statistics
+}
+
+impl SpeedConfig {
+    pub fn new(smoothing_window : f64, stops : StopConfig, bin_length : f32, percentiles : Vec<f32>) -> Self {
+        SpeedConfig {
+            smoothing_window : smoothing_window,
+            stops : stops,
+            bin_length : bin_length,
+            percentiles : percentiles
+        }
+    }
+
+    pub fn get_smoothing_window(&self) -> f64 {
+        self.smoothing_window
+    }
+
+    pub fn get_stops(&self) -> &StopConfig {
+        &self.stops
+    }
+
+    pub fn get_min_moving_speed(&self) -> f32 {
+        self.stops.get_min_moving_speed()
+    }
+
+    pub fn get_bin_length(&self) -> f32 {
+        self.bin_length
+    }
+
+    pub fn get_percentiles(&self) -> &[f32] {
+        &self.percentiles
+    }
+}
@@ -0,0 +1,28 @@
+#[derive(Clone, Copy, Debug)]
+pub struct StopConfig {
+    dwell_radius : f32,         // Meters, a rider staying within this distance of where they stopped is not moving
+    min_duration : f64,         // Seconds, shorter dwells are not reported as stops (traffic lights, turns)
+    min_moving_speed : f32      // m/s, slower moves between two points count as stopped time
+}
+
+impl StopConfig {
+    pub fn new(dwell_radius : f32, min_duration : f64, min_moving_speed : f32) -> Self {
+        StopConfig {
+            dwell_radius : dwell_radius,
+            min_duration : min_duration,
+            min_moving_speed : min_moving_speed
+        }
+    }
+
+    pub fn get_dwell_radius(&self) -> f32 {
+        self.dwell_radius
+    }
+
+    pub fn get_min_duration(&self) -> f64 {
+        self.min_duration
+    }
+
+    pub fn get_min_moving_speed(&self) -> f32 {
+        self.min_moving_speed
+    }
+}
@@ -0,0 +1,40 @@
+#[derive(Clone, Copy, Debug)]
+pub struct ValidationConfig {
+    max_speed : f64,            // Speed (m/s) between two consecutive points above which the move is implausible
+    max_time_gap : f64,         // Seconds between two consecutive points of a segment above which a time gap is reported
+    max_distance_gap : f64,     // Meters between two consecutive points of a segment above which a distance gap is reported
+    duplicate_distance : f64,   // Consecutive points closer than this (meters) without time passing are duplicates
+    max_reported_points : usize // How many point indices are listed per issue, the count is always complete
+}
+
+impl ValidationConfig {
+    pub fn new(max_speed : f64, max_time_gap : f64, max_distance_gap : f64, duplicate_distance : f64, max_reported_points : usize) -> Self {
+        ValidationConfig {
+            max_speed : max_speed,
+            max_time_gap : max_time_gap,
+            max_distance_gap : max_distance_gap,
+            duplicate_distance : duplicate_distance,
+            max_reported_points : max_reported_points
+        }
+    }
+
+    pub fn get_max_speed(&self) -> f64 {
+        self.max_speed
+    }
+
+    pub fn get_max_time_gap(&self) -> f64 {
+        self.max_time_gap
+    }
+
+    pub fn get_max_distance_gap(&self) -> f64 {
+        self.max_distance_gap
+    }
+
+    pub fn get_duplicate_distance(&self) -> f64 {
+        self.duplicate_distance
+    }
+
+    pub fn get_max_reported_points(&self) -> usize {
+        self.max_reported_points
+    }
+}
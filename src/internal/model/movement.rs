@@ -0,0 +1,90 @@
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopKind {
+    Dwell,      // The rider stayed within the dwell radius (aid station, mechanical, crash)
+    Pause       // The recording was paused, the break between two segments
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Stop {
+    pub kind : StopKind,
+    pub x : f32,                // Mean position of the stop (where the recording was paused for pauses), in the projected space of the track
+    pub y : f32,
+    pub start_index : usize,    // First point of the stop, the last point before the break for pauses
+    pub end_index : usize,      // Last point of the stop, the first point after the break for pauses
+    pub start_seconds : f64,    // delta_seconds of start_index
+    pub end_seconds : f64,      // delta_seconds of end_index
+    pub duration : f64
+}
+
+/*
+    Where and for how long a rider stopped, times are in seconds, distances in meters and speeds in m/s.
+    Averages are None when no time passed
+*/
+#[derive(Clone, Debug, Serialize)]
+pub struct MovementSummary {
+    pub stops : Vec<Stop>,
+    pub elapsed_time : f64,                 // From the first to the last point, stops and pauses included
+    pub moving_time : f64,
+    pub stopped_time : f64,                 // elapsed_time - moving_time
+    pub distance : f64,                     // Travelled inside segments, the breaks between them are not counted
+    pub average_speed : Option<f64>,        // Over the elapsed time
+    pub average_moving_speed : Option<f64>  // Over the moving time
+}
+
+/*
+    Speed of a rider at one recorded point, speeds are in m/s, pace in seconds per kilometer and acceleration in m/s².
+    Values are None when they can not be computed (no time, single point segment)
+*/
+#[derive(Clone, Debug, Serialize)]
+pub struct SpeedSample {
+    pub index : usize,                      // Index of the point in the rider track
+    pub delta_seconds : f64,
+    pub reference_distance : Option<f32>,   // Distance along the reference of the matched point, None for unmatched tracks
+    pub speed : Option<f32>,                // From the points around this one
+    pub smoothed_speed : Option<f32>,       // Mean speed over the smoothing window
+    pub pace : Option<f32>,                 // Of the smoothed speed, None while stopped
+    pub acceleration : Option<f32>          // Longitudinal, change of the smoothed speed
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SpeedPercentile {
+    pub percentile : f32,
+    pub speed : f32
+}
+
+/*
+    Speed statistics of a rider, same units as SpeedSample. Averages are None when no time passed
+*/
+#[derive(Clone, Debug, Serialize)]
+pub struct SpeedStats {
+    pub max_speed : Option<f32>,                // Of the smoothed speed, raw speeds are too noisy
+    pub average_speed : Option<f32>,            // Over the elapsed time, look at MovementSummary
+    pub average_moving_speed : Option<f32>,     // Over the moving time, stops excluded (look at MovementSummary)
+    pub average_moving_pace : Option<f32>,
+    pub max_acceleration : Option<f32>,
+    pub max_deceleration : Option<f32>,         // Negative
+    pub percentiles : Vec<SpeedPercentile>      // Of the smoothed speed while moving
+}
+
+// Time spent by a rider on one bin of the reference and the speed on it
+#[derive(Clone, Debug, Serialize)]
+pub struct SpeedBin {
+    pub from_distance : f32,
+    pub to_distance : f32,
+    pub elapsed_time : f64,         // Seconds spent between points matched inside the bin
+    pub speed : Option<f32>,        // Time weighted mean of the smoothed speed, None if the rider was never matched inside the bin
+    pub pace : Option<f32>
+}
+
+/*
+    Speed, pace and acceleration of a rider, point by point and binned along the reference so riders can be compared at the same course position
+*/
+#[derive(Clone, Debug, Serialize)]
+pub struct SpeedProfile {
+    pub stats : SpeedStats,
+    pub samples : Vec<SpeedSample>,
+    pub bins : Vec<SpeedBin>        // Empty for tracks that were not matched
+}
@@ -1,2 +1,4 @@
 pub mod grid;
-pub mod points;
\ No newline at end of file
+pub mod points;
+pub mod dem;
+pub mod projection;
\ No newline at end of file
@@ -0,0 +1,68 @@
+/*
+    Layout of a digital elevation model raster in WGS84, rows go from north to south and columns from west to east.
+    (west, north) is the center of the first sample
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DemGrid {
+    pub west : f64,
+    pub north : f64,
+    pub lon_step : f64,     // Degrees between two columns
+    pub lat_step : f64,     // Degrees between two rows
+    pub width : usize,
+    pub height : usize
+}
+
+impl DemGrid {
+    /*
+        Returns (west, south, east, north) of the sample centers of the grid
+    */
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        let east = self.west + self.width.saturating_sub(1) as f64 * self.lon_step;
+        let south = self.north - self.height.saturating_sub(1) as f64 * self.lat_step;
+        (self.west, south, east, self.north)
+    }
+
+    pub fn contains(&self, lon : f64, lat : f64) -> bool {
+        let (west, south, east, north) = self.bounds();
+        (west..=east).contains(&lon) && (south..=north).contains(&lat)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DemTile {
+    pub grid : DemGrid,
+    pub elevations : Vec<f32>   // grid.width * grid.height samples in meters, row by row, NaN where the raster has no data
+}
+
+impl DemTile {
+    /*
+        Bilinear interpolation of the elevation at (@lon, @lat).
+        Samples without data are left out and the weights of the others renormalised.
+        Returns None outside of the tile or if none of the surrounding samples has data
+    */
+    pub fn sample(&self, lon : f64, lat : f64) -> Option<f32> {
+        let grid = &self.grid;
+        if !grid.contains(lon, lat) || self.elevations.len() < grid.width * grid.height {
+            return None;
+        }
+
+        let column = (lon - grid.west) / grid.lon_step;
+        let row = (grid.north - lat) / grid.lat_step;
+        let (left, top) = ((column.floor() as usize).min(grid.width - 1), (row.floor() as usize).min(grid.height - 1));
+        let (right, bottom) = ((left + 1).min(grid.width - 1), (top + 1).min(grid.height - 1));
+        let (column_fraction, row_fraction) = ((column - left as f64) as f32, (row - top as f64) as f32);
+
+        let corners = [
+            (top, left, (1.0 - column_fraction) * (1.0 - row_fraction)),
+            (top, right, column_fraction * (1.0 - row_fraction)),
+            (bottom, left, (1.0 - column_fraction) * row_fraction),
+            (bottom, right, column_fraction * row_fraction)
+        ];
+        let (weighted, weights) = corners.iter()
+            .map(|&(row, column, weight)| (self.elevations[row * grid.width + column], weight))
+            .filter(|(elevation, weight)| !elevation.is_nan() && *weight > 0.0)
+            .fold((0.0, 0.0), |(weighted, weights), (elevation, weight)| (weighted + elevation * weight, weights + weight));
+
+        (weights > 0.0).then(|| weighted / weights)
+    }
+}
@@ -2,6 +2,7 @@ pub trait Point {
     fn x(&self) -> f32;
     fn y(&self) -> f32;
     fn z(&self) -> f32;
+    fn has_elevation(&self) -> bool;
     fn delta_seconds(&self) -> f64;
 }
 
@@ -10,7 +11,92 @@ pub struct SpatialPoint {
     pub lon: f64,
     pub lat: f64,
     pub elev: Option<f64>,
-    pub delta_seconds: Option<f64>
+    pub delta_seconds: Option<f64>,
+    pub hdop: Option<f64>,      // Horizontal dilution of precision reported by the receiver
+    pub accuracy: Option<f64>   // Estimated horizontal error in meters reported by the device (phones, some watches)
+}
+
+// Sensor values recorded with a track point, None when the device did not report the channel
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SensorPoint {
+    pub heart_rate : Option<f32>,   // Beats per minute
+    pub cadence : Option<f32>,      // Revolutions (or steps) per minute
+    pub power : Option<f32>,        // Watts
+    pub temperature : Option<f32>,  // Degrees Celsius
+    pub speed : Option<f32>         // Meters per second, as reported by the device
+}
+
+// Channels of a SensorPoint
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SensorChannel {
+    HeartRate,
+    Cadence,
+    Power,
+    Temperature,
+    Speed
+}
+
+impl SensorChannel {
+    pub const ALL : [SensorChannel; 5] = [SensorChannel::HeartRate, SensorChannel::Cadence, SensorChannel::Power, SensorChannel::Temperature, SensorChannel::Speed];
+}
+
+impl SensorPoint {
+    pub fn is_empty(&self) -> bool {
+        *self == SensorPoint::default()
+    }
+
+    pub fn get(&self, channel : SensorChannel) -> Option<f32> {
+        match channel {
+            SensorChannel::HeartRate => self.heart_rate,
+            SensorChannel::Cadence => self.cadence,
+            SensorChannel::Power => self.power,
+            SensorChannel::Temperature => self.temperature,
+            SensorChannel::Speed => self.speed
+        }
+    }
+
+    pub fn set(&mut self, channel : SensorChannel, value : f32) {
+        let field = match channel {
+            SensorChannel::HeartRate => &mut self.heart_rate,
+            SensorChannel::Cadence => &mut self.cadence,
+            SensorChannel::Power => &mut self.power,
+            SensorChannel::Temperature => &mut self.temperature,
+            SensorChannel::Speed => &mut self.speed
+        };
+        *field = Some(value);
+    }
+
+    /*
+        Returns the values at @fraction (0...1) of the way from self to @next,
+        a channel recorded on one side only is kept while that side is the closest
+    */
+    pub fn interpolate(&self, next : &SensorPoint, fraction : f32) -> SensorPoint {
+        let mut interpolated = SensorPoint::default();
+        for channel in SensorChannel::ALL {
+            let value = match (self.get(channel), next.get(channel)) {
+                (Some(from), Some(to)) => Some(from + (to - from) * fraction),
+                (Some(from), None) if fraction < 0.5 => Some(from),
+                (None, Some(to)) if fraction >= 0.5 => Some(to),
+                _ => None
+            };
+            if let Some(value) = value {
+                interpolated.set(channel, value);
+            }
+        }
+        interpolated
+    }
+}
+
+/*
+    Sensor channels are stored next to the points of a track (one SensorPoint per point) or not at all.
+    Returns @sensors or an empty vector if none of them holds a value
+*/
+pub fn keep_recorded(sensors : Vec<SensorPoint>) -> Vec<SensorPoint> {
+    if sensors.iter().all(SensorPoint::is_empty) {
+        Vec::new()
+    } else {
+        sensors
+    }
 }
 
 impl From<SpatialPoint> for (f64, f64) {
@@ -24,6 +110,7 @@ pub struct RefPoint {
     pub x: f32,
     pub y: f32,
     pub z: f32,
+    pub has_elevation : bool,   // False when the point was loaded without elevation, z is then 0
     pub total_distance: f32
 }
 
@@ -32,7 +119,10 @@ pub struct RiderPoint {
     pub x: f32,
     pub y: f32,
     pub z: f32,
-    pub delta_seconds : f64
+    pub has_elevation : bool,       // False when the point was loaded without elevation, z is then 0
+    pub delta_seconds : f64,
+    pub hdop : Option<f32>,         // As recorded, look at SpatialPoint
+    pub accuracy : Option<f32>      // As recorded, look at SpatialPoint
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -42,7 +132,10 @@ pub struct MatchPoint {
     pub direction_similarity : f32,
     pub lateral: f32,
     pub distance_z: f32,
-    pub count_to_error : bool
+    pub has_elevation : bool,   // False when the rider point or the matched reference point has no elevation, distance_z is then meaningless
+    pub count_to_error : bool,
+    pub segment_start : bool,   // First point recorded after a break in the rider track
+    pub gap_end : bool          // First point recorded after a recording gap inside a segment (look at gap_detection)
 }
 
 impl Point for RefPoint {
@@ -59,6 +152,10 @@ impl Point for RefPoint {
     fn z(&self) -> f32 {
         self.z
     }
+
+    fn has_elevation(&self) -> bool {
+        self.has_elevation
+    }
 }
 
 impl Point for RiderPoint {
@@ -75,5 +172,9 @@ impl Point for RiderPoint {
     fn z(&self) -> f32 {
         self.z
     }
+
+    fn has_elevation(&self) -> bool {
+        self.has_elevation
+    }
 }
 
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+use crate::internal::model::config::coordinates::CoordinatesConfig;
+
+// Metric projections a track can be converted to before analysis
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectionKind {
+    Utm,                    // UTM zone of the track (EPSG:326xx / EPSG:327xx), for tracks inside of a single zone
+    TransverseMercator,     // Transverse Mercator centred on the track, for regional tracks spanning UTM zones
+    AzimuthalEquidistant    // Azimuthal equidistant centred on the track, for polar and continental tracks
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hemisphere {
+    North,
+    South
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectionWarningKind {
+    ZoneSpan,           // The track crosses a UTM zone boundary
+    HemisphereSpan,     // The track crosses the equator
+    LargeExtent,        // The track is too large for any projection to keep distances exact
+    Polar               // The track goes beyond the latitudes covered by UTM (80°S, 84°N)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProjectionWarning {
+    pub kind : ProjectionWarningKind,
+    pub message : String
+}
+
+/*
+    Metric projection selected for a track by projection_selection::select_projection, crs is an EPSG code or a PROJ string
+*/
+#[derive(Clone, Debug, Serialize)]
+pub struct ProjectionChoice {
+    pub crs : String,
+    pub kind : ProjectionKind,
+    pub zone : Option<u8>,          // UTM zone of the centre of the track, even when another kind was selected
+    pub hemisphere : Hemisphere,    // Of the centre of the track
+    pub center_lon : f64,
+    pub center_lat : f64,
+    pub warnings : Vec<ProjectionWarning>
+}
+
+impl ProjectionChoice {
+    /*
+        Conversion settings from @origin_space to the selected projection
+    */
+    pub fn to_config(&self, origin_space : &str) -> CoordinatesConfig {
+        CoordinatesConfig::new(origin_space.to_string(), self.crs.clone())
+    }
+}
@@ -1,6 +1,8 @@
+use std::borrow::Cow;
+
 use chrono::{DateTime, Utc};
 
-use crate::internal::model::spatial::points::SpatialPoint;
+use crate::internal::model::spatial::points::{SensorPoint, SpatialPoint};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct TrackOrigin {
@@ -8,7 +10,97 @@ pub struct TrackOrigin {
     pub epsg_y: f64,
 }
 
+/*
+    A continuous run of recorded points, points [start, start + count) of the owning track belong to the segment.
+    Consecutive segments are separated by a recording break (pause, device restart, new day), the space between them is a gap not a jump.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackSegment {
+    pub start : usize,          // Index of the first point of the segment
+    pub count : usize,          // Number of points in the segment
+    pub track_index : usize,    // Index of the track (SpatialTrack.tracks) the segment was recorded in
+}
+
+impl TrackSegment {
+    /*
+        Returns the segment list of a track with @point_count points that was recorded without any breaks
+    */
+    pub fn single(point_count : usize) -> Vec<TrackSegment> {
+        if point_count == 0 {
+            return Vec::new();
+        }
+        vec![TrackSegment { start: 0, count: point_count, track_index: 0 }]
+    }
+
+    /*
+        Returns the @segments of a track with @point_count points, or its single segment (look at single) when no segments were recorded
+    */
+    pub fn or_single(segments : &[TrackSegment], point_count : usize) -> Cow<'_, [TrackSegment]> {
+        if segments.is_empty() { Cow::Owned(TrackSegment::single(point_count)) } else { Cow::Borrowed(segments) }
+    }
+
+    pub fn end(&self) -> usize {
+        self.start + self.count
+    }
+}
+
+/*
+    Appends the points [@start, @end) as a segment of the track @track_index to @segments, empty ranges are ignored
+*/
+pub fn push_segment(segments : &mut Vec<TrackSegment>, start : usize, end : usize, track_index : usize) {
+    if end > start {
+        segments.push(TrackSegment { start, count: end - start, track_index });
+    }
+}
+
+/*
+    Returns true if the point at @index starts a new segment, meaning the previous point is on the other side of a recording break.
+    The first point of a track is never considered a break.
+*/
+#[inline]
+pub fn is_segment_break(segments : &[TrackSegment], index : usize) -> bool {
+    index != 0 && segments.binary_search_by_key(&index, |segment| segment.start).is_ok()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TrackInfo {
+    pub name : Option<String>,
+    pub description : Option<String>,
+    pub kind : Option<String>,  // Activity type as declared by the recording device
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TrackMetadata {
+    pub name : Option<String>,
+    pub description : Option<String>,
+    pub author : Option<String>,
+    pub creator : Option<String>,           // Application or device that wrote the file
+    pub time : Option<DateTime<Utc>>,       // Creation time of the file, not of the first point
+}
+
+/*
+    A planned course (<rte> in gpx), unlike a track its points are not recorded and carry no time
+*/
+#[derive(Clone, Debug, Default)]
+pub struct SpatialRoute {
+    pub info : TrackInfo,
+    pub points : Vec<SpatialPoint>
+}
+
+#[derive(Clone, Debug)]
+pub struct Waypoint {
+    pub info : TrackInfo,
+    pub point : SpatialPoint
+}
+
 pub struct SpatialTrack {
     pub track : Vec<SpatialPoint>,
-    pub start_time :  DateTime<Utc>
-}
\ No newline at end of file
+    pub sensors : Vec<SensorPoint>,     // Empty or one entry per point of track (look at keep_recorded)
+    pub start_time :  DateTime<Utc>,
+    pub segments : Vec<TrackSegment>,
+    pub laps : Vec<usize>,              // Index of the first point of every lap after the first (TCX), laps split a continuous recording and are not breaks
+    pub tracks : Vec<TrackInfo>,
+    pub routes : Vec<SpatialRoute>,
+    pub waypoints : Vec<Waypoint>,
+    pub metadata : TrackMetadata
+}
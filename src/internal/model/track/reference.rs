@@ -1,9 +1,27 @@
-use crate::internal::model::{spatial::points::RefPoint, track::common::TrackOrigin};
+use crate::internal::model::{spatial::points::RefPoint, track::common::{TrackOrigin, TrackSegment}};
+
+/*
+    A named point of interest of a course (start, finish, feed zone, timing point) bound to the closest reference point
+*/
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    pub name : String,
+    pub description : Option<String>,
+    pub x : f32,
+    pub y : f32,
+    pub z : f32,
+    pub has_elevation : bool,   // False when neither the waypoint nor its reference point has an elevation
+    pub reference_index : u32,  // Index of the closest point in ReferenceTrack.track
+    pub total_distance : f32,   // Distance along the reference track at reference_index
+    pub offset : f32            // Distance between the checkpoint and the reference point it is bound to
+}
 
 #[derive(Clone, Debug)]
 pub struct ReferenceTrack {
     pub class : String,
     pub projection : String,
     pub origin : TrackOrigin,
-    pub track : Vec<RefPoint>
+    pub track : Vec<RefPoint>,
+    pub segments : Vec<TrackSegment>,
+    pub checkpoints : Vec<Checkpoint>
 }
\ No newline at end of file
@@ -2,7 +2,7 @@
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
-use crate::internal::model::{spatial::points::{MatchPoint, RiderPoint}, track::common::TrackOrigin};
+use crate::internal::model::{spatial::points::{MatchPoint, RiderPoint, SensorPoint}, track::common::{TrackOrigin, TrackSegment}};
 
 #[derive(Clone)]
 pub struct MatchedTrack {
@@ -11,7 +11,25 @@ pub struct MatchedTrack {
     pub projection : String,
     pub start_time : DateTime<Utc>,
     pub track_origin : TrackOrigin,
-    pub track : Vec<MatchPoint>
+    pub track : Vec<MatchPoint>,
+    pub sensors : Vec<SensorPoint>,     // Empty or one entry per point of track, the sensor values of the matched rider point
+    pub gaps : Vec<TrackGap>            // Recording gaps inside the segments of the rider track (look at gap_detection)
+}
+
+/*
+    Missing data between two consecutive points of a rider segment (lost fix, battery saver), not a recording break.
+    The filled points are never part of MatchedTrack.track so it stays aligned with the rider points
+*/
+#[derive(Clone, Debug)]
+pub struct TrackGap {
+    pub start_index : usize,        // Last point before the gap
+    pub end_index : usize,          // First point after the gap
+    pub start_seconds : f64,
+    pub end_seconds : f64,
+    pub distance : f32,             // Straight line between the points around the gap
+    pub reference_start : u32,      // Reference point matched before the gap
+    pub reference_end : u32,        // Reference point matched after the gap
+    pub filled : Vec<MatchPoint>    // Points standing for the missing data, empty with GapPolicy::Leave
 }
 
 #[derive(Clone)]
@@ -19,7 +37,10 @@ pub struct RiderTrack {
     pub rider_uuid : Uuid,
     pub variant : u32,
     pub projection : String,
+    pub sport : Option<String>,         // Activity type as declared by the recording device, selects the outlier filter preset
     pub start_time : DateTime<Utc>,
     pub track_origin : TrackOrigin,
-    pub track : Vec<RiderPoint>
+    pub track : Vec<RiderPoint>,
+    pub sensors : Vec<SensorPoint>,     // Empty or one entry per point of track
+    pub segments : Vec<TrackSegment>
 }
\ No newline at end of file
@@ -0,0 +1,62 @@
+use serde::Serialize;
+
+// How bad an issue is, Error means the track can not be analysed as is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueLevel {
+    Info,
+    Warning,
+    Error
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    NoPoints,               // The file holds no recorded point (routes and waypoints only)
+    InvalidCoordinates,     // Latitude or longitude out of range or not a number
+    MissingTimestamps,
+    MissingElevations,
+    NonMonotonicTime,       // A point is recorded before the point preceding it
+    TimeOutOfRange,         // The time of a point can not be represented as a date (offsets of hundreds of thousands of years)
+    DuplicatePoints,        // Same position as the previous point without time passing
+    ImplausibleSpeed,       // Move faster than ValidationConfig.max_speed, or a move without time passing
+    TimeGap,                // Longer than ValidationConfig.max_time_gap without a point inside a segment
+    DistanceGap             // Jump longer than ValidationConfig.max_distance_gap inside a segment
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ValidationIssue {
+    pub kind : IssueKind,
+    pub level : IssueLevel,
+    pub count : usize,              // Number of offending points
+    pub point_indices : Vec<usize>, // First offending points (look at ValidationConfig.max_reported_points)
+    pub message : String
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct BoundingBox {
+    pub min_lon : f64,
+    pub min_lat : f64,
+    pub max_lon : f64,
+    pub max_lat : f64,
+    pub min_elev : Option<f64>,
+    pub max_elev : Option<f64>
+}
+
+/*
+    Outcome of the validation of a SpatialTrack, built by track_validation::validate_track
+*/
+#[derive(Clone, Debug, Serialize)]
+pub struct ValidationReport {
+    pub valid : bool,                       // No issue of level Error
+    pub point_count : usize,
+    pub segment_count : usize,
+    pub route_count : usize,
+    pub waypoint_count : usize,
+    pub start_time : Option<String>,        // RFC 3339 time of the first point, None if it is out of range (look at IssueKind::TimeOutOfRange)
+    pub end_time : Option<String>,          // RFC 3339 time of the last point, None if it is out of range
+    pub time_span : Option<f64>,            // Seconds between the first and the last timed point
+    pub total_distance : f64,               // Meters along the segments, breaks between segments excluded
+    pub bounding_box : Option<BoundingBox>, // Over every point with valid coordinates
+    pub issues : Vec<ValidationIssue>
+}
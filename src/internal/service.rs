@@ -1,3 +1,14 @@
 pub mod snapping;
 pub mod geo_conversions;
-pub mod track_processor;
\ No newline at end of file
+pub mod track_processor;
+pub mod track_export;
+pub mod track_validation;
+pub mod track_filter;
+pub mod track_smoothing;
+pub mod track_simplification;
+pub mod track_resampling;
+pub mod stop_detection;
+pub mod gap_detection;
+pub mod elevation;
+pub mod speed_profile;
+pub mod projection_selection;
\ No newline at end of file
@@ -0,0 +1,114 @@
+use std::{collections::HashMap, path::Path, sync::{Arc, LazyLock, Mutex}};
+
+use rayon::prelude::*;
+
+use crate::{errors::service_errors::ServiceError, internal::{io::dem_reader::DemStore, model::{analysis::{ElevationSummary, ascent_descent}, config::elevation::{ElevationConfig, ElevationMode}, spatial::points::Point, track::{common::{TrackOrigin, TrackSegment}, reference::ReferenceTrack, riders::RiderTrack}}, service::geo_conversions::local_to_spatial}};
+
+// DEM stores opened by the service keyed by directory, the rasters read stay cached for the next tracks
+static DEM_STORES : LazyLock<Mutex<HashMap<String, Arc<DemStore>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/*
+    Samples the DEM of @config under every point of @reference_track and applies it to their z following the ElevationMode of @config.
+    Meant to run before snapping so MatchPoint.distance_z is computed from the corrected elevations.
+    Returns the corrected ascent and descent of the course, look at ElevationSummary
+    Throws: CoordinateConversionError if the points can not be converted to WGS84, IOError if the DEM directory can not be listed or a DEM tile can not be read
+*/
+pub fn correct_reference(reference_track : &mut ReferenceTrack, config : &ElevationConfig) -> Result<ElevationSummary, ServiceError> {
+    let dem = dem_store(config)?;
+    let elevations = sample_track(&reference_track.track, &reference_track.origin, &reference_track.projection, &dem)?;
+    let (corrected, offset) = corrected_elevations(&reference_track.track, &elevations, config.get_mode());
+    for (point, z) in reference_track.track.iter_mut().zip(corrected) {
+        if let Some(z) = z {
+            point.z = z;
+            point.has_elevation = true;
+        }
+    }
+    Ok(summarize(&reference_track.track, &reference_track.segments, &elevations, offset, config))
+}
+
+/*
+    Samples the DEM of @config under every point of @rider_track and applies it to their z following the ElevationMode of @config.
+    Meant to run before snapping so MatchPoint.distance_z is computed from the corrected elevations.
+    Returns the corrected ascent and descent of the rider, look at ElevationSummary
+    Throws: CoordinateConversionError if the points can not be converted to WGS84, IOError if the DEM directory can not be listed or a DEM tile can not be read
+*/
+pub fn correct_rider(rider_track : &mut RiderTrack, config : &ElevationConfig) -> Result<ElevationSummary, ServiceError> {
+    let dem = dem_store(config)?;
+    let elevations = sample_track(&rider_track.track, &rider_track.track_origin, &rider_track.projection, &dem)?;
+    let (corrected, offset) = corrected_elevations(&rider_track.track, &elevations, config.get_mode());
+    for (point, z) in rider_track.track.iter_mut().zip(corrected) {
+        if let Some(z) = z {
+            point.z = z;
+            point.has_elevation = true;
+        }
+    }
+    Ok(summarize(&rider_track.track, &rider_track.segments, &elevations, offset, config))
+}
+
+/*
+    Returns the DemStore of the dem_directory of @config, it is opened the first time and shared by the following calls
+    Throws: IOError if the directory can not be listed
+*/
+fn dem_store(config : &ElevationConfig) -> Result<Arc<DemStore>, ServiceError> {
+    let mut stores = DEM_STORES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(store) = stores.get(config.get_dem_directory()) {
+        return Ok(store.clone());
+    }
+    let store = Arc::new(DemStore::open(Path::new(config.get_dem_directory())).map_err(ServiceError::io_error)?);
+    if store.is_empty() {
+        tracing::warn!("No DEM file found in {}", config.get_dem_directory());
+    }
+    stores.insert(config.get_dem_directory().to_string(), store.clone());
+    Ok(store)
+}
+
+/*
+    DEM elevation under every local point of @points, None where no tile covers the point
+*/
+fn sample_track<T : Point>(points : &[T], track_origin : &TrackOrigin, projection : &str, dem : &DemStore) -> Result<Vec<Option<f32>>, ServiceError> {
+    let spatial_points = local_to_spatial(points, track_origin, projection)?;
+    spatial_points.par_iter()
+        .map(|point| dem.sample(point.lon, point.lat).map_err(ServiceError::io_error))
+        .collect()
+}
+
+/*
+    New z of every point of @points, and the median offset between the GPS and the @elevations sampled from the DEM (0 if none was sampled).
+    Points loaded without elevation take the DEM elevation in both modes, they are left without elevation (None) outside of the DEM
+*/
+fn corrected_elevations<T : Point>(points : &[T], elevations : &[Option<f32>], mode : ElevationMode) -> (Vec<Option<f32>>, f32) {
+    let mut offsets : Vec<f32> = points.iter()
+        .zip(elevations)
+        .filter(|(point, _)| point.has_elevation())
+        .filter_map(|(point, elevation)| elevation.map(|elevation| point.z() - elevation))
+        .filter(|offset| offset.is_finite())
+        .collect();
+    offsets.sort_by(f32::total_cmp);
+    let offset = match offsets.len() {
+        0 => 0.0,
+        count if count % 2 == 0 => (offsets[count / 2 - 1] + offsets[count / 2]) / 2.0,
+        count => offsets[count / 2]
+    };
+
+    let corrected = points.iter()
+        .zip(elevations)
+        .map(|(point, elevation)| match (mode, elevation, point.has_elevation()) {
+            (ElevationMode::Replace, Some(elevation), _) | (_, Some(elevation), false) => Some(*elevation),
+            (_, _, true) => Some(point.z() - offset),
+            (_, None, false) => None
+        })
+        .collect();
+    (corrected, offset)
+}
+
+fn summarize<T : Point>(points : &[T], segments : &[TrackSegment], elevations : &[Option<f32>], offset : f32, config : &ElevationConfig) -> ElevationSummary {
+    let (ascent, descent) = ascent_descent(points, segments, config.get_ascent_threshold());
+    let sampled_points = elevations.iter().filter(|elevation| elevation.is_some()).count();
+    ElevationSummary {
+        ascent,
+        descent,
+        sampled_points,
+        missing_points : elevations.len() - sampled_points,
+        offset
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{errors::service_errors::ServiceError, internal::{model::{config::gaps::{GapConfig, GapPolicy}, spatial::points::{MatchPoint, RefPoint, RiderPoint}, track::{common::TrackSegment, reference::ReferenceTrack, riders::{MatchedTrack, RiderTrack, TrackGap}}}, service::geo_conversions::planar_distance}};
+
+/*
+    Returns the index of the first point after every recording gap of @rider_track, in order.
+    Gaps are intervals between two points of a segment longer than max_time_gap or jumps longer than max_distance_gap, breaks between segments are never gaps
+*/
+pub fn gap_ends(rider_track : &RiderTrack, config : &GapConfig) -> Vec<usize> {
+    let points = &rider_track.track;
+    let segments = TrackSegment::or_single(&rider_track.segments, points.len());
+
+    let mut ends = Vec::new();
+    for segment in segments.iter() {
+        for end_index in segment.start + 1..segment.end() {
+            let (before, after) = (&points[end_index - 1], &points[end_index]);
+            if after.delta_seconds - before.delta_seconds > config.get_max_time_gap() || planar_distance(before, after) > config.get_max_distance_gap() {
+                ends.push(end_index);
+            }
+        }
+    }
+    ends
+}
+
+/*
+    Finds the recording gaps of @rider_track (look at gap_ends) and stores them in @matched_track (the result of snapping @rider_track on @reference_track).
+    The missing part of every gap is filled following the GapPolicy of @config, look at GapPolicy
+    Throws: InvalidData if @matched_track was not snapped from @rider_track (inverse snapping) or onto @reference_track
+*/
+pub fn detect_gaps(rider_track : &RiderTrack, matched_track : &mut MatchedTrack, reference_track : &ReferenceTrack, config : &GapConfig) -> Result<(), ServiceError> {
+    if rider_track.track.len() != matched_track.track.len() {
+        return Err(ServiceError::invalid_data("matched track does not hold a match for every point of the rider track"));
+    }
+    if matched_track.track.iter().any(|matched| matched.reference_index as usize >= reference_track.track.len()) {
+        return Err(ServiceError::invalid_data("matched track points outside of the reference track"));
+    }
+
+    let points = &rider_track.track;
+    let gaps = gap_ends(rider_track, config).into_iter().map(|end_index| {
+        let start_index = end_index - 1;
+        let (before, after) = (&points[start_index], &points[end_index]);
+        let (matched_before, matched_after) = (&matched_track.track[start_index], &matched_track.track[end_index]);
+        let filled = match config.get_policy() {
+            GapPolicy::Leave => Vec::new(),
+            GapPolicy::Linear => fill_linear(before, after, matched_before, matched_after, &reference_track.track, config),
+            GapPolicy::AlongReference => fill_along_reference(before, after, matched_before, matched_after, &reference_track.track, config)
+        };
+        TrackGap {
+            start_index,
+            end_index,
+            start_seconds : before.delta_seconds,
+            end_seconds : after.delta_seconds,
+            distance : planar_distance(before, after),
+            reference_start : matched_before.reference_index,
+            reference_end : matched_after.reference_index,
+            filled
+        }
+    }).collect();
+
+    matched_track.gaps = gaps;
+    Ok(())
+}
+
+/*
+    Times of the points filled between @before and @after, one every fill_interval seconds.
+    Gaps without time (distance gaps of untimed tracks) are not filled
+*/
+fn fill_times(before : &RiderPoint, after : &RiderPoint, config : &GapConfig) -> Vec<f64> {
+    let duration = after.delta_seconds - before.delta_seconds;
+    let interval = config.get_fill_interval();
+    if !interval.is_finite() || interval <= 0.0 || duration <= interval {
+        return Vec::new();
+    }
+    let count = (duration / interval).ceil() as usize - 1;
+    (1..=count).map(|step| before.delta_seconds + step as f64 * interval).collect()
+}
+
+/*
+    Fills the gap with a straight line from @before to @after, every point is matched to the closest reference point
+    between the references matched around the gap
+*/
+fn fill_linear(before : &RiderPoint, after : &RiderPoint, matched_before : &MatchPoint, matched_after : &MatchPoint, refs : &[RefPoint], config : &GapConfig) -> Vec<MatchPoint> {
+    let duration = after.delta_seconds - before.delta_seconds;
+    let first_reference = matched_before.reference_index.min(matched_after.reference_index) as usize;
+    let last_reference = matched_before.reference_index.max(matched_after.reference_index) as usize;
+
+    fill_times(before, after, config).into_iter()
+        .map(|time| {
+            let fraction = ((time - before.delta_seconds) / duration) as f32;
+            let x = before.x + (after.x - before.x) * fraction;
+            let y = before.y + (after.y - before.y) * fraction;
+            let z = before.z + (after.z - before.z) * fraction;
+
+            let (reference_index, lateral) = (first_reference..=last_reference)
+                .map(|index| (index, (refs[index].x - x).hypot(refs[index].y - y)))
+                .min_by(|(_, left), (_, right)| left.total_cmp(right))
+                .unwrap_or((first_reference, 0.0));
+
+            let has_elevation = before.has_elevation && after.has_elevation && refs[reference_index].has_elevation;
+            filled_point(reference_index, time, matched_before.direction_similarity, lateral, z - refs[reference_index].z, has_elevation)
+        })
+        .collect()
+}
+
+/*
+    Fills the gap with points spread along the reference from the point matched before the gap to the one matched after it,
+    at a constant speed. Falls back to fill_linear if the rider did not move forward on the reference
+*/
+fn fill_along_reference(before : &RiderPoint, after : &RiderPoint, matched_before : &MatchPoint, matched_after : &MatchPoint, refs : &[RefPoint], config : &GapConfig) -> Vec<MatchPoint> {
+    let (first_reference, last_reference) = (matched_before.reference_index as usize, matched_after.reference_index as usize);
+    let (from_distance, to_distance) = (refs[first_reference].total_distance, refs[last_reference].total_distance);
+    if last_reference <= first_reference || to_distance <= from_distance {
+        return fill_linear(before, after, matched_before, matched_after, refs, config);
+    }
+
+    let duration = after.delta_seconds - before.delta_seconds;
+    let along = &refs[first_reference..=last_reference];
+    fill_times(before, after, config).into_iter()
+        .map(|time| {
+            let fraction = ((time - before.delta_seconds) / duration) as f32;
+            let distance = from_distance + (to_distance - from_distance) * fraction;
+            let reference_index = first_reference + along.partition_point(|point| point.total_distance < distance).min(along.len() - 1);
+            let z = before.z + (after.z - before.z) * fraction;
+
+            let has_elevation = before.has_elevation && after.has_elevation && refs[reference_index].has_elevation;
+            filled_point(reference_index, time, 1.0, 0.0, z - refs[reference_index].z, has_elevation)
+        })
+        .collect()
+}
+
+fn filled_point(reference_index : usize, delta_seconds : f64, direction_similarity : f32, lateral : f32, distance_z : f32, has_elevation : bool) -> MatchPoint {
+    MatchPoint {
+        reference_index : reference_index as u32,
+        delta_seconds,
+        direction_similarity,
+        lateral,
+        distance_z,
+        has_elevation,
+        count_to_error : false,
+        segment_start : false,
+        gap_end : false
+    }
+}
@@ -1,7 +1,45 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
 use proj::{Coord};
+use rayon::prelude::*;
+
+use crate::{errors::service_errors::ServiceError, internal::model::{config::coordinates::CoordinatesConfig, track::{common::{TrackOrigin, TrackSegment, Waypoint, is_segment_break}, reference::Checkpoint}}};
+use crate::internal::model::spatial::points::{Point, RefPoint, RiderPoint, SpatialPoint};
+
+// Coordinate space of the loaded and exported track files
+pub const WGS84 : &str = "EPSG:4326";
+// Mean radius of the earth in meters (IUGG)
+pub const EARTH_RADIUS : f64 = 6_371_008.8;
+
+// Points converted by a single rayon task, smaller chunks cost more in scheduling than they win
+const CONVERSION_CHUNK_SIZE : usize = 2048;
+// Projections kept per thread before the cache is emptied
+const PROJECTION_CACHE_SIZE : usize = 64;
+
+thread_local! {
+    // Projections built by this thread keyed by (origin space, destination space), look at cached_projection
+    static PROJECTIONS : RefCell<HashMap<(String, String), Rc<proj::Proj>>> = RefCell::new(HashMap::new());
+}
+
+/*
+    Returns the great circle distance in meters between the WGS84 points @from and @to (haversine, elevation ignored)
+*/
+pub fn haversine_distance(from : &SpatialPoint, to : &SpatialPoint) -> f64 {
+    let (from_lat, to_lat) = (from.lat.to_radians(), to.lat.to_radians());
+    let delta_lat = to_lat - from_lat;
+    let delta_lon = (to.lon - from.lon).to_radians();
+
+    let half_chord = (delta_lat / 2.0).sin().powi(2) + from_lat.cos() * to_lat.cos() * (delta_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * half_chord.sqrt().min(1.0).asin()
+}
 
-use crate::{errors::service_errors::ServiceError, internal::model::{config::coordinates::CoordinatesConfig, track::common::TrackOrigin}};
-use crate::internal::model::spatial::points::{RefPoint, RiderPoint, SpatialPoint};
+/*
+    Returns the horizontal distance between the projected points @from and @to, in the units of their projection
+*/
+#[inline(always)]
+pub fn planar_distance<T : Point>(from : &T, to : &T) -> f32 {
+    (to.x() - from.x()).hypot(to.y() - from.y())
+}
 
 /*
     Tries to convert from a vector of @spatial_points into a vector of rider points
@@ -16,7 +54,10 @@ pub fn spatial_to_rider(spatial_points : &[SpatialPoint], track_origin : &TrackO
             x: (x64 - track_origin.epsg_x) as f32,
             y: (y64 - track_origin.epsg_y) as f32,
             z: point.elev.unwrap_or(0.0) as f32,
-            delta_seconds : point.delta_seconds.unwrap_or(0.0)
+            has_elevation: point.elev.is_some(),
+            delta_seconds : point.delta_seconds.unwrap_or(0.0),
+            hdop : point.hdop.map(|hdop| hdop as f32),
+            accuracy : point.accuracy.map(|accuracy| accuracy as f32)
         }
     })?;
     Ok(reference_points)
@@ -24,10 +65,10 @@ pub fn spatial_to_rider(spatial_points : &[SpatialPoint], track_origin : &TrackO
 
 /*
     Tries to convert from a vector of @spatial_points into a vector of reference points
-    and computes a rolling distance in the new coordinate space.
+    and computes a rolling distance in the new coordinate space, the distance does not grow across the breaks between @segments.
     Throws: CoordinateConversionError if any of the initial points can not be converted to the new space 
 */
-pub fn spatial_to_reference(spatial_points : &[SpatialPoint], config : &CoordinatesConfig) -> Result<(TrackOrigin, Vec<RefPoint>), ServiceError> {
+pub fn spatial_to_reference(spatial_points : &[SpatialPoint], segments : &[TrackSegment], config : &CoordinatesConfig) -> Result<(TrackOrigin, Vec<RefPoint>), ServiceError> {
      let track_origin = get_track_origin(spatial_points, config, |(x64,y64), _| {
         TrackOrigin { 
             epsg_x: x64, 
@@ -40,12 +81,18 @@ pub fn spatial_to_reference(spatial_points : &[SpatialPoint], config : &Coordina
             x: (x64 - track_origin.epsg_x) as f32,
             y: (y64 - track_origin.epsg_y) as f32,
             z: point.elev.unwrap_or(0.0) as f32,
+            has_elevation: point.elev.is_some(),
             total_distance: 0.0, // Placeholder
         }
     })?;
 
     let mut total_distance = 0.0f32;
     for i in 1..ref_points.len() {
+        if is_segment_break(segments, i) {
+            ref_points[i].total_distance = total_distance;
+            continue;
+        }
+
         let previous_point = ref_points[i-1];
         let current_point = ref_points[i];
         
@@ -59,50 +106,147 @@ pub fn spatial_to_reference(spatial_points : &[SpatialPoint], config : &Coordina
     Ok((track_origin, ref_points))
 }
 
+/*
+    Converts the named @waypoints into checkpoints of the reference track made of @ref_points with origin @track_origin.
+    Every checkpoint is bound to the closest reference point, unnamed waypoints are skipped.
+    Throws: CoordinateConversionError if any of the waypoints can not be converted to the new space
+*/
+pub fn spatial_to_checkpoints(waypoints : &[Waypoint], ref_points : &[RefPoint], track_origin : &TrackOrigin, config : &CoordinatesConfig) -> Result<Vec<Checkpoint>, ServiceError> {
+    let named_waypoints : Vec<(&str, &Waypoint)> = waypoints.iter()
+        .filter_map(|waypoint| waypoint.info.name.as_deref().map(|name| (name, waypoint)))
+        .collect();
+
+    if named_waypoints.is_empty() || ref_points.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let spatial_points : Vec<SpatialPoint> = named_waypoints.iter().map(|(_, waypoint)| waypoint.point).collect();
+    let local_points = convert_to_space(&spatial_points, config, |(x64, y64), point| {
+        (
+            (x64 - track_origin.epsg_x) as f32,
+            (y64 - track_origin.epsg_y) as f32,
+            point.elev.map(|elevation| elevation as f32)
+        )
+    })?;
+
+    Ok(named_waypoints.iter().zip(local_points).map(|((name, waypoint), (x, y, z))| {
+        let mut best_squared_distance = f32::MAX;
+        let mut best_index = 0usize;
+        for (index, ref_point) in ref_points.iter().enumerate() {
+            let dx = ref_point.x - x;
+            let dy = ref_point.y - y;
+            let squared_distance = dx * dx + dy * dy;
+            if squared_distance < best_squared_distance {
+                best_squared_distance = squared_distance;
+                best_index = index;
+            }
+        }
+
+        let closest = ref_points[best_index];
+        Checkpoint {
+            name : name.to_string(),
+            description : waypoint.info.description.clone(),
+            x,
+            y,
+            z : z.unwrap_or(closest.z),
+            has_elevation : z.is_some() || closest.has_elevation,
+            reference_index : best_index as u32,
+            total_distance : closest.total_distance,
+            offset : best_squared_distance.sqrt()
+        }
+    }).collect())
+}
+
+/*
+    Converts the local @points (relative to @track_origin in the space @projection) back to longitude and latitude (WGS84).
+    The time offsets of the points are kept, the elevation is taken from the local z of the points that have one.
+    Throws: CoordinateConversionError if any of the points can not be converted to WGS84
+*/
+pub fn local_to_spatial<T : Point>(points : &[T], track_origin : &TrackOrigin, projection : &str) -> Result<Vec<SpatialPoint>, ServiceError> {
+    let conv_config = CoordinatesConfig::new(projection.to_string(), WGS84.to_string());
+
+    let absolute_points : Vec<(f64, f64)> = points.iter()
+        .map(|point| (point.x() as f64 + track_origin.epsg_x, point.y() as f64 + track_origin.epsg_y))
+        .collect();
+
+    let lon_lat = convert_to_space(&absolute_points, &conv_config, |coords, _| coords)?;
+
+    Ok(lon_lat.into_iter().zip(points).map(|((lon, lat), point)| {
+        SpatialPoint {
+            lon,
+            lat,
+            elev : point.has_elevation().then(|| point.z() as f64),
+            delta_seconds : Some(point.delta_seconds()),
+            hdop : None,
+            accuracy : None
+        }
+    }).collect())
+}
 
 /*
-    Converts a vector of @spatial_points from one coordinate space to another given a projection matrix given by @config and a transform function (@transform_fn) for the new output format
-    Throws: CoordinateConversionError if any of the initial points can not be converted to the new space
+    Returns the projection of @config built by the current thread, built on first use and kept in the thread cache.
+    proj::Proj can not be shared between threads, every rayon worker builds its own
+    Throws: InvalidProjection if PROJ does not know the spaces of @config or can not convert between them
+*/
+fn cached_projection(config : &CoordinatesConfig) -> Result<Rc<proj::Proj>, ServiceError> {
+    PROJECTIONS.with(|projections| {
+        let mut projections = projections.borrow_mut();
+        let key = (config.get_origin_space().clone(), config.get_destination_space().clone());
+        if let Some(projection) = projections.get(&key) {
+            return Ok(projection.clone());
+        }
+
+        let projection = proj::Proj::new_known_crs(config.get_origin_space(), config.get_destination_space(), None)
+            .map_err(|err| ServiceError::invalid_projection(config.get_origin_space(), config.get_destination_space(), err.to_string().as_str()))?;
+        // Selected projections are centred on their track, old ones would pile up over batches
+        if projections.len() >= PROJECTION_CACHE_SIZE {
+            projections.clear();
+        }
+        let projection = Rc::new(projection);
+        projections.insert(key, projection.clone());
+        Ok(projection)
+    })
+}
+
+/*
+    Converts a vector of @spatial_points from one coordinate space to another given a projection matrix given by @config and a transform function (@transform_fn) for the new output format.
+    Points are converted in parallel chunks of CONVERSION_CHUNK_SIZE, the order of @spatial_points is kept
+    Throws:
+    InvalidProjection if the spaces of @config are not valid,
+    CoordinateConversionError if any of the initial points can not be converted to the new space
 */
 pub fn convert_to_space<P, R>(
     spatial_points: &[P],
     config: &CoordinatesConfig,
-    transform_fn: impl Fn((f64, f64), &P) -> R,
+    transform_fn: impl Fn((f64, f64), &P) -> R + Sync,
 ) -> Result<Vec<R>, ServiceError>
 where
-    P: Send + Copy + Into<(f64, f64)> + std::fmt::Debug,
+    P: Send + Sync + Copy + Into<(f64, f64)> + std::fmt::Debug,
     R: Send,
 {   
+    // Checked once here so an invalid space is a single error and not one per chunk
+    cached_projection(config)?;
 
-    let projection = proj::Proj::new_known_crs(
-                    config.get_origin_space(),
-                    config.get_destination_space(),
-                    None,
-                )
-                .expect("Failed to initialize projection");
-            
-    spatial_points
-        .iter()
-        .map(
-            |&point| {
-                let (x, y) = point.into();
-                let coord = Coord::from_xy(x, y);
-
-                projection.convert(coord)
-                    .map(|coords| transform_fn(coords, &point))
-                    .map_err(|e| {
-                        ServiceError::coordinate_conversion(
-                            config.get_origin_space().as_str(),
-                            config.get_destination_space().as_str(),
-                            format!("{:?}", point).as_str(),
-                            e.to_string().as_str(),
-                        )
-                    })
-            },
-        )
-        .collect::<Result<Vec<_>, _>>()
+    let chunks = spatial_points
+        .par_chunks(CONVERSION_CHUNK_SIZE)
+        .map(|chunk| {
+            let projection = cached_projection(config)?;
+            chunk.iter()
+                .map(|&point| convert_point(&projection, config, point, &transform_fn))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(chunks.into_iter().flatten().collect())
 }
 
+/*
+    Converts the first of the @spatial_points to the space of @config, transformed into the TrackOrigin by @transform_fn
+    Throws:
+    EmptyTrack if there are no @spatial_points,
+    InvalidProjection if the spaces of @config are not valid,
+    CoordinateConversionError if the point can not be converted to the new space
+*/
 pub fn get_track_origin<P>(
     spatial_points: &[P],
     config: &CoordinatesConfig,
@@ -112,26 +256,24 @@ where
     P: Copy + Into<(f64, f64)> + std::fmt::Debug,
 {
 
-    let point = *spatial_points.get(0).clone().ok_or(ServiceError::empty_track())?; 
-    let (x, y)= point.into();
-    
-    let coord = Coord::from_xy(x, y);
-
-    let projection = proj::Proj::new_known_crs(
-                    config.get_origin_space(),
-                    config.get_destination_space(),
-                    None,
-                )
-                .expect("Failed to initialize projection");
-
-    projection.convert(coord)
-                    .map(|coords| transform_fn(coords, &point))
-                    .map_err(|e| {
-                        ServiceError::coordinate_conversion(
-                            config.get_origin_space().as_str(),
-                            config.get_destination_space().as_str(),
-                            format!("{:?}", point).as_str(),
-                            e.to_string().as_str(),
-                        )
-                    })
-}
\ No newline at end of file
+    let point = *spatial_points.first().ok_or(ServiceError::empty_track())?; 
+    let projection = cached_projection(config)?;
+    convert_point(&projection, config, point, &transform_fn)
+}
+
+fn convert_point<P, R>(projection : &proj::Proj, config : &CoordinatesConfig, point : P, transform_fn : &impl Fn((f64, f64), &P) -> R) -> Result<R, ServiceError>
+where
+    P: Copy + Into<(f64, f64)> + std::fmt::Debug,
+{
+    let (x, y) = point.into();
+    projection.convert(Coord::from_xy(x, y))
+        .map(|coords| transform_fn(coords, &point))
+        .map_err(|e| {
+            ServiceError::coordinate_conversion(
+                config.get_origin_space().as_str(),
+                config.get_destination_space().as_str(),
+                format!("{:?}", point).as_str(),
+                e.to_string().as_str(),
+            )
+        })
+}
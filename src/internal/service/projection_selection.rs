@@ -0,0 +1,132 @@
+use std::collections::BTreeSet;
+
+use crate::{errors::service_errors::ServiceError, internal::{model::spatial::{points::SpatialPoint, projection::{Hemisphere, ProjectionChoice, ProjectionKind, ProjectionWarning, ProjectionWarningKind}}, service::geo_conversions::{EARTH_RADIUS, haversine_distance}}};
+
+// Latitudes covered by the UTM zones
+const UTM_SOUTH_LIMIT : f64 = -80.0;
+const UTM_NORTH_LIMIT : f64 = 84.0;
+// Meters, wider tracks are projected with an azimuthal equidistant projection
+const LARGE_EXTENT : f64 = 1_000_000.0;
+
+/*
+    Selects a metric projection for the WGS84 @spatial_points from their bounding box, @kind forces the kind of projection.
+    Without a @kind the UTM zone of the track is used when every point falls inside of it, a transverse Mercator centred on the track
+    when it spans zone boundaries and an azimuthal equidistant centred on the track for polar or very large tracks.
+    Tracks crossing the antimeridian are centred on it. Look at ProjectionWarningKind for the warnings
+    Throws: EmptyTrack if no point has valid coordinates
+*/
+pub fn select_projection(spatial_points : &[SpatialPoint], kind : Option<ProjectionKind>) -> Result<ProjectionChoice, ServiceError> {
+    let points : Vec<&SpatialPoint> = spatial_points.iter()
+        .filter(|point| point.lon.is_finite() && point.lat.is_finite() && point.lon.abs() <= 180.0 && point.lat.abs() <= 90.0)
+        .collect();
+    if points.is_empty() {
+        return Err(ServiceError::empty_track());
+    }
+
+    let (min_lat, max_lat) = points.iter().fold((f64::MAX, f64::MIN), |(min, max), point| (min.min(point.lat), max.max(point.lat)));
+    let (west, east) = longitude_range(&points);
+    let center_lat = (min_lat + max_lat) / 2.0;
+    let center_lon = normalize_longitude((west + east) / 2.0);
+    let hemisphere = if center_lat >= 0.0 { Hemisphere::North } else { Hemisphere::South };
+
+    let corner = |lon : f64, lat : f64| SpatialPoint { lon, lat, elev : None, delta_seconds : None, hdop : None, accuracy : None };
+    let width = haversine_distance(&corner(west, center_lat), &corner(east, center_lat));
+    let height = (max_lat - min_lat).to_radians() * EARTH_RADIUS;
+    let extent = width.max(height);
+
+    let polar = min_lat < UTM_SOUTH_LIMIT || max_lat > UTM_NORTH_LIMIT;
+    let zones : BTreeSet<u8> = points.iter().map(|point| utm_zone(point.lon, point.lat)).collect();
+    let kind = kind.unwrap_or(if polar || extent > LARGE_EXTENT {
+        ProjectionKind::AzimuthalEquidistant
+    } else if zones.len() > 1 {
+        ProjectionKind::TransverseMercator
+    } else {
+        ProjectionKind::Utm
+    });
+    let zone = utm_zone(center_lon, center_lat);
+
+    let mut warnings = Vec::new();
+    if zones.len() > 1 {
+        let zone_list = zones.iter().map(|zone| zone.to_string()).collect::<Vec<_>>().join(", ");
+        let message = match kind {
+            ProjectionKind::Utm => format!("Track spans UTM zones {}, it is projected in zone {} and distances far from its centre are stretched", zone_list, zone),
+            _ => format!("Track spans UTM zones {}, it is projected centred on itself instead", zone_list)
+        };
+        warnings.push(ProjectionWarning { kind : ProjectionWarningKind::ZoneSpan, message });
+    }
+    if min_lat < 0.0 && max_lat > 0.0 {
+        let message = match (kind, hemisphere) {
+            (ProjectionKind::Utm, Hemisphere::North) => "Track crosses the equator, its southern points have negative northings".to_string(),
+            (ProjectionKind::Utm, Hemisphere::South) => "Track crosses the equator, its northern points have northings above 10 000 km".to_string(),
+            _ => "Track crosses the equator".to_string()
+        };
+        warnings.push(ProjectionWarning { kind : ProjectionWarningKind::HemisphereSpan, message });
+    }
+    if extent > LARGE_EXTENT {
+        warnings.push(ProjectionWarning {
+            kind : ProjectionWarningKind::LargeExtent,
+            message : format!("Track spans {:.0} km, distances far from its centre are distorted", extent / 1000.0)
+        });
+    }
+    if polar {
+        warnings.push(ProjectionWarning {
+            kind : ProjectionWarningKind::Polar,
+            message : format!("Track goes beyond the UTM latitudes ({}° to {}°)", UTM_SOUTH_LIMIT, UTM_NORTH_LIMIT)
+        });
+    }
+
+    let crs = match kind {
+        ProjectionKind::Utm => format!("EPSG:{}", if hemisphere == Hemisphere::North { 32600 } else { 32700 } + zone as u32),
+        ProjectionKind::TransverseMercator => format!(
+            "+proj=tmerc +lat_0={:.6} +lon_0={:.6} +k=1 +x_0=0 +y_0=0 +datum=WGS84 +units=m +no_defs +type=crs", center_lat, center_lon
+        ),
+        ProjectionKind::AzimuthalEquidistant => format!(
+            "+proj=aeqd +lat_0={:.6} +lon_0={:.6} +x_0=0 +y_0=0 +datum=WGS84 +units=m +no_defs +type=crs", center_lat, center_lon
+        )
+    };
+
+    Ok(ProjectionChoice {
+        crs,
+        kind,
+        zone : Some(zone),
+        hemisphere,
+        center_lon,
+        center_lat,
+        warnings
+    })
+}
+
+/*
+    Returns the UTM zone (1...60) of the WGS84 point (@lon, @lat), with the exceptions of south west Norway and Svalbard
+*/
+pub fn utm_zone(lon : f64, lat : f64) -> u8 {
+    if (56.0..64.0).contains(&lat) && (3.0..12.0).contains(&lon) {
+        return 32;
+    }
+    if (72.0..84.0).contains(&lat) && (0.0..42.0).contains(&lon) {
+        return match lon {
+            lon if lon < 9.0 => 31,
+            lon if lon < 21.0 => 33,
+            lon if lon < 33.0 => 35,
+            _ => 37
+        };
+    }
+    (((normalize_longitude(lon) + 180.0) / 6.0).floor() as i32 + 1).clamp(1, 60) as u8
+}
+
+/*
+    Returns the (west, east) longitudes of @points, east is past 180° for tracks crossing the antimeridian
+*/
+fn longitude_range(points : &[&SpatialPoint]) -> (f64, f64) {
+    let (west, east) = points.iter().fold((f64::MAX, f64::MIN), |(min, max), point| (min.min(point.lon), max.max(point.lon)));
+    // The same longitudes on [0, 360), narrower for tracks crossing the antimeridian
+    let (shifted_west, shifted_east) = points.iter()
+        .map(|point| if point.lon < 0.0 { point.lon + 360.0 } else { point.lon })
+        .fold((f64::MAX, f64::MIN), |(min, max), lon| (min.min(lon), max.max(lon)));
+    if shifted_east - shifted_west < east - west { (shifted_west, shifted_east) } else { (west, east) }
+}
+
+// Returns @lon in [-180, 180)
+fn normalize_longitude(lon : f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
@@ -1,8 +1,8 @@
 use std::ops::{Add, Mul, Sub};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
 use wide::f32x8;
 
-use crate::internal::model::{config::snapping::SnappingConfig, spatial::{grid::Grid, points::{MatchPoint, Point}}, track::{reference::ReferenceTrack, riders::{MatchedTrack, RiderTrack}}};
+use crate::{errors::service_errors::ServiceError, internal::{model::{config::{gaps::GapConfig, outlier::OutlierConfig, snapping::SnappingConfig}, spatial::{grid::Grid, points::{MatchPoint, Point}}, track::{common::{TrackSegment, is_segment_break}, reference::ReferenceTrack, riders::{MatchedTrack, RiderTrack}}}, service::{gap_detection, track_filter}}};
 
 /*
     Snaps given point (@px, @py) to the closest reference point in @refs.
@@ -74,10 +74,15 @@ pub fn min_distance<T : Point>(
 
 /*
     Tries to snap a @rider track to onto another @refs track using a @grid build on top of @refs.
+    The @rider_segments describe the recording breaks of @rider, no direction is derived across a break.
+    The @gap_ends are the sorted indices of the first point after every recording gap of @rider (look at gap_detection::gap_ends),
+    the continuity clamp does not hold the point after a gap or a recording break back.
     Look at SnappingConfig.
 */
 pub fn snap<T: Point, U : Point>(
     rider : &[T],
+    rider_segments : &[TrackSegment],
+    gap_ends : &[usize],
     refs : &[U],
     grid : &Grid,
     out : &mut Vec<MatchPoint>,
@@ -95,6 +100,8 @@ pub fn snap<T: Point, U : Point>(
         let mut best_squared_distance = f32::MAX;
         let mut best_index = 0u32;
         let mut direction_similarity = 0.0;
+        let segment_start = is_segment_break(rider_segments, ridx);
+        let gap_end = gap_ends.binary_search(&ridx).is_ok();
 
         for &neighbor in &neighbors {
             let grid_cell = &grid.cells[neighbor];
@@ -113,7 +120,7 @@ pub fn snap<T: Point, U : Point>(
         }
 
         let cc = config.get_continuity_clamp();
-        if let Some(prev) = last_reference {
+        if let (Some(prev), false) = (last_reference, gap_end || segment_start) {
             if best_index + cc < prev {
                 best_index = prev;
             }
@@ -121,8 +128,10 @@ pub fn snap<T: Point, U : Point>(
 
         last_reference = Some(best_index);
 
-        // Compute directions 
-        if ridx >= 1 && best_index >= 1 {
+        // Compute directions, the first point after a recording break has no direction (similarity 0)
+        if segment_start {
+            direction_similarity = 0.0;
+        } else if ridx >= 1 && best_index >= 1 {
             ref_direction_vec.0  = refs[best_index as usize].x() - refs[best_index as usize - 1].x();
             ref_direction_vec.1 = refs[best_index as usize].y() - refs[best_index as usize - 1].y();
             rider_direction_vec.0  = rider[ridx].x() - rider[ridx - 1].x();
@@ -145,7 +154,10 @@ pub fn snap<T: Point, U : Point>(
                 direction_similarity : direction_similarity,
                 lateral: best_squared_distance.sqrt(), 
                 distance_z: rider_point.z() - r.z(),
-                count_to_error : false
+                has_elevation : rider_point.has_elevation() && r.has_elevation(),
+                count_to_error : false,
+                segment_start : segment_start,
+                gap_end : gap_end
             }
         );
     }
@@ -155,25 +167,41 @@ pub fn snap<T: Point, U : Point>(
 
 /*
     Parralel snapping of multiple @riders tracks to a single track @refs track with a build @grid on the @refs.
+    With an @outlier_config the outliers of every rider are filtered in place first (look at track_filter::filter_for_sport)
+    so the matched tracks stay aligned with the @riders, without one outliers are snapped like any other point.
+    Jitter makes the travel direction of the @riders noisy, smooth them first if needed (look at track_smoothing::smooth_all).
+    The recording gaps of every rider are detected with @gap_config and stored in its MatchedTrack, look at gap_detection::detect_gaps.
     Look at SnappingConfig.
+    Throws: InvalidData if a gap can not be matched on @refs
 */
 pub fn snap_all(
-    riders : &[RiderTrack],
+    riders : &mut [RiderTrack],
     refs : &ReferenceTrack,
     grid : &Grid,
-    config : &SnappingConfig
-) -> Vec<MatchedTrack> {
-    riders.par_iter()
+    config : &SnappingConfig,
+    gap_config : &GapConfig,
+    outlier_config : Option<&OutlierConfig>
+) -> Result<Vec<MatchedTrack>, ServiceError> {
+    riders.par_iter_mut()
     .map(|rider| {
+        if let Some(outlier_config) = outlier_config {
+            track_filter::filter_for_sport(rider, outlier_config);
+        }
+        let rider = &*rider;
         let mut out = Vec::with_capacity(rider.track.len());
-        snap(&rider.track, &refs.track, grid, &mut out, config);
-        MatchedTrack { 
+        let gap_ends = gap_detection::gap_ends(rider, gap_config);
+        snap(&rider.track, &rider.segments, &gap_ends, &refs.track, grid, &mut out, config);
+        let mut matched = MatchedTrack { 
             bound_uuid: rider.rider_uuid.clone(),
             projection : refs.projection.clone(),
             variant: rider.variant,
             track_origin : rider.track_origin,
             start_time: rider.start_time,
-            track: out
-        }
+            track: out,
+            sensors: rider.sensors.clone(),
+            gaps: Vec::new()
+        };
+        gap_detection::detect_gaps(rider, &mut matched, refs, gap_config)?;
+        Ok(matched)
     }).collect()
 }
\ No newline at end of file
@@ -0,0 +1,190 @@
+use crate::{errors::service_errors::ServiceError, internal::{model::{analysis::{MAX_BIN_COUNT, MIN_BIN_LENGTH}, config::speed::SpeedConfig, movement::{SpeedBin, SpeedPercentile, SpeedProfile, SpeedSample, SpeedStats}, spatial::points::RiderPoint, track::{common::TrackSegment, reference::ReferenceTrack, riders::{MatchedTrack, RiderTrack}}}, service::{geo_conversions::planar_distance, stop_detection::detect_stops}}};
+
+/*
+    Speed, pace and acceleration of every point of @rider_track and their statistics, look at SpeedConfig.
+    Speeds never span the break between two segments. The profile has no bins, look at matched_speed_profile
+*/
+pub fn speed_profile(rider_track : &RiderTrack, config : &SpeedConfig) -> SpeedProfile {
+    let samples = speed_samples(rider_track, None, config);
+    SpeedProfile {
+        stats : speed_stats(rider_track, &samples, config),
+        samples,
+        bins : Vec::new()
+    }
+}
+
+/*
+    Speed profile of @rider_track (look at speed_profile) indexed by the distance along @reference_track of its matches in @matched_track,
+    with the time spent and the speed on every bin of bin_length meters of the reference
+    Throws: InvalidData if @matched_track was not snapped from @rider_track (inverse snapping) or onto @reference_track
+*/
+pub fn matched_speed_profile(rider_track : &RiderTrack, matched_track : &MatchedTrack, reference_track : &ReferenceTrack, config : &SpeedConfig) -> Result<SpeedProfile, ServiceError> {
+    if rider_track.track.len() != matched_track.track.len() {
+        return Err(ServiceError::invalid_data("matched track does not hold a match for every point of the rider track"));
+    }
+    let reference_distances = matched_track.track.iter()
+        .map(|matched| reference_track.track.get(matched.reference_index as usize).map(|point| point.total_distance))
+        .collect::<Option<Vec<f32>>>()
+        .ok_or_else(|| ServiceError::invalid_data("matched track points outside of the reference track"))?;
+
+    let samples = speed_samples(rider_track, Some(&reference_distances), config);
+    let length = reference_track.track.iter().map(|point| point.total_distance).fold(0.0, f32::max);
+    Ok(SpeedProfile {
+        stats : speed_stats(rider_track, &samples, config),
+        bins : speed_bins(rider_track, &samples, length, config),
+        samples
+    })
+}
+
+fn speed_samples(rider_track : &RiderTrack, reference_distances : Option<&[f32]>, config : &SpeedConfig) -> Vec<SpeedSample> {
+    let points = &rider_track.track;
+    let mut speeds = vec![None; points.len()];
+    let mut smoothed = vec![None; points.len()];
+    let mut accelerations = vec![None; points.len()];
+
+    for segment in TrackSegment::or_single(&rider_track.segments, rider_track.track.len()).iter() {
+        let (start, end) = (segment.start, segment.end());
+        // Central differences, one sided on the ends of the segment
+        let around = |index : usize| (index.max(start + 1) - 1, (index + 1).min(end - 1));
+        for index in start..end {
+            let (previous, next) = around(index);
+            let elapsed = points[next].delta_seconds - points[previous].delta_seconds;
+            if elapsed > 0.0 {
+                let moved = planar_distance(&points[previous], &points[index]) + planar_distance(&points[index], &points[next]);
+                speeds[index] = Some(moved / elapsed as f32);
+            }
+        }
+
+        smooth_segment(points, &speeds, &mut smoothed, start, end, config.get_smoothing_window());
+
+        for index in start..end {
+            let (previous, next) = around(index);
+            let elapsed = points[next].delta_seconds - points[previous].delta_seconds;
+            if let (Some(before), Some(after), true) = (smoothed[previous], smoothed[next], elapsed > 0.0) {
+                accelerations[index] = Some((after - before) / elapsed as f32);
+            }
+        }
+    }
+
+    points.iter()
+        .enumerate()
+        .map(|(index, point)| SpeedSample {
+            index,
+            delta_seconds : point.delta_seconds,
+            reference_distance : reference_distances.map(|distances| distances[index]),
+            speed : speeds[index],
+            smoothed_speed : smoothed[index],
+            pace : smoothed[index].and_then(|speed| pace(speed, config)),
+            acceleration : accelerations[index]
+        })
+        .collect()
+}
+
+/*
+    Mean of the @speeds of the points of [@start, @end) within half of @window seconds of every point, written in @smoothed
+*/
+fn smooth_segment(points : &[RiderPoint], speeds : &[Option<f32>], smoothed : &mut [Option<f32>], start : usize, end : usize, window : f64) {
+    let half_window = window.max(0.0) / 2.0;
+    let (mut sum, mut count) = (0.0, 0usize);
+    let (mut low, mut high) = (start, start);
+    for index in start..end {
+        let time = points[index].delta_seconds;
+        while high < end && (high <= index || points[high].delta_seconds <= time + half_window) {
+            if let Some(speed) = speeds[high] {
+                sum += speed;
+                count += 1;
+            }
+            high += 1;
+        }
+        while low < index && points[low].delta_seconds < time - half_window {
+            if let Some(speed) = speeds[low] {
+                sum -= speed;
+                count -= 1;
+            }
+            low += 1;
+        }
+        smoothed[index] = (count > 0).then(|| (sum / count as f32).max(0.0));
+    }
+}
+
+// Seconds per kilometer, None while stopped
+fn pace(speed : f32, config : &SpeedConfig) -> Option<f32> {
+    (speed > 0.0 && speed >= config.get_min_moving_speed()).then(|| 1000.0 / speed)
+}
+
+/*
+    Statistics of the @samples of @rider_track, the averages are the ones of stop_detection::detect_stops with the StopConfig of @config
+*/
+fn speed_stats(rider_track : &RiderTrack, samples : &[SpeedSample], config : &SpeedConfig) -> SpeedStats {
+    let movement = detect_stops(rider_track, config.get_stops());
+    let average_moving_speed = movement.average_moving_speed.map(|speed| speed as f32);
+
+    let mut moving_speeds : Vec<f32> = samples.iter()
+        .filter_map(|sample| sample.smoothed_speed)
+        .filter(|speed| *speed >= config.get_min_moving_speed())
+        .collect();
+    moving_speeds.sort_by(f32::total_cmp);
+    let percentiles = if moving_speeds.is_empty() { Vec::new() } else {
+        config.get_percentiles().iter()
+            .map(|&percentile| SpeedPercentile { percentile, speed : percentile_of(&moving_speeds, percentile) })
+            .collect()
+    };
+
+    let accelerations = samples.iter().filter_map(|sample| sample.acceleration);
+    SpeedStats {
+        max_speed : samples.iter().filter_map(|sample| sample.smoothed_speed).max_by(f32::total_cmp),
+        average_speed : movement.average_speed.map(|speed| speed as f32),
+        average_moving_speed,
+        average_moving_pace : average_moving_speed.and_then(|speed| pace(speed, config)),
+        max_acceleration : accelerations.clone().max_by(f32::total_cmp),
+        max_deceleration : accelerations.min_by(f32::total_cmp),
+        percentiles
+    }
+}
+
+/*
+    Linear interpolation between the closest ranks of the sorted and non empty @speeds
+*/
+fn percentile_of(speeds : &[f32], percentile : f32) -> f32 {
+    let rank = (percentile.clamp(0.0, 100.0) / 100.0) * (speeds.len() - 1) as f32;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    speeds[below] + (speeds[above] - speeds[below]) * (rank - below as f32)
+}
+
+/*
+    Time spent and time weighted smoothed speed on every bin of the reference, a move belongs to the bin its first point is matched in.
+    Bins are at least MIN_BIN_LENGTH long and long references get longer bins, look at MAX_BIN_COUNT
+*/
+fn speed_bins(rider_track : &RiderTrack, samples : &[SpeedSample], length : f32, config : &SpeedConfig) -> Vec<SpeedBin> {
+    let bin_length = if config.get_bin_length() > 0.0 { config.get_bin_length().max(MIN_BIN_LENGTH) } else { length.max(1.0) };
+    let bin_length = bin_length.max(length / MAX_BIN_COUNT as f32);
+    let bin_count = ((length / bin_length).ceil() as usize).clamp(1, MAX_BIN_COUNT);
+    let mut totals = vec![(0.0f64, 0.0f64); bin_count];
+
+    for segment in TrackSegment::or_single(&rider_track.segments, rider_track.track.len()).iter() {
+        for index in segment.start + 1..segment.end() {
+            let (previous, current) = (&samples[index - 1], &samples[index]);
+            let elapsed = current.delta_seconds - previous.delta_seconds;
+            let Some(distance) = previous.reference_distance.filter(|_| elapsed > 0.0) else {
+                continue;
+            };
+            let bin = &mut totals[((distance.max(0.0) / bin_length) as usize).min(bin_count - 1)];
+            bin.0 += elapsed;
+            bin.1 += previous.smoothed_speed.unwrap_or(0.0) as f64 * elapsed;
+        }
+    }
+
+    totals.iter()
+        .enumerate()
+        .map(|(index, (elapsed_time, weighted_speed))| {
+            let speed = (*elapsed_time > 0.0).then(|| (weighted_speed / elapsed_time) as f32);
+            SpeedBin {
+                from_distance : index as f32 * bin_length,
+                to_distance : ((index + 1) as f32 * bin_length).min(length.max(bin_length)),
+                elapsed_time : *elapsed_time,
+                speed,
+                pace : speed.and_then(|speed| pace(speed, config))
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,101 @@
+use crate::internal::{model::{config::stops::StopConfig, movement::{MovementSummary, Stop, StopKind}, spatial::points::RiderPoint, track::{common::TrackSegment, riders::RiderTrack}}, service::geo_conversions::planar_distance};
+
+/*
+    Finds where the rider of @rider_track stopped and splits the elapsed time into moving and stopped time.
+    A dwell starts at a point and lasts as long as the following points stay within the dwell radius of it, it is a stop if it lasts at least min_duration.
+    Every break between two segments is a pause. Moves slower than min_moving_speed count as stopped time even outside of stops.
+    Tracks without time have no stops and no moving time. Look at StopConfig
+*/
+pub fn detect_stops(rider_track : &RiderTrack, config : &StopConfig) -> MovementSummary {
+    let points = &rider_track.track;
+    let segments = TrackSegment::or_single(&rider_track.segments, points.len());
+
+    // in_dwell[k] is true when the move from point k - 1 to point k happens inside a dwell
+    let mut in_dwell = vec![false; points.len()];
+    let mut stops = Vec::new();
+    for segment in segments.iter() {
+        detect_dwells(points, segment, config, &mut stops, &mut in_dwell);
+    }
+    for (before, after) in segments.iter().zip(segments.iter().skip(1)) {
+        let (last, first) = (&points[before.end() - 1], &points[after.start]);
+        if first.delta_seconds > last.delta_seconds {
+            stops.push(Stop {
+                kind : StopKind::Pause,
+                x : last.x,
+                y : last.y,
+                start_index : before.end() - 1,
+                end_index : after.start,
+                start_seconds : last.delta_seconds,
+                end_seconds : first.delta_seconds,
+                duration : first.delta_seconds - last.delta_seconds
+            });
+        }
+    }
+    stops.sort_by_key(|stop| stop.start_index);
+
+    let mut distance = 0.0;
+    let mut moving_time = 0.0;
+    for segment in segments.iter() {
+        for index in segment.start + 1..segment.end() {
+            let (previous, point) = (&points[index - 1], &points[index]);
+            let moved = planar_distance(previous, point) as f64;
+            let elapsed = point.delta_seconds - previous.delta_seconds;
+            distance += moved;
+            if elapsed > 0.0 && !in_dwell[index] && moved / elapsed >= config.get_min_moving_speed() as f64 {
+                moving_time += elapsed;
+            }
+        }
+    }
+
+    let elapsed_time = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (last.delta_seconds - first.delta_seconds).max(0.0),
+        _ => 0.0
+    };
+    let average = |time : f64| (time > 0.0).then(|| distance / time);
+
+    MovementSummary {
+        stops,
+        elapsed_time,
+        moving_time,
+        stopped_time : (elapsed_time - moving_time).max(0.0),
+        distance,
+        average_speed : average(elapsed_time),
+        average_moving_speed : average(moving_time)
+    }
+}
+
+/*
+    Pushes the dwells of @segment to @stops and marks the moves inside them in @in_dwell
+*/
+fn detect_dwells(points : &[RiderPoint], segment : &TrackSegment, config : &StopConfig, stops : &mut Vec<Stop>, in_dwell : &mut [bool]) {
+    let end = segment.end();
+    let mut index = segment.start;
+    while index < end {
+        let anchor = &points[index];
+        let mut last = index;
+        while last + 1 < end && planar_distance(anchor, &points[last + 1]) <= config.get_dwell_radius() {
+            last += 1;
+        }
+
+        let duration = points[last].delta_seconds - anchor.delta_seconds;
+        if last == index || duration <= 0.0 || duration < config.get_min_duration() {
+            index += 1;
+            continue;
+        }
+
+        let count = (last - index + 1) as f32;
+        let (sum_x, sum_y) = points[index..=last].iter().fold((0.0, 0.0), |(sum_x, sum_y), point| (sum_x + point.x, sum_y + point.y));
+        stops.push(Stop {
+            kind : StopKind::Dwell,
+            x : sum_x / count,
+            y : sum_y / count,
+            start_index : index,
+            end_index : last,
+            start_seconds : anchor.delta_seconds,
+            end_seconds : points[last].delta_seconds,
+            duration
+        });
+        in_dwell[index + 1..=last].fill(true);
+        index = last + 1;
+    }
+}
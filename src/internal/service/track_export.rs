@@ -0,0 +1,406 @@
+use std::io::Write;
+
+use serde_json::{Map, Value, json};
+
+use crate::{errors::{io_errors::IOError, service_errors::ServiceError}, internal::{io::{geojson_writer::{self, GeoJsonLayout, GeoJsonLine, GeoJsonPoint, insert_sensor_properties}, gpx_writer::GpxWriter, kml_writer::{self, KmlLine}, time_parser}, model::{analysis::Severity, spatial::points::{RefPoint, SpatialPoint}, track::{common::{SpatialTrack, TrackInfo, TrackMetadata, TrackOrigin, TrackSegment, Waypoint}, reference::ReferenceTrack, riders::{MatchedTrack, RiderTrack}}}, service::geo_conversions}};
+
+/*
+    Writes the @matched_track of @rider_track as a KML document to @writer.
+    Every segment of the rider track is drawn with the neutral track style, every run of counted errors (MatchPoint.count_to_error)
+    of the same severity is drawn on top of it with the style of its @severity, a run never spans a recording break or gap.
+    @severity is the output of one of the analysis classifiers (classify_lateral, classify_directional) for @matched_track.
+    Throws:
+    InvalidData if the matched track was not snapped from the rider track (inverse snapping) or @severity does not belong to it,
+    CoordinateConversionError if any point can not be converted back to WGS84,
+    IOError if the @writer fails
+*/
+pub fn matched_to_kml<W : Write>(writer : &mut W, rider_track : &RiderTrack, matched_track : &MatchedTrack, severity : &[Severity]) -> Result<(), ServiceError> {
+    if rider_track.track.len() != matched_track.track.len() {
+        return Err(ServiceError::invalid_data("matched track does not hold a match for every point of the rider track"));
+    }
+    if severity.len() != matched_track.track.len() {
+        return Err(ServiceError::invalid_data("severity does not hold a value for every point of the matched track"));
+    }
+
+    let track_name = format!("{}_{}", rider_track.rider_uuid, rider_track.variant);
+    let points = geo_conversions::local_to_spatial(&rider_track.track, &rider_track.track_origin, &rider_track.projection)?;
+
+    let segments = TrackSegment::or_single(&rider_track.segments, points.len());
+    let mut lines : Vec<KmlLine> = segments.iter().filter(|segment| segment.end() <= points.len()).map(|segment| {
+        KmlLine {
+            info : TrackInfo { name: Some(track_name.clone()), ..TrackInfo::default() },
+            severity : None,
+            points : points[segment.start..segment.end()].to_vec()
+        }
+    }).collect();
+
+    let matches = &matched_track.track;
+    let mut run_start = 0usize;
+    while run_start < matches.len() {
+        let run_severity = severity[run_start];
+        if !matches[run_start].count_to_error || run_severity == Severity::Ok {
+            run_start += 1;
+            continue;
+        }
+
+        let mut run_end = run_start + 1;
+        while run_end < matches.len()
+            && matches[run_end].count_to_error
+            && severity[run_end] == run_severity
+            && !matches[run_end].segment_start
+            && !matches[run_end].gap_end {
+            run_end += 1;
+        }
+
+        // The next point closes the drawn line unless it lies after a recording break or gap
+        let line_end = if run_end < matches.len() && !matches[run_end].segment_start && !matches[run_end].gap_end { run_end + 1 } else { run_end };
+        let max_lateral = matches[run_start..run_end].iter().fold(0.0f32, |max, matched| max.max(matched.lateral));
+
+        lines.push(KmlLine {
+            info : TrackInfo {
+                name : Some(format!("{:?} deviation", run_severity)),
+                description : Some(format!(
+                    "Max lateral deviation : {:.1} m, from {:.0} s to {:.0} s",
+                    max_lateral,
+                    matches[run_start].delta_seconds,
+                    matches[run_end - 1].delta_seconds
+                )),
+                kind : None
+            },
+            severity : Some(run_severity),
+            points : points[run_start..line_end].to_vec()
+        });
+
+        run_start = run_end;
+    }
+
+    let metadata = TrackMetadata {
+        name : Some(track_name.clone()),
+        time : Some(matched_track.start_time),
+        ..TrackMetadata::default()
+    };
+
+    kml_writer::write_kml(writer, &track_name, &metadata, &lines, &[])
+        .map_err(|err| ServiceError::io_error(err))
+}
+
+/*
+    Writes the loaded @track as a GeoJSON FeatureCollection in the given @layout to @writer.
+    Every recorded track becomes a line made of its segments, routes are lines with the role "route" and waypoints are points.
+    Throws: IOError if the @writer fails
+*/
+pub fn spatial_to_geojson<W : Write>(writer : &mut W, track : &SpatialTrack, layout : GeoJsonLayout) -> Result<(), ServiceError> {
+    let segments = TrackSegment::or_single(&track.segments, track.track.len());
+    let track_count = segments.iter().map(|segment| segment.track_index + 1).max().unwrap_or(0).max(track.tracks.len());
+
+    let mut lines : Vec<GeoJsonLine> = (0..track_count).map(|track_index| {
+        let info = track.tracks.get(track_index).cloned().unwrap_or_default();
+        GeoJsonLine { properties: info_properties(&info, "track"), segments: Vec::new() }
+    }).collect();
+
+    for segment in segments.iter() {
+        let points = (segment.start..segment.end()).map(|index| {
+            let point = &track.track[index];
+            let mut properties = Map::new();
+            if let Some(time) = point.delta_seconds.and_then(|delta_seconds| time_parser::offset_time(track.start_time, delta_seconds)) {
+                properties.insert("time".to_string(), json!(time.to_rfc3339()));
+            }
+            if let Some(hdop) = point.hdop {
+                properties.insert("hdop".to_string(), json!(hdop));
+            }
+            if let Some(accuracy) = point.accuracy {
+                properties.insert("accuracy".to_string(), json!(accuracy));
+            }
+            if let Some(sensor) = track.sensors.get(index) {
+                insert_sensor_properties(&mut properties, sensor);
+            }
+            GeoJsonPoint { point: *point, properties }
+        }).collect();
+        lines[segment.track_index].segments.push(points);
+    }
+
+    lines.retain(|line| !line.segments.is_empty());
+    lines.extend(track.routes.iter().filter(|route| !route.points.is_empty()).map(|route| GeoJsonLine {
+        properties : info_properties(&route.info, "route"),
+        segments : vec![route.points.iter().map(|point| GeoJsonPoint { point: *point, properties: Map::new() }).collect()]
+    }));
+
+    let points = track.waypoints.iter().map(|waypoint| GeoJsonPoint {
+        point : waypoint.point,
+        properties : info_properties(&waypoint.info, "waypoint")
+    }).collect();
+
+    let mut collection_properties = Map::new();
+    for (key, value) in [
+        ("name", &track.metadata.name),
+        ("description", &track.metadata.description),
+        ("author", &track.metadata.author),
+        ("creator", &track.metadata.creator)
+    ] {
+        if let Some(value) = value {
+            collection_properties.insert(key.to_string(), json!(value));
+        }
+    }
+    if let Some(time) = track.metadata.time {
+        collection_properties.insert("time".to_string(), json!(time.to_rfc3339()));
+    }
+    collection_properties.insert("start_time".to_string(), json!(track.start_time.to_rfc3339()));
+
+    let source = track.metadata.name.clone().unwrap_or("track".to_string());
+    geojson_writer::write_geojson(writer, &source, layout, collection_properties, lines, points)
+        .map_err(|err| ServiceError::io_error(err))
+}
+
+/*
+    Writes the @reference_track converted back to WGS84 as a GeoJSON FeatureCollection in the given @layout to @writer.
+    Every point carries its distance along the reference (look at along_distances), checkpoints are written as points with the role "checkpoint".
+    Throws:
+    CoordinateConversionError if any point can not be converted back to WGS84,
+    IOError if the @writer fails
+*/
+pub fn reference_to_geojson<W : Write>(writer : &mut W, reference_track : &ReferenceTrack, layout : GeoJsonLayout) -> Result<(), ServiceError> {
+    let points = geo_conversions::local_to_spatial(&reference_track.track, &reference_track.origin, &reference_track.projection)?;
+
+    let segments = TrackSegment::or_single(&reference_track.segments, points.len());
+    let distances = along_distances(&points, &segments);
+    let line = GeoJsonLine {
+        properties : info_properties(&TrackInfo { name: Some(reference_track.class.clone()), ..TrackInfo::default() }, "reference"),
+        segments : segments.iter().map(|segment| {
+            (segment.start..segment.end()).map(|index| GeoJsonPoint {
+                point : SpatialPoint { delta_seconds: None, ..points[index] },
+                properties : Map::from_iter([("distance".to_string(), json!(distances[index]))])
+            }).collect()
+        }).collect()
+    };
+
+    let checkpoint_points : Vec<RefPoint> = reference_track.checkpoints.iter()
+        .map(|checkpoint| RefPoint { x: checkpoint.x, y: checkpoint.y, z: checkpoint.z, has_elevation: checkpoint.has_elevation, total_distance: checkpoint.total_distance })
+        .collect();
+    let checkpoint_positions = geo_conversions::local_to_spatial(&checkpoint_points, &reference_track.origin, &reference_track.projection)?;
+
+    let checkpoints = reference_track.checkpoints.iter().zip(checkpoint_positions).map(|(checkpoint, position)| {
+        let info = TrackInfo { name: Some(checkpoint.name.clone()), description: checkpoint.description.clone(), kind: None };
+        let mut properties = info_properties(&info, "checkpoint");
+        properties.insert("distance".to_string(), json!(checkpoint_distance(&distances, checkpoint.reference_index)));
+        properties.insert("offset".to_string(), json!(checkpoint.offset));
+        properties.insert("reference_index".to_string(), json!(checkpoint.reference_index));
+        GeoJsonPoint { point: SpatialPoint { delta_seconds: None, ..position }, properties }
+    }).collect();
+
+    let collection_properties = Map::from_iter([
+        ("class".to_string(), json!(reference_track.class)),
+        ("projection".to_string(), json!(reference_track.projection)),
+        ("origin".to_string(), origin_value(&reference_track.origin))
+    ]);
+
+    geojson_writer::write_geojson(writer, &reference_track.class, layout, collection_properties, vec![line], checkpoints)
+        .map_err(|err| ServiceError::io_error(err))
+}
+
+/*
+    Writes the @matched_track as a GeoJSON FeatureCollection in the given @layout to @writer.
+    Matched points are drawn at the reference point they were snapped to, @reference_track must be the reference used by snap_rider_track.
+    Every match value is written as a per-point property, so the document can be read back with geojson_loader::read_matched_geojson.
+    Throws:
+    InvalidData if the matched track does not belong to @reference_track,
+    CoordinateConversionError if any point can not be converted back to WGS84,
+    IOError if the @writer fails
+*/
+pub fn matched_to_geojson<W : Write>(writer : &mut W, matched_track : &MatchedTrack, reference_track : &ReferenceTrack, layout : GeoJsonLayout) -> Result<(), ServiceError> {
+    if !matched_track.projection.eq_ignore_ascii_case(&reference_track.projection) || matched_track.track_origin != reference_track.origin {
+        return Err(ServiceError::invalid_data("matched track is not in the space of the reference track"));
+    }
+
+    let snapped_points = matched_track.track.iter().map(|matched| {
+        reference_track.track.get(matched.reference_index as usize).copied()
+            .ok_or_else(|| ServiceError::invalid_data("matched track points outside of the reference track"))
+    }).collect::<Result<Vec<RefPoint>, ServiceError>>()?;
+    let points = geo_conversions::local_to_spatial(&snapped_points, &reference_track.origin, &reference_track.projection)?;
+
+    let track_name = format!("{}_{}", matched_track.bound_uuid, matched_track.variant);
+    let mut line = GeoJsonLine {
+        properties : info_properties(&TrackInfo { name: Some(track_name.clone()), ..TrackInfo::default() }, "matched"),
+        segments : Vec::new()
+    };
+
+    for (index, (matched, point)) in matched_track.track.iter().zip(points).enumerate() {
+        if matched.segment_start || line.segments.is_empty() {
+            line.segments.push(Vec::new());
+        }
+
+        let mut properties = Map::from_iter([
+            ("delta_seconds".to_string(), json!(matched.delta_seconds)),
+            ("reference_index".to_string(), json!(matched.reference_index)),
+            ("lateral".to_string(), json!(matched.lateral)),
+            ("direction_similarity".to_string(), json!(matched.direction_similarity)),
+            ("distance_z".to_string(), json!(matched.distance_z)),
+            ("has_elevation".to_string(), json!(matched.has_elevation)),
+            ("count_to_error".to_string(), json!(matched.count_to_error)),
+            ("segment_start".to_string(), json!(matched.segment_start)),
+            ("gap_end".to_string(), json!(matched.gap_end))
+        ]);
+        if let Some(sensor) = matched_track.sensors.get(index) {
+            insert_sensor_properties(&mut properties, sensor);
+        }
+        if let Some(segment) = line.segments.last_mut() {
+            segment.push(GeoJsonPoint { point: SpatialPoint { delta_seconds: None, ..point }, properties });
+        }
+    }
+
+    let collection_properties = Map::from_iter([
+        ("bound_uuid".to_string(), json!(matched_track.bound_uuid.to_string())),
+        ("variant".to_string(), json!(matched_track.variant)),
+        ("projection".to_string(), json!(matched_track.projection)),
+        ("start_time".to_string(), json!(matched_track.start_time.to_rfc3339())),
+        ("origin".to_string(), origin_value(&matched_track.track_origin))
+    ]);
+
+    geojson_writer::write_geojson(writer, &track_name, layout, collection_properties, vec![line], Vec::new())
+        .map_err(|err| ServiceError::io_error(err))
+}
+
+/*
+    Writes the loaded @track as GPX to @writer: waypoints, routes and every recorded track with its segments
+    Throws: IOError if the @writer fails
+*/
+pub fn spatial_to_gpx<W : Write>(writer : W, track : &SpatialTrack) -> Result<W, ServiceError> {
+    let source = track.metadata.name.clone().unwrap_or("track".to_string());
+    let segments = TrackSegment::or_single(&track.segments, track.track.len());
+
+    let write = || -> Result<W, IOError> {
+        let mut gpx = GpxWriter::start(writer, &source, &track.metadata)?;
+        for waypoint in &track.waypoints {
+            gpx.write_waypoint(waypoint, &[])?;
+        }
+        for route in &track.routes {
+            gpx.write_route(route)?;
+        }
+
+        let mut current_track : Option<usize> = None;
+        for segment in segments.iter() {
+            if current_track != Some(segment.track_index) {
+                gpx.start_track(&track.tracks.get(segment.track_index).cloned().unwrap_or_default())?;
+                current_track = Some(segment.track_index);
+            }
+            gpx.start_segment()?;
+            for index in segment.start..segment.end() {
+                let point = &track.track[index];
+                let time = point.delta_seconds.and_then(|delta_seconds| time_parser::offset_time(track.start_time, delta_seconds));
+                gpx.write_track_point(point, time, track.sensors.get(index), &[])?;
+            }
+        }
+        gpx.finish()
+    };
+
+    write().map_err(|err| ServiceError::io_error(err))
+}
+
+/*
+    Writes the (cleaned or resampled) @rider_track converted back to WGS84 as GPX to @writer, one <trkseg> per segment
+    Throws:
+    CoordinateConversionError if any point can not be converted back to WGS84,
+    IOError if the @writer fails
+*/
+pub fn rider_to_gpx<W : Write>(writer : W, rider_track : &RiderTrack) -> Result<W, ServiceError> {
+    let points = geo_conversions::local_to_spatial(&rider_track.track, &rider_track.track_origin, &rider_track.projection)?;
+    let segments = TrackSegment::or_single(&rider_track.segments, points.len());
+
+    let track_name = format!("{}_{}", rider_track.rider_uuid, rider_track.variant);
+    let metadata = TrackMetadata { name : Some(track_name.clone()), time : Some(rider_track.start_time), ..TrackMetadata::default() };
+
+    let write = || -> Result<W, IOError> {
+        let mut gpx = GpxWriter::start(writer, &track_name, &metadata)?;
+        gpx.start_track(&TrackInfo { name: Some(track_name.clone()), ..TrackInfo::default() })?;
+        for segment in segments.iter() {
+            gpx.start_segment()?;
+            for index in segment.start..segment.end() {
+                let point = &points[index];
+                let time = point.delta_seconds.and_then(|delta_seconds| time_parser::offset_time(rider_track.start_time, delta_seconds));
+                gpx.write_track_point(point, time, rider_track.sensors.get(index), &[])?;
+            }
+        }
+        gpx.finish()
+    };
+
+    write().map_err(|err| ServiceError::io_error(err))
+}
+
+/*
+    Writes the @reference_track converted back to WGS84 as GPX to @writer, the official version of a course.
+    Checkpoints are written as waypoints, every point carries its distance along the reference (look at along_distances) as a gpsa:distance extension.
+    Throws:
+    CoordinateConversionError if any point can not be converted back to WGS84,
+    IOError if the @writer fails
+*/
+pub fn reference_to_gpx<W : Write>(writer : W, reference_track : &ReferenceTrack) -> Result<W, ServiceError> {
+    let points = geo_conversions::local_to_spatial(&reference_track.track, &reference_track.origin, &reference_track.projection)?;
+    let segments = TrackSegment::or_single(&reference_track.segments, points.len());
+    let distances = along_distances(&points, &segments);
+
+    let checkpoint_points : Vec<RefPoint> = reference_track.checkpoints.iter()
+        .map(|checkpoint| RefPoint { x: checkpoint.x, y: checkpoint.y, z: checkpoint.z, has_elevation: checkpoint.has_elevation, total_distance: checkpoint.total_distance })
+        .collect();
+    let checkpoint_positions = geo_conversions::local_to_spatial(&checkpoint_points, &reference_track.origin, &reference_track.projection)?;
+
+    let metadata = TrackMetadata { name : Some(reference_track.class.clone()), ..TrackMetadata::default() };
+
+    let write = || -> Result<W, IOError> {
+        let mut gpx = GpxWriter::start(writer, &reference_track.class, &metadata)?;
+        for (checkpoint, position) in reference_track.checkpoints.iter().zip(&checkpoint_positions) {
+            let waypoint = Waypoint {
+                info : TrackInfo { name: Some(checkpoint.name.clone()), description: checkpoint.description.clone(), kind: None },
+                point : SpatialPoint { delta_seconds: None, ..*position }
+            };
+            gpx.write_waypoint(&waypoint, &[("distance", format!("{:.1}", checkpoint_distance(&distances, checkpoint.reference_index)))])?;
+        }
+
+        gpx.start_track(&TrackInfo { name: Some(reference_track.class.clone()), ..TrackInfo::default() })?;
+        for segment in segments.iter() {
+            gpx.start_segment()?;
+            for index in segment.start..segment.end() {
+                let distance = format!("{:.1}", distances[index]);
+                gpx.write_track_point(&points[index], None, None, &[("distance", distance)])?;
+            }
+        }
+        gpx.finish()
+    };
+
+    write().map_err(|err| ServiceError::io_error(err))
+}
+
+/*
+    Great circle distance in meters from the start along the WGS84 @points, it does not grow across the breaks between @segments.
+    Exported distances do not depend on the projection the track was processed in, unlike RefPoint.total_distance
+*/
+fn along_distances(points : &[SpatialPoint], segments : &[TrackSegment]) -> Vec<f64> {
+    let mut distances = vec![0.0; points.len()];
+    let mut total_distance = 0.0;
+    for segment in segments {
+        for index in segment.start..segment.end() {
+            if index > segment.start {
+                total_distance += geo_conversions::haversine_distance(&points[index - 1], &points[index]);
+            }
+            distances[index] = total_distance;
+        }
+    }
+    distances
+}
+
+fn checkpoint_distance(distances : &[f64], reference_index : u32) -> f64 {
+    distances.get(reference_index as usize).copied().unwrap_or(0.0)
+}
+
+fn info_properties(info : &TrackInfo, role : &str) -> Map<String, Value> {
+    let mut properties = Map::new();
+    properties.insert("role".to_string(), json!(role));
+    for (key, value) in [("name", &info.name), ("description", &info.description), ("kind", &info.kind)] {
+        if let Some(value) = value {
+            properties.insert(key.to_string(), json!(value));
+        }
+    }
+    properties
+}
+
+fn origin_value(origin : &TrackOrigin) -> Value {
+    json!([origin.epsg_x, origin.epsg_y])
+}
@@ -0,0 +1,198 @@
+use rayon::prelude::*;
+
+use crate::internal::{model::{config::outlier::{OutlierConfig, OutlierRepair}, spatial::points::RiderPoint, track::{common::{TrackSegment, push_segment}, riders::RiderTrack}}, service::geo_conversions::planar_distance};
+
+/*
+    Parralel outlier filtering of multiple @riders tracks, for riders that were not filtered by track_processor::process_rider_track or snap_all.
+    Every track uses the preset of its sport (OutlierConfig::for_sport), @default_config if the sport is missing or unknown.
+    Returns the outliers of every track in the order of @riders, look at filter_outliers
+*/
+pub fn filter_all(riders : &mut [RiderTrack], default_config : &OutlierConfig) -> Vec<Vec<usize>> {
+    riders.par_iter_mut()
+    .map(|rider| filter_for_sport(rider, default_config))
+    .collect()
+}
+
+/*
+    Filters the outliers of @rider_track with the preset of its sport (OutlierConfig::for_sport), @default_config if the sport is missing or unknown.
+    Returns the indices (before filtering) of the outliers, look at filter_outliers
+*/
+pub fn filter_for_sport(rider_track : &mut RiderTrack, default_config : &OutlierConfig) -> Vec<usize> {
+    let config = rider_track.sport.as_deref()
+        .and_then(OutlierConfig::for_sport)
+        .unwrap_or(*default_config);
+    filter_outliers(rider_track, &config)
+}
+
+/*
+    Removes or repairs (look at OutlierRepair) the points of @rider_track that imply an impossible speed or acceleration for @config,
+    mostly single point spikes caused by multipath in forests and cities.
+    Every point is compared to the last valid point of its segment, so the point following a spike is not an outlier itself.
+    Runs of more than max_outlier_run outliers are a teleport : the points are kept and start a new segment, snapping never joins them to the points before.
+    Points without time (or recorded at the time of the last valid point) can not be judged and are always kept.
+    The sensor values and segments of @rider_track are kept aligned with its points.
+    Returns the indices (before filtering) of the outliers
+*/
+pub fn filter_outliers(rider_track : &mut RiderTrack, config : &OutlierConfig) -> Vec<usize> {
+    let segments = TrackSegment::or_single(&rider_track.segments, rider_track.track.len());
+    let mut outlier = vec![false; rider_track.track.len()];
+    let mut filtered_segments = Vec::with_capacity(segments.len());
+
+    for segment in segments.iter() {
+        let starts = detect_outliers(&rider_track.track, segment, config, &mut outlier);
+        let ends = starts.iter().skip(1).copied().chain([segment.end()]);
+        for (start, end) in starts.iter().copied().zip(ends) {
+            push_segment(&mut filtered_segments, start, end, segment.track_index);
+        }
+    }
+
+    let outliers : Vec<usize> = outlier.iter()
+        .enumerate()
+        .filter_map(|(index, is_outlier)| is_outlier.then_some(index))
+        .collect();
+    if outliers.is_empty() && filtered_segments.len() == segments.len() {
+        return outliers;
+    }
+
+    match config.get_repair() {
+        OutlierRepair::Remove => remove_points(rider_track, &outlier, &filtered_segments),
+        OutlierRepair::Interpolate => {
+            interpolate_points(&mut rider_track.track, &outlier, &filtered_segments);
+            rider_track.segments = filtered_segments;
+        }
+    }
+    outliers
+}
+
+/*
+    Marks the outliers of @segment in @outlier, returns the start of every segment it is split into (teleports)
+*/
+fn detect_outliers(points : &[RiderPoint], segment : &TrackSegment, config : &OutlierConfig, outlier : &mut [bool]) -> Vec<usize> {
+    let mut starts = vec![segment.start];
+    let end = segment.end();
+    if segment.count < 2 {
+        return starts;
+    }
+
+    let mut anchor = segment.start;
+    // A spike on the first point would make every following point look impossible
+    if segment.count >= 3
+        && is_too_fast(&points[anchor], &points[anchor + 1], config)
+        && is_too_fast(&points[anchor], &points[anchor + 2], config)
+        && !is_too_fast(&points[anchor + 1], &points[anchor + 2], config) {
+        outlier[anchor] = true;
+        anchor += 1;
+    }
+
+    let mut anchor_speed : Option<f32> = None;
+    let mut run_start : Option<usize> = None;
+    let mut index = anchor + 1;
+    while index < end {
+        let elapsed = (points[index].delta_seconds - points[anchor].delta_seconds) as f32;
+        let distance = planar_distance(&points[anchor], &points[index]);
+        let speed = if elapsed > 0.0 { Some(distance / elapsed) } else { None };
+
+        let is_outlier = distance > config.get_jitter_distance() && speed.is_some_and(|speed| {
+            speed > config.get_max_speed()
+                || anchor_speed.is_some_and(|anchor_speed| (speed - anchor_speed) / elapsed > config.get_max_acceleration())
+        });
+        if !is_outlier {
+            anchor = index;
+            anchor_speed = speed.or(anchor_speed);
+            run_start = None;
+            index += 1;
+            continue;
+        }
+
+        let start = *run_start.get_or_insert(index);
+        if index - start + 1 > config.get_max_outlier_run() {
+            // Teleport, the signal went on from somewhere else : the run is valid and judged again from its first point
+            outlier[start..=index].fill(false);
+            starts.push(start);
+            anchor = start;
+            anchor_speed = None;
+            run_start = None;
+            index = start + 1;
+            continue;
+        }
+        outlier[index] = true;
+        index += 1;
+    }
+
+    starts
+}
+
+fn is_too_fast(from : &RiderPoint, to : &RiderPoint, config : &OutlierConfig) -> bool {
+    let elapsed = (to.delta_seconds - from.delta_seconds) as f32;
+    let distance = planar_distance(from, to);
+    elapsed > 0.0 && distance > config.get_jitter_distance() && distance / elapsed > config.get_max_speed()
+}
+
+/*
+    Drops the @outlier points (and their sensor values) of @rider_track, @segments are rebuilt over the remaining points
+*/
+fn remove_points(rider_track : &mut RiderTrack, outlier : &[bool], segments : &[TrackSegment]) {
+    let has_sensors = rider_track.sensors.len() == rider_track.track.len();
+    let mut track = Vec::with_capacity(rider_track.track.len());
+    let mut sensors = Vec::with_capacity(rider_track.sensors.len());
+    let mut kept_segments = Vec::with_capacity(segments.len());
+
+    for segment in segments {
+        let start = track.len();
+        for index in segment.start..segment.end() {
+            if outlier[index] {
+                continue;
+            }
+            track.push(rider_track.track[index]);
+            if has_sensors {
+                sensors.push(rider_track.sensors[index]);
+            }
+        }
+        push_segment(&mut kept_segments, start, track.len(), segment.track_index);
+    }
+
+    rider_track.track = track;
+    rider_track.sensors = sensors;
+    rider_track.segments = kept_segments;
+}
+
+/*
+    Moves every @outlier point between the valid points around it in its segment, by time (by index if they share the same time).
+    Outliers at the ends of a segment take the position of the closest valid point
+*/
+fn interpolate_points(points : &mut [RiderPoint], outlier : &[bool], segments : &[TrackSegment]) {
+    for segment in segments {
+        let mut previous : Option<usize> = None;
+        let mut index = segment.start;
+        while index < segment.end() {
+            if !outlier[index] {
+                previous = Some(index);
+                index += 1;
+                continue;
+            }
+
+            let run_end = (index..segment.end()).find(|&candidate| !outlier[candidate]).unwrap_or(segment.end());
+            let next = (run_end < segment.end()).then_some(run_end);
+            for repaired in index..run_end {
+                let (from, to, fraction) = match (previous, next) {
+                    (Some(from), Some(to)) => {
+                        let span = points[to].delta_seconds - points[from].delta_seconds;
+                        let fraction = if span > 0.0 {
+                            ((points[repaired].delta_seconds - points[from].delta_seconds) / span).clamp(0.0, 1.0) as f32
+                        } else {
+                            (repaired - from) as f32 / (to - from) as f32
+                        };
+                        (points[from], points[to], fraction)
+                    }
+                    (Some(closest), None) | (None, Some(closest)) => (points[closest], points[closest], 0.0),
+                    (None, None) => break
+                };
+                let point = &mut points[repaired];
+                point.x = from.x + (to.x - from.x) * fraction;
+                point.y = from.y + (to.y - from.y) * fraction;
+                point.z = from.z + (to.z - from.z) * fraction;
+            }
+            index = run_end;
+        }
+    }
+}
@@ -2,44 +2,107 @@ use std::path::Path;
 
 use uuid::Uuid;
 
-use crate::{errors::service_errors::ServiceError, internal::{io::track_loader, model::{config::{coordinates::CoordinatesConfig, snapping::SnappingConfig}, spatial::grid::Grid, track::{common::TrackOrigin, reference::ReferenceTrack, riders::{MatchedTrack, RiderTrack}}}, service::{geo_conversions, snapping::snap}}};
+use crate::{errors::service_errors::ServiceError, internal::{io::track_loader, model::{config::{coordinates::CoordinatesConfig, gaps::GapConfig, outlier::OutlierConfig, snapping::SnappingConfig}, spatial::{grid::Grid, points::SpatialPoint, projection::{ProjectionChoice, ProjectionKind}}, track::{common::{SpatialRoute, SpatialTrack, TrackOrigin, TrackSegment}, reference::ReferenceTrack, riders::{MatchedTrack, RiderTrack}}}, service::{gap_detection, geo_conversions, projection_selection, snapping::snap, track_filter}}};
 
 
 // FIXME class_name should not be here, it should not be sored in ReferenceTrack, we should have a separate structure that composes a reference track and holds metadata about it!
 // FIXME organisational related data about tracks and other things should not be part of the internal track analysis, they differ from ogranisation to organisation
 /*
     Generate a ReferenceTrack from a file found at @track_path.
+    The recorded track of the file is used if present, otherwise its routes are joined into the reference (one segment per route).
+    Named waypoints of the file become checkpoints of the reference.
     Throws: 
     ServiceError if spatial conversion fails,
+    if the file holds neither a track nor a route
     IOError if file is not found
     if file contains errors
 */
 pub fn process_reference_track(track_path : &Path, class_name : &str, origin_space: &str, destination_space : &str) -> Result<ReferenceTrack, ServiceError> {
-    let loaded_track = track_loader::load_track(track_path).map_err(
+    let mut loaded_track = track_loader::load_track(track_path).map_err(
         |err| {ServiceError::io_error(err)}
     )?;
     
     // Conversion Settings
     let conv_config = CoordinatesConfig::new(origin_space.to_string(), destination_space.to_string());
 
-    let (track_origin, converted_track) = geo_conversions::spatial_to_reference(&loaded_track.track, &conv_config)?;
+    let (points, segments) = reference_points(&mut loaded_track);
+    build_reference(&loaded_track, points, segments, class_name, &conv_config)
+}
+
+/*
+    Generate a ReferenceTrack from the WGS84 file found at @track_path in a metric projection selected from its location,
+    @kind forces the kind of projection (look at projection_selection::select_projection).
+    Riders compared to the reference have to be processed in its projection (ReferenceTrack.projection).
+    Returns the reference and the selected projection with its warnings
+    Throws: the errors of process_reference_track
+*/
+pub fn process_reference_track_auto(track_path : &Path, class_name : &str, kind : Option<ProjectionKind>) -> Result<(ReferenceTrack, ProjectionChoice), ServiceError> {
+    let mut loaded_track = track_loader::load_track(track_path).map_err(
+        |err| {ServiceError::io_error(err)}
+    )?;
+
+    let (points, segments) = reference_points(&mut loaded_track);
+    let projection = projection_selection::select_projection(&points, kind)?;
+    let conv_config = projection.to_config(geo_conversions::WGS84);
+
+    let reference = build_reference(&loaded_track, points, segments, class_name, &conv_config)?;
+    Ok((reference, projection))
+}
+
+/*
+    Takes the recorded points of @loaded_track with their segments, or its routes joined if it holds no recorded point
+*/
+fn reference_points(loaded_track : &mut SpatialTrack) -> (Vec<SpatialPoint>, Vec<TrackSegment>) {
+    if !loaded_track.track.is_empty() {
+        (std::mem::take(&mut loaded_track.track), std::mem::take(&mut loaded_track.segments))
+    } else {
+        routes_to_points(&loaded_track.routes)
+    }
+}
+
+fn build_reference(loaded_track : &SpatialTrack, points : Vec<SpatialPoint>, segments : Vec<TrackSegment>, class_name : &str, conv_config : &CoordinatesConfig) -> Result<ReferenceTrack, ServiceError> {
+    let destination_space = conv_config.get_destination_space();
+    let (track_origin, converted_track) = geo_conversions::spatial_to_reference(&points, &segments, conv_config)?;
+    let checkpoints = geo_conversions::spatial_to_checkpoints(&loaded_track.waypoints, &converted_track, &track_origin, conv_config)?;
 
     Ok(ReferenceTrack{
         class : class_name.to_string(),
         projection : destination_space.to_string(),
         track : converted_track,
-        origin : track_origin
+        origin : track_origin,
+        segments : segments,
+        checkpoints : checkpoints
     })
 }
 
 /*
-    Generates a RiderTrack from a file found at @track_path 
+    Joins the points of all @routes into a single point list, every non empty route becomes a segment
+*/
+fn routes_to_points(routes : &[SpatialRoute]) -> (Vec<SpatialPoint>, Vec<TrackSegment>) {
+    let mut points = Vec::new();
+    let mut segments = Vec::new();
+
+    for (route_index, route) in routes.iter().enumerate() {
+        if route.points.is_empty() {
+            continue;
+        }
+
+        segments.push(TrackSegment { start: points.len(), count: route.points.len(), track_index: route_index });
+        points.extend_from_slice(&route.points);
+    }
+
+    (points, segments)
+}
+
+/*
+    Generates a RiderTrack from a file found at @track_path.
+    With an @outlier_config its outliers are filtered with the preset of its sport, @outlier_config if the sport is unknown (look at track_filter::filter_for_sport)
     Throws: 
     ServiceError if spatial conversion fails,
     IOError if file is not found
     if file contains errors
 */
-pub fn process_rider_track(track_path : &Path, rider_uuid : Uuid, variant : u32, origin_space: &str, destination_space : &str, origin : &TrackOrigin) -> Result<RiderTrack, ServiceError> {
+pub fn process_rider_track(track_path : &Path, rider_uuid : Uuid, variant : u32, origin_space: &str, destination_space : &str, origin : &TrackOrigin, outlier_config : Option<&OutlierConfig>) -> Result<RiderTrack, ServiceError> {
 
     let loaded_track = track_loader::load_track(track_path)
         .map_err( |err| {ServiceError::io_error(err)})?;
@@ -48,25 +111,33 @@ pub fn process_rider_track(track_path : &Path, rider_uuid : Uuid, variant : u32,
     
     let converted_track = geo_conversions::spatial_to_rider(&loaded_track.track, origin, &conv_config)?;
 
-    Ok(RiderTrack {
+    let mut rider_track = RiderTrack {
         rider_uuid,
         projection : destination_space.to_string(),
+        sport : loaded_track.tracks.iter().find_map(|info| info.kind.clone()),
         start_time : loaded_track.start_time,
         track : converted_track,
+        sensors : loaded_track.sensors,
         track_origin : origin.clone(),
-        variant : variant
-    })
+        variant : variant,
+        segments : loaded_track.segments
+    };
+    if let Some(outlier_config) = outlier_config {
+        track_filter::filter_for_sport(&mut rider_track, outlier_config);
+    }
+    Ok(rider_track)
 }
 
 /*
     Generates a MatchedTrack from a @rider_track with a @ref_track.
+    The recording gaps of the rider are detected with @gap_config before snapping and stored in the MatchedTrack, look at gap_detection::detect_gaps
     Throws: 
     ServiceError if spatial coordinates are in different spaces
     if tracks dont have the same origin,
     IOError if file is not found
     if file contains errors
 */
-pub fn snap_rider_track(rider_track : &RiderTrack, ref_track: &ReferenceTrack, grid : &Grid, snapping_config : &SnappingConfig) -> Result<MatchedTrack, ServiceError> {
+pub fn snap_rider_track(rider_track : &RiderTrack, ref_track: &ReferenceTrack, grid : &Grid, snapping_config : &SnappingConfig, gap_config : &GapConfig) -> Result<MatchedTrack, ServiceError> {
     if !rider_track.projection.eq_ignore_ascii_case(&ref_track.projection) {
         Err(ServiceError::track_snapping_error(format!("tracks : {}_{} and {} are not in the same space", &rider_track.rider_uuid, &rider_track.variant, &ref_track.class).as_str()))?
     }
@@ -76,23 +147,28 @@ pub fn snap_rider_track(rider_track : &RiderTrack, ref_track: &ReferenceTrack, g
     }
 
     let mut mapped_track = Vec::new();
+    let gap_ends = gap_detection::gap_ends(rider_track, gap_config);
 
-    snap(&rider_track.track,&ref_track.track, grid, &mut mapped_track, snapping_config);
+    snap(&rider_track.track, &rider_track.segments, &gap_ends, &ref_track.track, grid, &mut mapped_track, snapping_config);
 
-    Ok(MatchedTrack {
+    let mut matched_track = MatchedTrack {
         bound_uuid : rider_track.rider_uuid.clone(),
         variant : rider_track.variant.clone(),
         projection : ref_track.projection.clone(),
         start_time : rider_track.start_time,
         track : mapped_track,
-        track_origin : ref_track.origin
-    })
+        sensors : rider_track.sensors.clone(),
+        track_origin : ref_track.origin,
+        gaps : Vec::new()
+    };
+    gap_detection::detect_gaps(rider_track, &mut matched_track, ref_track, gap_config)?;
+    Ok(matched_track)
 
 }
 
 /*
     Generates a MatchedTrack from a @rider_track with a @ref_track BUT it tries to snap the @ref_track to the @rider_track
-    at the cost of losing data about the seconds in the track
+    at the cost of losing data about the seconds and the sensor values in the track
     Throws: 
     ServiceError if spatial coordinates are in different spaces
     if tracks dont have the same origin,
@@ -110,7 +186,7 @@ pub fn snap_rider_track_inverse(rider_track : &RiderTrack, ref_track: &Reference
 
     let mut mapped_track = Vec::new();
 
-    snap(&ref_track.track, &rider_track.track, grid, &mut mapped_track, snapping_config);
+    snap(&ref_track.track, &ref_track.segments, &[], &rider_track.track, grid, &mut mapped_track, snapping_config);
 
     Ok(MatchedTrack {
         bound_uuid : rider_track.rider_uuid.clone(),
@@ -118,7 +194,9 @@ pub fn snap_rider_track_inverse(rider_track : &RiderTrack, ref_track: &Reference
         projection : ref_track.projection.clone(),
         start_time : rider_track.start_time,
         track : mapped_track,
-        track_origin : ref_track.origin
+        sensors : Vec::new(),
+        track_origin : ref_track.origin,
+        gaps : Vec::new()
     })
 
 }
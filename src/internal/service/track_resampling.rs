@@ -0,0 +1,173 @@
+use crate::{errors::service_errors::ServiceError, internal::model::{spatial::points::{RefPoint, RiderPoint}, track::{common::{TrackSegment, push_segment}, reference::ReferenceTrack, riders::RiderTrack}}};
+
+// Most points of a resampled track, a step far too small for the track length is refused
+pub const MAX_SAMPLE_COUNT : usize = 1_000_000;
+
+/*
+    Resamples @rider_track to a point every @time_step seconds, starting at the first point of every segment.
+    Positions, elevations and sensor values are interpolated linearly between the recorded points, hdop and accuracy are taken from the closest one.
+    The last point of every segment is kept so the track still ends where it was recorded, segments without time are kept as recorded.
+    Throws: InvalidData if @time_step is not positive or the resampled track would have more than MAX_SAMPLE_COUNT points
+*/
+pub fn resample_rider(rider_track : &RiderTrack, time_step : f64) -> Result<RiderTrack, ServiceError> {
+    if !(time_step > 0.0 && time_step.is_finite()) {
+        return Err(ServiceError::invalid_data(format!("time step must be positive, got {}", time_step).as_str()));
+    }
+
+    let points = &rider_track.track;
+    let has_sensors = rider_track.sensors.len() == points.len();
+    let segments = TrackSegment::or_single(&rider_track.segments, points.len());
+
+    let mut track = Vec::with_capacity(points.len());
+    let mut sensors = Vec::new();
+    let mut resampled_segments = Vec::with_capacity(segments.len());
+    for segment in segments.iter() {
+        let start = track.len();
+        let samples = sample_positions(&points[segment.start..segment.end()], time_step, MAX_SAMPLE_COUNT - track.len(), |point| point.delta_seconds)?;
+        for (offset, fraction, time) in samples {
+            let index = segment.start + offset;
+            let next = (index + 1).min(segment.end() - 1);
+            track.push(interpolate_rider(&points[index], &points[next], fraction as f32, time));
+            if has_sensors {
+                sensors.push(rider_track.sensors[index].interpolate(&rider_track.sensors[next], fraction as f32));
+            }
+        }
+        push_segment(&mut resampled_segments, start, track.len(), segment.track_index);
+    }
+
+    Ok(RiderTrack {
+        track,
+        sensors,
+        segments : resampled_segments,
+        ..rider_track.clone()
+    })
+}
+
+/*
+    Resamples @reference_track to a point every @spacing meters along total_distance, starting at the first point of every segment.
+    Positions and elevations are interpolated linearly, the last point of every segment is kept so the course ends at its finish.
+    Checkpoints are bound again to the resampled point closest along the track.
+    Throws: InvalidData if @spacing is not positive or the resampled track would have more than MAX_SAMPLE_COUNT points
+*/
+pub fn resample_reference(reference_track : &ReferenceTrack, spacing : f32) -> Result<ReferenceTrack, ServiceError> {
+    if !(spacing > 0.0 && spacing.is_finite()) {
+        return Err(ServiceError::invalid_data(format!("spacing must be positive, got {}", spacing).as_str()));
+    }
+
+    let points = &reference_track.track;
+    let segments = TrackSegment::or_single(&reference_track.segments, points.len());
+
+    let mut track = Vec::with_capacity(points.len());
+    let mut resampled_segments = Vec::with_capacity(segments.len());
+    for segment in segments.iter() {
+        let start = track.len();
+        let samples = sample_positions(&points[segment.start..segment.end()], spacing as f64, MAX_SAMPLE_COUNT - track.len(), |point| point.total_distance as f64)?;
+        for (offset, fraction, distance) in samples {
+            let index = segment.start + offset;
+            let next = (index + 1).min(segment.end() - 1);
+            track.push(interpolate_reference(&points[index], &points[next], fraction as f32, distance as f32));
+        }
+        push_segment(&mut resampled_segments, start, track.len(), segment.track_index);
+    }
+
+    let checkpoints = reference_track.checkpoints.iter()
+        .map(|checkpoint| {
+            let mut checkpoint = checkpoint.clone();
+            if let Some(index) = closest_along(&track, checkpoint.total_distance) {
+                let point = &track[index];
+                checkpoint.reference_index = index as u32;
+                checkpoint.total_distance = point.total_distance;
+                checkpoint.offset = (checkpoint.x - point.x).hypot(checkpoint.y - point.y);
+            }
+            checkpoint
+        })
+        .collect();
+
+    Ok(ReferenceTrack {
+        track,
+        segments : resampled_segments,
+        checkpoints,
+        ..reference_track.clone()
+    })
+}
+
+/*
+    Places a sample every @step along the non decreasing @key of the recorded @points, plus the last point.
+    Every sample is (index of the recorded point before it, fraction of the way to the next point, key of the sample).
+    Points whose key does not grow are returned as recorded
+    Throws: InvalidData if there would be more than @max_samples samples
+*/
+fn sample_positions<T>(points : &[T], step : f64, max_samples : usize, key : impl Fn(&T) -> f64) -> Result<Vec<(usize, f64, f64)>, ServiceError> {
+    let too_many = || ServiceError::invalid_data(format!("resampled track would have more than {} points", MAX_SAMPLE_COUNT).as_str());
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return Ok(Vec::new());
+    };
+    let (first_key, last_key) = (key(first), key(last));
+    if last_key <= first_key {
+        if points.len() > max_samples {
+            return Err(too_many());
+        }
+        return Ok(points.iter().enumerate().map(|(index, point)| (index, 0.0, key(point))).collect());
+    }
+
+    let sample_count = ((last_key - first_key) / step).ceil() + 1.0;
+    if !sample_count.is_finite() || sample_count > max_samples as f64 {
+        return Err(too_many());
+    }
+
+    let mut samples = Vec::with_capacity(sample_count as usize);
+    let mut index = 0;
+    let mut sample = 0usize;
+    loop {
+        // Computed from the start every time so rounding errors do not pile up
+        let sample_key = first_key + sample as f64 * step;
+        if sample_key >= last_key {
+            break;
+        }
+        while index + 2 < points.len() && key(&points[index + 1]) <= sample_key {
+            index += 1;
+        }
+        let (from, to) = (key(&points[index]), key(&points[index + 1]));
+        let fraction = if to > from { ((sample_key - from) / (to - from)).clamp(0.0, 1.0) } else { 0.0 };
+        samples.push((index, fraction, sample_key));
+        sample += 1;
+    }
+    samples.push((points.len() - 1, 0.0, last_key));
+    Ok(samples)
+}
+
+fn interpolate_rider(from : &RiderPoint, to : &RiderPoint, fraction : f32, delta_seconds : f64) -> RiderPoint {
+    let closest = if fraction < 0.5 { from } else { to };
+    RiderPoint {
+        x : from.x + (to.x - from.x) * fraction,
+        y : from.y + (to.y - from.y) * fraction,
+        z : from.z + (to.z - from.z) * fraction,
+        has_elevation : from.has_elevation && to.has_elevation,
+        delta_seconds,
+        hdop : closest.hdop,
+        accuracy : closest.accuracy
+    }
+}
+
+fn interpolate_reference(from : &RefPoint, to : &RefPoint, fraction : f32, total_distance : f32) -> RefPoint {
+    RefPoint {
+        x : from.x + (to.x - from.x) * fraction,
+        y : from.y + (to.y - from.y) * fraction,
+        z : from.z + (to.z - from.z) * fraction,
+        has_elevation : from.has_elevation && to.has_elevation,
+        total_distance
+    }
+}
+
+/*
+    Index of the point of @track whose total_distance is the closest to @total_distance
+*/
+fn closest_along(track : &[RefPoint], total_distance : f32) -> Option<usize> {
+    let after = track.partition_point(|point| point.total_distance < total_distance);
+    let candidates = [after.checked_sub(1), (after < track.len()).then_some(after)];
+    candidates.into_iter()
+        .flatten()
+        .min_by(|&left, &right| {
+            (track[left].total_distance - total_distance).abs().total_cmp(&(track[right].total_distance - total_distance).abs())
+        })
+}
@@ -0,0 +1,196 @@
+use std::{cmp::{Ordering, Reverse}, collections::BinaryHeap};
+
+use crate::internal::{model::{config::simplification::{SimplificationAlgorithm, SimplificationConfig}, spatial::points::{Point, SpatialPoint}, track::common::TrackSegment}, service::geo_conversions::EARTH_RADIUS};
+
+// Point of the Visvalingam heap, the smallest area comes out first
+#[derive(PartialEq)]
+struct AreaCandidate {
+    area : f64,
+    index : usize
+}
+
+impl Eq for AreaCandidate {}
+
+impl PartialOrd for AreaCandidate {
+    fn partial_cmp(&self, other : &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AreaCandidate {
+    fn cmp(&self, other : &Self) -> Ordering {
+        self.area.total_cmp(&other.area).then(self.index.cmp(&other.index))
+    }
+}
+
+/*
+    Simplifies the projected @points (RefPoint, RiderPoint...) using @config, only x and y are considered.
+    The first and last point of every one of @segments are always kept, segments are simplified independently.
+    Returns the sorted indices of the kept points, they map the simplified track back to @points (look at select and select_segments)
+*/
+pub fn simplify<T : Point>(points : &[T], segments : &[TrackSegment], config : &SimplificationConfig) -> Vec<usize> {
+    let positions : Vec<(f64, f64)> = points.iter()
+        .map(|point| (point.x() as f64, point.y() as f64))
+        .collect();
+    simplify_positions(&positions, segments, config)
+}
+
+/*
+    Simplifies the WGS84 @points using @config, look at simplify.
+    Distances are measured on an equirectangular projection centered on the track, precise enough for tolerances of a few meters
+*/
+pub fn simplify_spatial(points : &[SpatialPoint], segments : &[TrackSegment], config : &SimplificationConfig) -> Vec<usize> {
+    let Some(first) = points.first() else {
+        return Vec::new();
+    };
+    let mean_lat = points.iter().map(|point| point.lat).sum::<f64>() / points.len() as f64;
+    let meters_per_degree = EARTH_RADIUS.to_radians();
+    let lon_scale = meters_per_degree * mean_lat.to_radians().cos();
+
+    let positions : Vec<(f64, f64)> = points.iter()
+        .map(|point| {
+            // Tracks crossing the antimeridian stay continuous
+            let lon_offset = (point.lon - first.lon + 540.0).rem_euclid(360.0) - 180.0;
+            (lon_offset * lon_scale, (point.lat - first.lat) * meters_per_degree)
+        })
+        .collect();
+    simplify_positions(&positions, segments, config)
+}
+
+/*
+    Returns the @items (points, sensor values, matched points...) at the @kept indices
+*/
+pub fn select<T : Clone>(items : &[T], kept : &[usize]) -> Vec<T> {
+    kept.iter()
+        .filter_map(|&index| items.get(index).cloned())
+        .collect()
+}
+
+/*
+    Returns the @segments of the original track expressed over the @kept points, segments left empty are dropped
+*/
+pub fn select_segments(segments : &[TrackSegment], kept : &[usize]) -> Vec<TrackSegment> {
+    let mut simplified = Vec::with_capacity(segments.len());
+    for segment in segments {
+        let start = kept.partition_point(|&index| index < segment.start);
+        let end = kept.partition_point(|&index| index < segment.end());
+        if end > start {
+            simplified.push(TrackSegment { start, count : end - start, track_index : segment.track_index });
+        }
+    }
+    simplified
+}
+
+/*
+    Returns the index in the simplified track of the point standing for @original_index,
+    the last kept point at or before it (ex: to draw a MatchPoint.reference_index on a simplified reference)
+*/
+pub fn simplified_index(kept : &[usize], original_index : usize) -> usize {
+    kept.partition_point(|&index| index <= original_index).saturating_sub(1)
+}
+
+fn simplify_positions(positions : &[(f64, f64)], segments : &[TrackSegment], config : &SimplificationConfig) -> Vec<usize> {
+    let segments = TrackSegment::or_single(segments, positions.len());
+    let tolerance = config.get_tolerance().max(0.0);
+    let mut kept = Vec::new();
+
+    for segment in segments.iter() {
+        let Some(segment_positions) = positions.get(segment.start..segment.end()) else {
+            continue;
+        };
+        let keep = match config.get_algorithm() {
+            SimplificationAlgorithm::DouglasPeucker => douglas_peucker(segment_positions, tolerance),
+            SimplificationAlgorithm::Visvalingam => visvalingam(segment_positions, tolerance * tolerance)
+        };
+        kept.extend(keep.iter().enumerate().filter_map(|(offset, &keep)| keep.then_some(segment.start + offset)));
+    }
+    kept
+}
+
+/*
+    Marks the points further than @tolerance from the simplified line, iterative so long segments can not overflow the stack
+*/
+fn douglas_peucker(positions : &[(f64, f64)], tolerance : f64) -> Vec<bool> {
+    let count = positions.len();
+    let mut keep = vec![count <= 2; count];
+    if count <= 2 {
+        return keep;
+    }
+    keep[0] = true;
+    keep[count - 1] = true;
+
+    let mut ranges = vec![(0, count - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let farthest = (first + 1..last)
+            .map(|index| (index, segment_distance(positions[index], positions[first], positions[last])))
+            .max_by(|(_, left), (_, right)| left.total_cmp(right));
+        if let Some((index, _)) = farthest.filter(|(_, distance)| *distance > tolerance) {
+            keep[index] = true;
+            ranges.push((first, index));
+            ranges.push((index, last));
+        }
+    }
+    keep
+}
+
+/*
+    Removes the point with the smallest effective area until every remaining area is at least @min_area.
+    The area of a point never drops below the area of a point removed before it, so removals happen in a stable order
+*/
+fn visvalingam(positions : &[(f64, f64)], min_area : f64) -> Vec<bool> {
+    let count = positions.len();
+    let mut keep = vec![true; count];
+    if count <= 2 {
+        return keep;
+    }
+
+    let mut previous : Vec<usize> = (0..count).map(|index| index.saturating_sub(1)).collect();
+    let mut next : Vec<usize> = (0..count).map(|index| (index + 1).min(count - 1)).collect();
+    let mut areas = vec![f64::INFINITY; count];
+    let mut heap = BinaryHeap::with_capacity(count);
+    for index in 1..count - 1 {
+        areas[index] = triangle_area(positions[index - 1], positions[index], positions[index + 1]);
+        heap.push(Reverse(AreaCandidate { area : areas[index], index }));
+    }
+
+    while let Some(Reverse(AreaCandidate { area, index })) = heap.pop() {
+        // Stale entry, the area changed when a neighbour was removed
+        if !keep[index] || area.to_bits() != areas[index].to_bits() {
+            continue;
+        }
+        if area >= min_area {
+            break;
+        }
+
+        keep[index] = false;
+        let (before, after) = (previous[index], next[index]);
+        next[before] = after;
+        previous[after] = before;
+        for neighbour in [before, after] {
+            if neighbour == 0 || neighbour == count - 1 {
+                continue;
+            }
+            areas[neighbour] = triangle_area(positions[previous[neighbour]], positions[neighbour], positions[next[neighbour]]).max(area);
+            heap.push(Reverse(AreaCandidate { area : areas[neighbour], index : neighbour }));
+        }
+    }
+    keep
+}
+
+fn triangle_area(first : (f64, f64), second : (f64, f64), third : (f64, f64)) -> f64 {
+    ((second.0 - first.0) * (third.1 - first.1) - (third.0 - first.0) * (second.1 - first.1)).abs() / 2.0
+}
+
+/*
+    Distance from @point to the line segment between @start and @end
+*/
+fn segment_distance(point : (f64, f64), start : (f64, f64), end : (f64, f64)) -> f64 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length_squared = dx * dx + dy * dy;
+    let along = if length_squared > 0.0 {
+        (((point.0 - start.0) * dx + (point.1 - start.1) * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (point.0 - start.0 - along * dx).hypot(point.1 - start.1 - along * dy)
+}
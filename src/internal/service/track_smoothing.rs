@@ -0,0 +1,207 @@
+use rayon::prelude::*;
+
+use crate::internal::model::{config::smoothing::SmoothingConfig, spatial::points::RiderPoint, track::{common::TrackSegment, riders::RiderTrack}};
+
+// Speed uncertainty (m/s) at the first point of a segment, nothing is known about the initial motion
+const INITIAL_SPEED_ERROR : f64 = 30.0;
+
+type Matrix = [[f64; 2]; 2];
+
+/*
+    State of the constant velocity model at one point, (position, velocity) along each axis.
+    Both axes have the same dynamics and isotropic measurement errors so they share a single covariance
+*/
+#[derive(Clone, Copy)]
+struct KalmanState {
+    x : [f64; 2],
+    y : [f64; 2],
+    covariance : Matrix
+}
+
+/*
+    Parralel smoothing of multiple @riders tracks, look at smooth_track
+*/
+pub fn smooth_all(riders : &mut [RiderTrack], config : &SmoothingConfig) {
+    riders.par_iter_mut()
+    .for_each(|rider| smooth_track(rider, config));
+}
+
+/*
+    Smooths the horizontal positions of @rider_track with a constant velocity Kalman filter followed by a Rauch-Tung-Striebel backward pass.
+    Every point is weighted by its measurement error (look at SmoothingConfig), segments are smoothed independently.
+    Segments without time can not be smoothed and are left untouched, elevations, times and sensor values are never changed.
+    Meant to run between the outlier filter (track_filter) and snapping, a spike left in the track would be spread over its neighbours
+*/
+pub fn smooth_track(rider_track : &mut RiderTrack, config : &SmoothingConfig) {
+    let segments = TrackSegment::or_single(&rider_track.segments, rider_track.track.len());
+    for segment in segments.iter() {
+        smooth_segment(&mut rider_track.track[segment.start..segment.end()], config);
+    }
+}
+
+fn smooth_segment(points : &mut [RiderPoint], config : &SmoothingConfig) {
+    let Some(last) = points.last() else {
+        return;
+    };
+    if points.len() < 3 || last.delta_seconds <= points[0].delta_seconds {
+        return;
+    }
+    let acceleration_variance = (config.get_acceleration_noise() as f64).powi(2);
+
+    // Forward pass, the prediction of every point is kept for the backward pass
+    let mut state = KalmanState {
+        x : [points[0].x as f64, 0.0],
+        y : [points[0].y as f64, 0.0],
+        covariance : [[measurement_variance(&points[0], config), 0.0], [0.0, INITIAL_SPEED_ERROR * INITIAL_SPEED_ERROR]]
+    };
+    let mut predicted = Vec::with_capacity(points.len());
+    let mut filtered = Vec::with_capacity(points.len());
+    predicted.push(state);
+    filtered.push(state);
+    for index in 1..points.len() {
+        let elapsed = (points[index].delta_seconds - points[index - 1].delta_seconds).max(0.0);
+        let prediction = predict(&state, elapsed, acceleration_variance);
+        state = update(&prediction, &points[index], measurement_variance(&points[index], config));
+        predicted.push(prediction);
+        filtered.push(state);
+    }
+
+    // Backward pass, every filtered state is corrected with the smoothed state that follows it
+    let last_index = points.len() - 1;
+    let mut smoothed = filtered[last_index];
+    store_position(&mut points[last_index], &smoothed);
+    for index in (0..last_index).rev() {
+        let elapsed = (points[index + 1].delta_seconds - points[index].delta_seconds).max(0.0);
+        smoothed = smooth_step(&filtered[index], &predicted[index + 1], &smoothed, elapsed);
+        store_position(&mut points[index], &smoothed);
+    }
+}
+
+/*
+    Measurement variance of @point, from its accuracy, else its hdop, else the default error of @config
+*/
+fn measurement_variance(point : &RiderPoint, config : &SmoothingConfig) -> f64 {
+    let error = point.accuracy
+        .or_else(|| point.hdop.map(|hdop| hdop * config.get_hdop_error()))
+        .filter(|error| error.is_finite())
+        .unwrap_or(config.get_default_error())
+        .max(config.get_min_error()) as f64;
+    error * error
+}
+
+fn transition(elapsed : f64) -> Matrix {
+    [[1.0, elapsed], [0.0, 1.0]]
+}
+
+/*
+    Moves @state @elapsed seconds forward, the unknown acceleration adds the process noise of a white noise acceleration model
+*/
+fn predict(state : &KalmanState, elapsed : f64, acceleration_variance : f64) -> KalmanState {
+    let transition = transition(elapsed);
+    let process_noise = [
+        [elapsed.powi(3) / 3.0 * acceleration_variance, elapsed.powi(2) / 2.0 * acceleration_variance],
+        [elapsed.powi(2) / 2.0 * acceleration_variance, elapsed * acceleration_variance]
+    ];
+    KalmanState {
+        x : apply(&transition, state.x),
+        y : apply(&transition, state.y),
+        covariance : add(&multiply(&multiply(&transition, &state.covariance), &transpose(&transition)), &process_noise)
+    }
+}
+
+/*
+    Corrects the @prediction with the measured position of @point
+*/
+fn update(prediction : &KalmanState, point : &RiderPoint, variance : f64) -> KalmanState {
+    let covariance = &prediction.covariance;
+    let innovation_variance = covariance[0][0] + variance;
+    let gain = [covariance[0][0] / innovation_variance, covariance[1][0] / innovation_variance];
+    let correct = |axis : [f64; 2], measured : f64| {
+        let innovation = measured - axis[0];
+        [axis[0] + gain[0] * innovation, axis[1] + gain[1] * innovation]
+    };
+
+    KalmanState {
+        x : correct(prediction.x, point.x as f64),
+        y : correct(prediction.y, point.y as f64),
+        covariance : [
+            [covariance[0][0] - gain[0] * covariance[0][0], covariance[0][1] - gain[0] * covariance[0][1]],
+            [covariance[1][0] - gain[1] * covariance[0][0], covariance[1][1] - gain[1] * covariance[0][1]]
+        ]
+    }
+}
+
+/*
+    Rauch-Tung-Striebel step, combines the @filtered state of a point with the @next_smoothed state of the following point (@next_predicted from @filtered)
+*/
+fn smooth_step(filtered : &KalmanState, next_predicted : &KalmanState, next_smoothed : &KalmanState, elapsed : f64) -> KalmanState {
+    let Some(predicted_inverse) = inverse(&next_predicted.covariance) else {
+        return *filtered;
+    };
+    let gain = multiply(&multiply(&filtered.covariance, &transpose(&transition(elapsed))), &predicted_inverse);
+    let correct = |axis : [f64; 2], next_smoothed : [f64; 2], next_predicted : [f64; 2]| {
+        let correction = apply(&gain, [next_smoothed[0] - next_predicted[0], next_smoothed[1] - next_predicted[1]]);
+        [axis[0] + correction[0], axis[1] + correction[1]]
+    };
+    let covariance_difference = subtract(&next_smoothed.covariance, &next_predicted.covariance);
+
+    KalmanState {
+        x : correct(filtered.x, next_smoothed.x, next_predicted.x),
+        y : correct(filtered.y, next_smoothed.y, next_predicted.y),
+        covariance : add(&filtered.covariance, &multiply(&multiply(&gain, &covariance_difference), &transpose(&gain)))
+    }
+}
+
+fn store_position(point : &mut RiderPoint, state : &KalmanState) {
+    point.x = state.x[0] as f32;
+    point.y = state.y[0] as f32;
+}
+
+fn apply(matrix : &Matrix, vector : [f64; 2]) -> [f64; 2] {
+    [
+        matrix[0][0] * vector[0] + matrix[0][1] * vector[1],
+        matrix[1][0] * vector[0] + matrix[1][1] * vector[1]
+    ]
+}
+
+fn multiply(left : &Matrix, right : &Matrix) -> Matrix {
+    let mut product = [[0.0; 2]; 2];
+    for row in 0..2 {
+        for column in 0..2 {
+            product[row][column] = left[row][0] * right[0][column] + left[row][1] * right[1][column];
+        }
+    }
+    product
+}
+
+fn add(left : &Matrix, right : &Matrix) -> Matrix {
+    [
+        [left[0][0] + right[0][0], left[0][1] + right[0][1]],
+        [left[1][0] + right[1][0], left[1][1] + right[1][1]]
+    ]
+}
+
+fn subtract(left : &Matrix, right : &Matrix) -> Matrix {
+    [
+        [left[0][0] - right[0][0], left[0][1] - right[0][1]],
+        [left[1][0] - right[1][0], left[1][1] - right[1][1]]
+    ]
+}
+
+fn transpose(matrix : &Matrix) -> Matrix {
+    [[matrix[0][0], matrix[1][0]], [matrix[0][1], matrix[1][1]]]
+}
+
+/*
+    Returns None if @matrix is (numerically) singular, two points recorded at the same time without any uncertainty left
+*/
+fn inverse(matrix : &Matrix) -> Option<Matrix> {
+    let determinant = matrix[0][0] * matrix[1][1] - matrix[0][1] * matrix[1][0];
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    Some([
+        [matrix[1][1] / determinant, -matrix[0][1] / determinant],
+        [-matrix[1][0] / determinant, matrix[0][0] / determinant]
+    ])
+}
@@ -0,0 +1,176 @@
+use crate::internal::{io::time_parser, model::{config::validation::ValidationConfig, spatial::points::SpatialPoint, track::common::{SpatialTrack, TrackSegment, is_segment_break}, validation::{BoundingBox, IssueKind, IssueLevel, ValidationIssue, ValidationReport}}, service::geo_conversions};
+
+// Offending points of a single issue kind
+#[derive(Default)]
+struct IssueTally {
+    count : usize,
+    point_indices : Vec<usize>
+}
+
+impl IssueTally {
+    fn add(&mut self, index : usize, max_reported : usize) {
+        self.count += 1;
+        if self.point_indices.len() < max_reported {
+            self.point_indices.push(index);
+        }
+    }
+
+    /*
+        Pushes the issue to @issues if any point was counted, @message receives the count
+    */
+    fn report(self, issues : &mut Vec<ValidationIssue>, kind : IssueKind, level : IssueLevel, message : impl Fn(usize) -> String) {
+        if self.count == 0 {
+            return;
+        }
+        issues.push(ValidationIssue { kind, level, count : self.count, point_indices : self.point_indices, message : message(self.count) });
+    }
+}
+
+/*
+    Validates the recorded points of @track before analysis, routes and waypoints are only counted.
+    Consecutive points are only compared inside their segment, the breaks between segments are expected and never reported as gaps.
+    Time order is checked over the whole track. Look at ValidationConfig for the thresholds
+*/
+pub fn validate_track(track : &SpatialTrack, config : &ValidationConfig) -> ValidationReport {
+    let points = &track.track;
+    let segments = TrackSegment::or_single(&track.segments, points.len());
+    let max_reported = config.get_max_reported_points();
+
+    let mut invalid_coordinates = IssueTally::default();
+    let mut missing_timestamps = IssueTally::default();
+    let mut missing_elevations = IssueTally::default();
+    let mut non_monotonic_time = IssueTally::default();
+    let mut times_out_of_range = IssueTally::default();
+    let mut duplicates = IssueTally::default();
+    let mut implausible_speeds = IssueTally::default();
+    let mut time_gaps = IssueTally::default();
+    let mut distance_gaps = IssueTally::default();
+
+    let mut bounding_box : Option<BoundingBox> = None;
+    let mut total_distance = 0.0;
+    let mut last_time : Option<f64> = None;
+    let mut time_range : Option<(f64, f64)> = None;
+
+    for (index, point) in points.iter().enumerate() {
+        let valid_coordinates = has_valid_coordinates(point);
+        if valid_coordinates {
+            extend_bounding_box(&mut bounding_box, point);
+        } else {
+            invalid_coordinates.add(index, max_reported);
+        }
+        if point.elev.is_none() {
+            missing_elevations.add(index, max_reported);
+        }
+
+        match point.delta_seconds {
+            Some(time) => {
+                if last_time.is_some_and(|last_time| time < last_time) {
+                    non_monotonic_time.add(index, max_reported);
+                }
+                if time_parser::offset_time(track.start_time, time).is_none() {
+                    times_out_of_range.add(index, max_reported);
+                }
+                last_time = Some(time);
+                time_range = Some(time_range.map_or((time, time), |(first, last)| (first.min(time), last.max(time))));
+            }
+            None => missing_timestamps.add(index, max_reported)
+        }
+
+        if index == 0 || is_segment_break(&segments, index) || !valid_coordinates || !has_valid_coordinates(&points[index - 1]) {
+            continue;
+        }
+
+        let previous = &points[index - 1];
+        let distance = geo_conversions::haversine_distance(previous, point);
+        let elapsed = previous.delta_seconds.zip(point.delta_seconds).map(|(previous_time, time)| time - previous_time);
+        total_distance += distance;
+
+        if distance <= config.get_duplicate_distance() && elapsed.is_none_or(|elapsed| elapsed <= 0.0) {
+            duplicates.add(index, max_reported);
+        } else if let Some(elapsed) = elapsed.filter(|&elapsed| elapsed >= 0.0) {
+            // Moving without time passing is an infinite speed
+            if elapsed == 0.0 || distance / elapsed > config.get_max_speed() {
+                implausible_speeds.add(index, max_reported);
+            }
+            if elapsed > config.get_max_time_gap() {
+                time_gaps.add(index, max_reported);
+            }
+        }
+        if distance > config.get_max_distance_gap() {
+            distance_gaps.add(index, max_reported);
+        }
+    }
+
+    let mut issues = Vec::new();
+    if points.is_empty() {
+        issues.push(ValidationIssue {
+            kind : IssueKind::NoPoints,
+            level : IssueLevel::Error,
+            count : 0,
+            point_indices : Vec::new(),
+            message : "The file holds no recorded point".to_string()
+        });
+    }
+    invalid_coordinates.report(&mut issues, IssueKind::InvalidCoordinates, IssueLevel::Error,
+        |count| format!("{} points have a latitude or longitude out of range", count));
+    missing_timestamps.report(&mut issues, IssueKind::MissingTimestamps, IssueLevel::Warning,
+        |count| format!("{} of {} points have no timestamp", count, points.len()));
+    missing_elevations.report(&mut issues, IssueKind::MissingElevations, IssueLevel::Info,
+        |count| format!("{} of {} points have no elevation", count, points.len()));
+    non_monotonic_time.report(&mut issues, IssueKind::NonMonotonicTime, IssueLevel::Warning,
+        |count| format!("{} points are recorded before the point preceding them", count));
+    times_out_of_range.report(&mut issues, IssueKind::TimeOutOfRange, IssueLevel::Error,
+        |count| format!("{} points have a time out of the range of dates", count));
+    duplicates.report(&mut issues, IssueKind::DuplicatePoints, IssueLevel::Info,
+        |count| format!("{} points repeat the previous point", count));
+    implausible_speeds.report(&mut issues, IssueKind::ImplausibleSpeed, IssueLevel::Warning,
+        |count| format!("{} points are reached faster than {} m/s", count, config.get_max_speed()));
+    time_gaps.report(&mut issues, IssueKind::TimeGap, IssueLevel::Info,
+        |count| format!("{} gaps longer than {} s inside segments", count, config.get_max_time_gap()));
+    distance_gaps.report(&mut issues, IssueKind::DistanceGap, IssueLevel::Warning,
+        |count| format!("{} jumps longer than {} m inside segments", count, config.get_max_distance_gap()));
+
+    let point_time = |delta_seconds : f64| time_parser::offset_time(track.start_time, delta_seconds).map(|time| time.to_rfc3339());
+
+    ValidationReport {
+        valid : issues.iter().all(|issue| issue.level < IssueLevel::Error),
+        point_count : points.len(),
+        segment_count : segments.len(),
+        route_count : track.routes.len(),
+        waypoint_count : track.waypoints.len(),
+        start_time : time_range.and_then(|(first, _)| point_time(first)),
+        end_time : time_range.and_then(|(_, last)| point_time(last)),
+        time_span : time_range.map(|(first, last)| last - first),
+        total_distance,
+        bounding_box,
+        issues
+    }
+}
+
+fn has_valid_coordinates(point : &SpatialPoint) -> bool {
+    point.lat.is_finite() && point.lon.is_finite() && (-90.0..=90.0).contains(&point.lat) && (-180.0..=180.0).contains(&point.lon)
+}
+
+fn extend_bounding_box(bounding_box : &mut Option<BoundingBox>, point : &SpatialPoint) {
+    let elev = point.elev.filter(|elev| elev.is_finite());
+    let Some(bounds) = bounding_box else {
+        *bounding_box = Some(BoundingBox {
+            min_lon : point.lon,
+            min_lat : point.lat,
+            max_lon : point.lon,
+            max_lat : point.lat,
+            min_elev : elev,
+            max_elev : elev
+        });
+        return;
+    };
+
+    bounds.min_lon = bounds.min_lon.min(point.lon);
+    bounds.min_lat = bounds.min_lat.min(point.lat);
+    bounds.max_lon = bounds.max_lon.max(point.lon);
+    bounds.max_lat = bounds.max_lat.max(point.lat);
+    if let Some(elev) = elev {
+        bounds.min_elev = Some(bounds.min_elev.map_or(elev, |min_elev| min_elev.min(elev)));
+        bounds.max_elev = Some(bounds.max_elev.map_or(elev, |max_elev| max_elev.max(elev)));
+    }
+}